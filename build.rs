@@ -0,0 +1,151 @@
+// Generates Rust source for the OBJ files listed in EMBEDDED_MESHES so they can be compiled into the binary
+// with include_mesh!("name") instead of being pasted in by hand like glium_teapot.rs used to be.
+//
+// Each mesh becomes $OUT_DIR/meshes/<name>.rs holding VERTICES, NORMALS and INDICES constants.
+// The including module has to define the Vertex and Normal types, see src/glium_teapot.rs.
+
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+use std::process;
+
+// OBJ triangulation and normal generation are the crate's own, so embedded meshes come out like loaded ones.
+// Only parts of them are used here.
+#[allow(dead_code)]
+#[path = "src/math.rs"]
+mod math;
+#[allow(dead_code)]
+#[path = "src/normals.rs"]
+mod normals;
+#[allow(dead_code)]
+#[path = "src/obj_polygons.rs"]
+mod obj_polygons;
+
+// Stand-ins for the crate's Vertex and Mesh that normals.rs works on, with the same fields. normals.rs may only use what
+// is defined here: Vertex's fields and Copy, Mesh's fields, Default and triangle_count. Anything more fails this script
+// to compile, and the test in src/glium_teapot.rs checks that the embedded teapot still matches the loaded one.
+#[allow(dead_code)]
+#[derive(Copy, Clone, Debug)]
+struct Vertex {
+    position: [f32; 3],
+    color: [f32; 3],
+    normal: [f32; 3],
+    tex_coords: [f32; 2],
+    tangent: [f32; 4],
+}
+
+mod mesh {
+    #[derive(Clone, Debug, Default)]
+    pub struct Mesh {
+        pub vertices: Vec<crate::Vertex>,
+        pub indices: Vec<u32>,
+    }
+
+    impl Mesh {
+        pub fn triangle_count(&self) -> usize {
+            self.indices.len() / 3
+        }
+    }
+}
+
+// (name used with include_mesh!, path to the OBJ file)
+const EMBEDDED_MESHES: &[(&str, &str)] = &[
+    ("teapot", "models/obj/teapot.obj"),
+];
+
+// Values written per line, keeps the generated files readable without thousands of lines
+const VALUES_PER_LINE: usize = 8;
+
+// A broken asset fails the build with a message saying which one, rather than a panic from deep inside the script
+fn fail(message: String) -> ! {
+    println!("cargo:warning={}", message);
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
+
+fn main() {
+    let out_dir = Path::new(&env::var("OUT_DIR").unwrap_or_else(|_| fail(String::from("OUT_DIR is not set")))).join("meshes");
+    if let Err(err) = fs::create_dir_all(&out_dir) {
+        fail(format!("Could not create {}: {}", out_dir.display(), err));
+    }
+
+    for &(name, path) in EMBEDDED_MESHES {
+        println!("cargo:rerun-if-changed={}", path);
+        let source = generate_mesh_source(path).unwrap_or_else(|err| fail(format!("Could not embed {}: {}", path, err)));
+        let out_path = out_dir.join(format!("{}.rs", name));
+        if let Err(err) = fs::write(&out_path, source) {
+            fail(format!("Could not write {}: {}", out_path.display(), err));
+        }
+    }
+    println!("cargo:rerun-if-changed=build.rs");
+    for shared in ["src/math.rs", "src/normals.rs", "src/obj_polygons.rs"] {
+        println!("cargo:rerun-if-changed={}", shared);
+    }
+}
+
+fn generate_mesh_source(path: &str) -> Result<String, String> {
+    let file = fs::File::open(path).map_err(|err| err.to_string())?;
+    let raw = obj::raw::parse_obj(BufReader::new(file)).map_err(|err| err.to_string())?;
+
+    let (corners, indices) = obj_polygons::triangulate(&raw.polygons);
+    if corners.len() > u32::MAX as usize + 1 {
+        return Err(format!("{} vertices are more than a u32 index can address", corners.len()));
+    }
+    let out_of_range = || String::from("a face references a position, texture coordinate or normal the file does not contain");
+    let mut mesh = mesh::Mesh { vertices: Vec::with_capacity(corners.len()), indices: indices.into_iter().map(|index| index as u32).collect() };
+    for &(p, t, n) in &corners {
+        let position = raw.positions.get(p).ok_or_else(out_of_range)?;
+        if t.is_some_and(|t| t >= raw.tex_coords.len()) {
+            return Err(out_of_range());
+        }
+        let normal = match n {
+            Some(n) => raw.normals.get(n).ok_or_else(out_of_range)?,
+            None => &(0.0, 0.0, 0.0),
+        };
+        mesh.vertices.push(Vertex {
+            position: [position.0, position.1, position.2],
+            color: [1.0; 3],
+            normal: [normal.0, normal.1, normal.2],
+            tex_coords: [0.0; 2],
+            tangent: [0.0; 4],
+        });
+    }
+    // Same default as the model loader when the file has no `vn`
+    if !corners.iter().all(|corner| corner.2.is_some()) {
+        mesh = normals::generate_normals(&mesh, normals::NormalMode::default());
+    }
+    let vertices = mesh.vertices;
+    let indices = mesh.indices;
+
+    // Same rule as Mesh::index_buffer, u16 unless the mesh needs more
+    let index_type = if vertices.len() <= u16::MAX as usize + 1 { "u16" } else { "u32" };
+
+    let mut source = String::new();
+    writeln!(source, "// @generated by build.rs from {}, do not edit", path).unwrap();
+    writeln!(source).unwrap();
+
+    writeln!(source, "pub const VERTICES: [Vertex; {}] = [", vertices.len()).unwrap();
+    for Vertex { position, .. } in &vertices {
+        writeln!(source, "    Vertex {{ position: ({:?}, {:?}, {:?}) }},", position[0], position[1], position[2]).unwrap();
+    }
+    writeln!(source, "];").unwrap();
+    writeln!(source).unwrap();
+
+    writeln!(source, "pub const NORMALS: [Normal; {}] = [", vertices.len()).unwrap();
+    for Vertex { normal, .. } in &vertices {
+        writeln!(source, "    Normal {{ normal: ({:?}, {:?}, {:?}) }},", normal[0], normal[1], normal[2]).unwrap();
+    }
+    writeln!(source, "];").unwrap();
+    writeln!(source).unwrap();
+
+    writeln!(source, "pub const INDICES: [{}; {}] = [", index_type, indices.len()).unwrap();
+    for line in indices.chunks(VALUES_PER_LINE) {
+        let values: Vec<String> = line.iter().map(u32::to_string).collect();
+        writeln!(source, "    {},", values.join(", ")).unwrap();
+    }
+    writeln!(source, "];").unwrap();
+
+    Ok(source)
+}
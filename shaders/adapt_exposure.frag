@@ -0,0 +1,16 @@
+#version 150
+
+// Moves the adapted luminance a step towards the average of this frame, like eyes adjusting to a dark room
+
+uniform sampler2D u_luminance;
+uniform sampler2D u_previous;
+uniform float u_blend;
+
+out vec4 color;
+
+void main() {
+    // The smallest mip level holds the average of the whole image
+    float average = exp(textureLod(u_luminance, vec2(0.5), 20.0).r);
+    float previous = texture(u_previous, vec2(0.5)).r;
+    color = vec4(mix(previous, average, u_blend), 0.0, 0.0, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 150
+
+// Adds the blurred bright parts back onto the scene
+
+uniform sampler2D u_texture;
+uniform sampler2D u_bloom;
+uniform float u_intensity;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+void main() {
+    vec4 scene = texture(u_texture, v_tex_coords);
+    color = vec4(scene.rgb + texture(u_bloom, v_tex_coords).rgb * u_intensity, scene.a);
+}
@@ -0,0 +1,16 @@
+#version 150
+
+// Halves the resolution with four bilinear taps, each level of the chain is blurrier than the one before
+
+uniform sampler2D u_texture;
+uniform vec2 u_inverse_size;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+void main() {
+    color = 0.25 * (texture(u_texture, v_tex_coords + vec2(-1.0, -1.0) * u_inverse_size)
+                  + texture(u_texture, v_tex_coords + vec2(1.0, -1.0) * u_inverse_size)
+                  + texture(u_texture, v_tex_coords + vec2(-1.0, 1.0) * u_inverse_size)
+                  + texture(u_texture, v_tex_coords + vec2(1.0, 1.0) * u_inverse_size));
+}
@@ -0,0 +1,28 @@
+#version 150
+
+// First step of bloom: keeps only what is brighter than the threshold while halving the resolution.
+// The soft knee fades pixels in just below the threshold instead of cutting them off.
+
+uniform sampler2D u_texture;
+uniform vec2 u_inverse_size;
+uniform float u_threshold;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+const float KNEE = 0.5;
+
+void main() {
+    // Four bilinear taps cover the 4x4 source texels under this output texel
+    vec3 rgb = 0.25 * (texture(u_texture, v_tex_coords + vec2(-1.0, -1.0) * u_inverse_size).rgb
+                     + texture(u_texture, v_tex_coords + vec2(1.0, -1.0) * u_inverse_size).rgb
+                     + texture(u_texture, v_tex_coords + vec2(-1.0, 1.0) * u_inverse_size).rgb
+                     + texture(u_texture, v_tex_coords + vec2(1.0, 1.0) * u_inverse_size).rgb);
+
+    float brightness = max(rgb.r, max(rgb.g, rgb.b));
+    float knee = u_threshold * KNEE;
+    float soft = clamp(brightness - u_threshold + knee, 0.0, 2.0 * knee);
+    soft = soft * soft / (4.0 * knee + 0.0001);
+    float contribution = max(soft, brightness - u_threshold) / max(brightness, 0.0001);
+    color = vec4(rgb * contribution, 1.0);
+}
@@ -0,0 +1,22 @@
+#version 150
+
+// Spreads a smaller level of the chain over the next bigger one with a 3x3 tent filter.
+// The result is added onto that level by blending, so every level ends up with the blur of all smaller ones.
+
+uniform sampler2D u_texture;
+uniform vec2 u_inverse_size;
+// Tent size in source texels, wider spreads the glow further
+uniform float u_radius;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+void main() {
+    vec2 d = u_inverse_size * u_radius;
+    vec4 sum = 4.0 * texture(u_texture, v_tex_coords);
+    sum += 2.0 * (texture(u_texture, v_tex_coords + vec2(-d.x, 0.0)) + texture(u_texture, v_tex_coords + vec2(d.x, 0.0))
+                + texture(u_texture, v_tex_coords + vec2(0.0, -d.y)) + texture(u_texture, v_tex_coords + vec2(0.0, d.y)));
+    sum += texture(u_texture, v_tex_coords - d) + texture(u_texture, v_tex_coords + d)
+         + texture(u_texture, v_tex_coords + vec2(-d.x, d.y)) + texture(u_texture, v_tex_coords + vec2(d.x, -d.y));
+    color = vec4(sum.rgb / 16.0, 1.0);
+}
@@ -0,0 +1,10 @@
+#version 150
+
+uniform sampler2D u_texture;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+void main() {
+    color = texture(u_texture, v_tex_coords);
+}
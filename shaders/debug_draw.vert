@@ -0,0 +1,15 @@
+#version 150
+
+in vec3 position;
+in vec4 color;
+
+out vec4 v_color;
+
+uniform mat4 perspective;
+uniform mat4 view;
+uniform mat4 model;
+
+void main() {
+    v_color = color;
+    gl_Position = perspective * view * model * vec4(position, 1.0);
+}
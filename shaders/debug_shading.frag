@@ -0,0 +1,30 @@
+#version 150
+
+// Outputs one surface attribute as a color, u_mode follows render_mode::ShadingMode:
+// 1 view space normals, 2 texture coordinates (wrapped), 3 linear depth (near is white), 4 world position across the scene bounds
+
+uniform int u_mode;
+uniform vec2 u_depth_range;
+uniform vec3 u_bounds_min;
+uniform vec3 u_bounds_size;
+
+in vec3 v_normal;
+in vec2 v_tex_coords;
+in vec3 v_world_position;
+in float v_view_depth;
+out vec4 color;
+
+void main() {
+    vec3 result;
+    if (u_mode == 1) {
+        result = normalize(v_normal) * 0.5 + 0.5;
+    } else if (u_mode == 2) {
+        result = vec3(fract(v_tex_coords), 0.0);
+    } else if (u_mode == 3) {
+        float depth = (v_view_depth - u_depth_range.x) / max(u_depth_range.y - u_depth_range.x, 1e-6);
+        result = vec3(1.0 - clamp(depth, 0.0, 1.0));
+    } else {
+        result = clamp((v_world_position - u_bounds_min) / max(u_bounds_size, vec3(1e-6)), 0.0, 1.0);
+    }
+    color = vec4(result, 1.0);
+}
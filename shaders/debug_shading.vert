@@ -0,0 +1,27 @@
+#version 150
+
+in vec3 position;
+in vec3 normal;
+in vec2 tex_coords;
+
+out vec3 v_normal;
+out vec2 v_tex_coords;
+out vec3 v_world_position;
+out float v_view_depth;
+
+uniform mat4 perspective;
+uniform mat4 view;
+uniform mat4 model;
+
+void main() {
+    mat4 modelview = view * model;
+    // Same normal as teapot_gouraud.vert, in view space
+    v_normal = transpose(inverse(mat3(modelview))) * normal;
+    v_tex_coords = tex_coords;
+    vec4 world_position = model * vec4(position, 1.0);
+    v_world_position = world_position.xyz;
+    vec4 view_position = view * world_position;
+    // The viewer's view space looks down +z
+    v_view_depth = view_position.z;
+    gl_Position = perspective * view_position;
+}
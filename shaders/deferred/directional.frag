@@ -0,0 +1,24 @@
+#version 150
+
+// Ambient, the directional light and emission, the same lighting teapot_gouraud.frag does in the forward path
+
+uniform sampler2D u_albedo;
+uniform sampler2D u_normal;
+uniform sampler2D u_emissive;
+uniform sampler2D u_depth;
+uniform sampler2D u_occlusion;
+uniform vec3 u_light;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+void main() {
+    // Keep the background where nothing was drawn
+    if (texture(u_depth, v_tex_coords).r >= 1.0) {
+        discard;
+    }
+    vec3 regular_color = texture(u_albedo, v_tex_coords).rgb;
+    float brightness = max(dot(normalize(texture(u_normal, v_tex_coords).xyz), normalize(u_light)), 0.0);
+    vec3 dark_color = 0.6 * regular_color * texture(u_occlusion, v_tex_coords).r;
+    color = vec4(mix(dark_color, regular_color, brightness) + texture(u_emissive, v_tex_coords).rgb, 1.0);
+}
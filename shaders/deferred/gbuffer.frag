@@ -0,0 +1,32 @@
+#version 150
+
+// Geometry pass of the deferred path, used with teapot_gouraud.vert. Stores what the lighting passes need per pixel,
+// the view space position comes back from the depth buffer.
+
+uniform sampler2D tex;
+uniform vec4 u_base_color;
+uniform vec3 u_emissive;
+uniform float u_metallic;
+uniform float u_roughness;
+// Alpha masked materials drop the fragments below their cutoff, blended ones are drawn forward afterwards
+uniform float u_alpha_cutoff;
+
+in vec3 v_normal;
+in vec3 v_color;
+in vec2 v_tex_coords;
+
+out vec4 albedo;
+out vec4 normal;
+out vec4 material;
+out vec4 emissive;
+
+void main() {
+    vec4 texel = texture(tex, v_tex_coords);
+    if (u_base_color.a * texel.a < u_alpha_cutoff) {
+        discard;
+    }
+    albedo = vec4(u_base_color.rgb * v_color * texel.rgb, 1.0);
+    normal = vec4(normalize(v_normal), 1.0);
+    material = vec4(u_metallic, u_roughness, 0.0, 1.0);
+    emissive = vec4(u_emissive, 1.0);
+}
@@ -0,0 +1,39 @@
+#version 150
+
+// Shows one of the G-buffer targets instead of the lit image
+// u_mode: 1 albedo, 2 view space normals, 3 metallic (red) and roughness (green), 4 depth, 5 emission
+
+uniform sampler2D u_albedo;
+uniform sampler2D u_normal;
+uniform sampler2D u_material;
+uniform sampler2D u_emissive;
+uniform sampler2D u_depth;
+uniform mat4 u_inverse_projection;
+// View space depths mapped to white and black
+uniform vec2 u_depth_range;
+uniform int u_mode;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+void main() {
+    float depth = texture(u_depth, v_tex_coords).r;
+    if (depth >= 1.0) {
+        discard;
+    }
+    vec3 value;
+    if (u_mode == 1) {
+        value = texture(u_albedo, v_tex_coords).rgb;
+    } else if (u_mode == 2) {
+        value = normalize(texture(u_normal, v_tex_coords).xyz) * 0.5 + 0.5;
+    } else if (u_mode == 3) {
+        value = vec3(texture(u_material, v_tex_coords).rg, 0.0);
+    } else if (u_mode == 4) {
+        vec4 position = u_inverse_projection * vec4(vec3(v_tex_coords, depth) * 2.0 - 1.0, 1.0);
+        float z = position.z / position.w;
+        value = vec3(1.0 - clamp((z - u_depth_range.x) / max(u_depth_range.y - u_depth_range.x, 0.0001), 0.0, 1.0));
+    } else {
+        value = texture(u_emissive, v_tex_coords).rgb;
+    }
+    color = vec4(value, 1.0);
+}
@@ -0,0 +1,47 @@
+#version 150
+
+// One point light over the whole screen, added onto what the passes before it wrote.
+// Lambert diffuse plus Blinn-Phong specular, shinier for low roughness and tinted by the albedo for metals.
+
+uniform sampler2D u_albedo;
+uniform sampler2D u_normal;
+uniform sampler2D u_material;
+uniform sampler2D u_depth;
+uniform mat4 u_inverse_projection;
+// View space
+uniform vec3 u_light_position;
+uniform vec3 u_light_color;
+// Distance at which the light has faded out completely
+uniform float u_light_radius;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+void main() {
+    float depth = texture(u_depth, v_tex_coords).r;
+    if (depth >= 1.0) {
+        discard;
+    }
+    vec4 position = u_inverse_projection * vec4(vec3(v_tex_coords, depth) * 2.0 - 1.0, 1.0);
+    position.xyz /= position.w;
+
+    vec3 to_light = u_light_position - position.xyz;
+    float distance = length(to_light);
+    float falloff = clamp(1.0 - distance / u_light_radius, 0.0, 1.0);
+    falloff *= falloff;
+    if (falloff <= 0.0) {
+        discard;
+    }
+
+    vec3 albedo = texture(u_albedo, v_tex_coords).rgb;
+    vec3 normal = normalize(texture(u_normal, v_tex_coords).xyz);
+    vec2 material = texture(u_material, v_tex_coords).rg;
+    vec3 light_direction = to_light / distance;
+    vec3 half_vector = normalize(light_direction + normalize(-position.xyz));
+
+    float metallic = material.r;
+    float shininess = mix(256.0, 4.0, material.g);
+    vec3 diffuse = albedo * (1.0 - metallic) * max(dot(normal, light_direction), 0.0);
+    vec3 specular = mix(vec3(0.04), albedo, metallic) * pow(max(dot(normal, half_vector), 0.0), shininess);
+    color = vec4((diffuse + specular) * u_light_color * falloff, 1.0);
+}
@@ -0,0 +1,17 @@
+#version 150
+
+// Diagnostic colors: front faces blue, back faces red, lit a little so the shape stays readable.
+// gl_FrontFacing means counter-clockwise on screen, u_front_clockwise says the draw's transforms flipped that.
+
+uniform vec3 u_light;
+uniform bool u_front_clockwise;
+
+in vec3 v_normal;
+out vec4 color;
+
+void main() {
+    bool front = gl_FrontFacing != u_front_clockwise;
+    vec3 face_color = front ? vec3(0.2, 0.45, 1.0) : vec3(1.0, 0.15, 0.1);
+    float brightness = abs(dot(normalize(v_normal), normalize(u_light)));
+    color = vec4(face_color * (0.4 + 0.6 * brightness), 1.0);
+}
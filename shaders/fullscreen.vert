@@ -0,0 +1,12 @@
+#version 150
+
+// Shared by every fullscreen pass, covers the viewport with a quad and hands out texture coordinates for it
+
+in vec2 position;
+
+out vec2 v_tex_coords;
+
+void main() {
+    v_tex_coords = position * 0.5 + 0.5;
+    gl_Position = vec4(position, 0.0, 1.0);
+}
@@ -0,0 +1,48 @@
+#version 150
+
+// FXAA in the compact form of Timothy Lottes' original "FXAA 3.11 console" variant: finds the local edge
+// direction from the luma of the four diagonal neighbours and blurs along it, falling back to a shorter blur
+// when the longer one would reach past the local contrast range.
+
+uniform sampler2D u_texture;
+uniform vec2 u_inverse_size;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+const float REDUCE_MIN = 1.0 / 128.0;
+const float REDUCE_MUL = 1.0 / 8.0;
+const float SPAN_MAX = 8.0;
+
+float luma(vec3 rgb) {
+    return dot(rgb, vec3(0.299, 0.587, 0.114));
+}
+
+void main() {
+    vec3 rgb_nw = texture(u_texture, v_tex_coords + vec2(-1.0, -1.0) * u_inverse_size).rgb;
+    vec3 rgb_ne = texture(u_texture, v_tex_coords + vec2(1.0, -1.0) * u_inverse_size).rgb;
+    vec3 rgb_sw = texture(u_texture, v_tex_coords + vec2(-1.0, 1.0) * u_inverse_size).rgb;
+    vec3 rgb_se = texture(u_texture, v_tex_coords + vec2(1.0, 1.0) * u_inverse_size).rgb;
+    vec4 center = texture(u_texture, v_tex_coords);
+
+    float luma_nw = luma(rgb_nw);
+    float luma_ne = luma(rgb_ne);
+    float luma_sw = luma(rgb_sw);
+    float luma_se = luma(rgb_se);
+    float luma_m = luma(center.rgb);
+    float luma_min = min(luma_m, min(min(luma_nw, luma_ne), min(luma_sw, luma_se)));
+    float luma_max = max(luma_m, max(max(luma_nw, luma_ne), max(luma_sw, luma_se)));
+
+    // Perpendicular to the luma gradient, i.e. along the edge
+    vec2 direction = vec2(-((luma_nw + luma_ne) - (luma_sw + luma_se)), (luma_nw + luma_sw) - (luma_ne + luma_se));
+    float direction_reduce = max((luma_nw + luma_ne + luma_sw + luma_se) * 0.25 * REDUCE_MUL, REDUCE_MIN);
+    float inverse_smallest = 1.0 / (min(abs(direction.x), abs(direction.y)) + direction_reduce);
+    direction = clamp(direction * inverse_smallest, vec2(-SPAN_MAX), vec2(SPAN_MAX)) * u_inverse_size;
+
+    vec3 rgb_a = 0.5 * (texture(u_texture, v_tex_coords + direction * (1.0 / 3.0 - 0.5)).rgb +
+                        texture(u_texture, v_tex_coords + direction * (2.0 / 3.0 - 0.5)).rgb);
+    vec3 rgb_b = rgb_a * 0.5 + 0.25 * (texture(u_texture, v_tex_coords + direction * -0.5).rgb +
+                                       texture(u_texture, v_tex_coords + direction * 0.5).rgb);
+    float luma_b = luma(rgb_b);
+    color = vec4((luma_b < luma_min || luma_b > luma_max) ? rgb_a : rgb_b, center.a);
+}
@@ -0,0 +1,55 @@
+#version 150
+
+// Grid on the plane y = u_height with minor lines every u_cell_size, major lines every ten cells,
+// the x axis in red and the z axis in blue. Lines are a pixel wide wherever they are and fade out with distance.
+
+in vec3 v_near;
+in vec3 v_far;
+out vec4 color;
+
+uniform mat4 u_view_projection;
+uniform vec3 u_camera_position;
+uniform float u_height;
+uniform float u_cell_size;
+uniform float u_fade_distance;
+
+// 1 on a line, 0 away from it, with fwidth keeping lines about a pixel wide
+float grid_lines(vec2 coord, float cell_size) {
+    vec2 cell = coord / cell_size;
+    vec2 distance_in_pixels = abs(fract(cell - 0.5) - 0.5) / fwidth(cell);
+    return 1.0 - min(min(distance_in_pixels.x, distance_in_pixels.y), 1.0);
+}
+
+void main() {
+    float t = (u_height - v_near.y) / (v_far.y - v_near.y);
+    // Rays that never reach the ground
+    if (t <= 0.0 || t > 1.0) {
+        discard;
+    }
+    vec3 point = mix(v_near, v_far, t);
+
+    // Depth of the hit point so the grid hides behind the model and the model behind the grid
+    vec4 clip = u_view_projection * vec4(point, 1.0);
+    gl_FragDepth = clip.z / clip.w * 0.5 + 0.5;
+
+    float minor = grid_lines(point.xz, u_cell_size);
+    float major = grid_lines(point.xz, u_cell_size * 10.0);
+    vec3 line_color = mix(vec3(0.55), vec3(0.85), major);
+    float alpha = max(minor * 0.5, major);
+
+    vec2 axis_width = fwidth(point.xz);
+    if (abs(point.z) < axis_width.y) {
+        line_color = vec3(0.9, 0.2, 0.2);
+        alpha = 1.0;
+    }
+    if (abs(point.x) < axis_width.x) {
+        line_color = vec3(0.2, 0.4, 1.0);
+        alpha = 1.0;
+    }
+
+    float fade = 1.0 - smoothstep(0.3, 1.0, distance(point, u_camera_position) / u_fade_distance);
+    if (alpha * fade <= 0.0) {
+        discard;
+    }
+    color = vec4(line_color, alpha * fade);
+}
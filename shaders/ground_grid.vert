@@ -0,0 +1,22 @@
+#version 150
+
+// Fullscreen quad, each corner is unprojected onto the near and far planes so the fragment shader
+// can intersect the view ray with the ground instead of drawing a huge (but still finite) plane
+
+in vec2 position;
+
+out vec3 v_near;
+out vec3 v_far;
+
+uniform mat4 u_inverse_view_projection;
+
+vec3 unproject(vec3 ndc) {
+    vec4 point = u_inverse_view_projection * vec4(ndc, 1.0);
+    return point.xyz / point.w;
+}
+
+void main() {
+    v_near = unproject(vec3(position, -1.0));
+    v_far = unproject(vec3(position, 1.0));
+    gl_Position = vec4(position, 0.0, 1.0);
+}
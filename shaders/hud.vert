@@ -0,0 +1,15 @@
+#version 150
+
+// Positions are in pixels from the top left corner of the window
+in vec2 position;
+in vec4 color;
+
+out vec4 v_color;
+
+uniform vec2 u_inverse_viewport;
+
+void main() {
+    v_color = color;
+    vec2 ndc = position * u_inverse_viewport * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 150
+
+// Log luminance of the HDR scene, averaged by the mipmap chain for auto exposure.
+// Averaging the log keeps a few very bright pixels from darkening the whole image.
+
+uniform sampler2D u_texture;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+void main() {
+    vec3 rgb = texture(u_texture, v_tex_coords).rgb;
+    float luminance = dot(rgb, vec3(0.2126, 0.7152, 0.0722));
+    color = vec4(log(max(luminance, 0.0001)), 0.0, 0.0, 1.0);
+}
@@ -0,0 +1,15 @@
+#version 150
+
+// Example post pass, darkens the corners of the image
+
+uniform sampler2D u_texture;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+void main() {
+    vec2 offset = v_tex_coords - 0.5;
+    float vignette = 1.0 - smoothstep(0.4, 0.8, length(offset));
+    vec4 scene = texture(u_texture, v_tex_coords);
+    color = vec4(scene.rgb * mix(0.3, 1.0, vignette), scene.a);
+}
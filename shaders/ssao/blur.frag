@@ -0,0 +1,19 @@
+#version 150
+
+// Averages 4x4 pixels, the size of the noise tile, which evens out the rotated sample patterns
+
+uniform sampler2D u_texture;
+uniform vec2 u_inverse_size;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+void main() {
+    float sum = 0.0;
+    for (int x = -2; x < 2; x++) {
+        for (int y = -2; y < 2; y++) {
+            sum += texture(u_texture, v_tex_coords + vec2(x, y) * u_inverse_size).r;
+        }
+    }
+    color = vec4(vec3(sum / 16.0), 1.0);
+}
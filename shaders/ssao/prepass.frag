@@ -0,0 +1,13 @@
+#version 150
+
+// Back faces of double sided materials get their normal turned towards the camera, see facing.frag for u_front_clockwise
+
+uniform bool u_front_clockwise;
+
+in vec3 v_normal;
+out vec4 color;
+
+void main() {
+    vec3 normal = normalize(v_normal);
+    color = vec4(gl_FrontFacing != u_front_clockwise ? normal : -normal, 1.0);
+}
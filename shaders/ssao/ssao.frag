@@ -0,0 +1,53 @@
+#version 150
+
+// Screen space ambient occlusion. Rebuilds the view space position of each pixel from the depth buffer,
+// then checks how many points of a hemisphere around its normal end up behind what the depth buffer saw there.
+// The hemisphere is turned by a small tiled noise texture so fewer samples are needed, blur.frag hides the pattern.
+// View space looks down +z, so a surface in front of a sample has the smaller z.
+
+uniform sampler2D u_depth;
+uniform sampler2D u_normals;
+uniform sampler2D u_noise;
+// One sample offset per texel, inside the unit hemisphere around +z
+uniform sampler2D u_kernel;
+uniform int u_sample_count;
+uniform mat4 u_projection;
+uniform mat4 u_inverse_projection;
+uniform float u_radius;
+uniform vec2 u_noise_scale;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+vec3 view_position(vec2 tex_coords) {
+    float depth = texture(u_depth, tex_coords).r;
+    vec4 position = u_inverse_projection * vec4(vec3(tex_coords, depth) * 2.0 - 1.0, 1.0);
+    return position.xyz / position.w;
+}
+
+void main() {
+    // Nothing was drawn here
+    if (texture(u_depth, v_tex_coords).r >= 1.0) {
+        color = vec4(1.0);
+        return;
+    }
+
+    vec3 position = view_position(v_tex_coords);
+    vec3 normal = normalize(texture(u_normals, v_tex_coords).xyz);
+    vec3 random = vec3(texture(u_noise, v_tex_coords * u_noise_scale).xy, 0.0);
+    vec3 tangent = normalize(random - normal * dot(random, normal));
+    mat3 tbn = mat3(tangent, cross(normal, tangent), normal);
+
+    float bias = 0.025 * u_radius;
+    float occlusion = 0.0;
+    for (int i = 0; i < u_sample_count; i++) {
+        vec3 sample_position = position + tbn * texelFetch(u_kernel, ivec2(i, 0), 0).xyz * u_radius;
+        vec4 clip = u_projection * vec4(sample_position, 1.0);
+        vec2 sample_tex_coords = clip.xy / clip.w * 0.5 + 0.5;
+        float surface_z = view_position(sample_tex_coords).z;
+        // Surfaces much closer to the camera than the pixel should not darken it
+        float in_range = smoothstep(0.0, 1.0, u_radius / abs(position.z - surface_z));
+        occlusion += (surface_z <= sample_position.z - bias ? 1.0 : 0.0) * in_range;
+    }
+    color = vec4(vec3(1.0 - occlusion / float(u_sample_count)), 1.0);
+}
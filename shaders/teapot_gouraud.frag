@@ -2,10 +2,29 @@
 
 uniform sampler2D tex;
 uniform vec3 u_light;
+uniform vec4 u_base_color;
+// Light the surface gives off by itself, unaffected by shading
+uniform vec3 u_emissive;
+// Ambient occlusion for the whole screen, white when SSAO is off
+uniform sampler2D u_occlusion;
+uniform vec2 u_inverse_viewport;
+// Fragments less opaque than this are dropped, 0 for materials that are not alpha masked
+uniform float u_alpha_cutoff;
+// 0 writes an opaque color, 1 a color for alpha blending,
+// 2 and 3 the accumulation and revealage values of weighted blended order independent transparency
+uniform int u_transparency_pass;
 
 in vec3 v_normal;
+in vec3 v_color;
+in vec2 v_tex_coords;
 out vec4 color;
 
+// McGuire and Bavoil's depth weight, keeps nearer surfaces on top where transparent layers overlap
+float oit_weight(float alpha) {
+    float depth = 1.0 - gl_FragCoord.z * 0.9;
+    return clamp(pow(min(1.0, alpha * 10.0) + 0.01, 3.0) * 1e8 * depth * depth * depth, 1e-2, 3e3);
+}
+
 // Idea behind Gouraud Shading is that if the direction of the light is perpendicular to object surface,
 // Then this surface should be bright, if direction of light is parallel to surface, then it should be dark.
 // We do calculation once per fragment, where pixel brightness = sin(angle(surface, light))
@@ -18,13 +37,33 @@ out vec4 color;
 // -> If vertex normal and light perpendicular, 0 brightness
 // Not to worry, vertex normals are already interpolated per fragment
 void main() {
-    float brightness = dot(normalize(v_normal), normalize(u_light));
-    vec3 dark_color = vec3(0.6, 0.0, 0.0);
-    vec3 regular_color = vec3(1.0, 0.0, 0.0);
+    vec4 texel = texture(tex, v_tex_coords);
+    float alpha = u_base_color.a * texel.a;
+    if (alpha < u_alpha_cutoff) {
+        discard;
+    }
+
+    // Faces turned away from the light stay at the dark color, below it they would add negative light to the HDR target
+    float brightness = max(dot(normalize(v_normal), normalize(u_light)), 0.0);
+    // Colors are linear (textures are sampled from sRGB) and may go above 1.0, tone_map.frag brings them back to the screen
+    // Surface color is the material color times the vertex color and the base color texture
+    vec3 regular_color = u_base_color.rgb * v_color * texel.rgb;
+    float occlusion = texture(u_occlusion, gl_FragCoord.xy * u_inverse_viewport).r;
+    vec3 dark_color = 0.6 * regular_color * occlusion;
 
     // We then declare two colors: the color when the surface is entirely dark, and the color when the surface is entirely bright. 
     // In real life, it's not because an object is not exposed directly to a light source that it is black. 
     // Even unexposed surfaces receive some light from indirect sources. 
-    // Therefore the dark color is not black but an intermediate level of red.
-    color = vec4(mix(dark_color, regular_color, brightness), 1.0);
+    // Therefore the dark color is not black but an intermediate level of the surface color.
+    vec3 lit = mix(dark_color, regular_color, brightness) + u_emissive;
+
+    if (u_transparency_pass == 0) {
+        color = vec4(lit, 1.0);
+    } else if (u_transparency_pass == 1) {
+        color = vec4(lit, alpha);
+    } else if (u_transparency_pass == 2) {
+        color = vec4(lit * alpha, alpha) * oit_weight(alpha);
+    } else {
+        color = vec4(alpha);
+    }
 }
\ No newline at end of file
@@ -2,8 +2,12 @@
 
 in vec3 position;
 in vec3 normal;
+in vec3 color;
+in vec2 tex_coords;
 
 out vec3 v_normal;
+out vec3 v_color;
+out vec2 v_tex_coords;
 
 uniform mat4 perspective;
 uniform mat4 view;
@@ -12,5 +16,7 @@ uniform mat4 model;
 void main() {
     mat4 modelview = view * model;
     v_normal = transpose(inverse(mat3(modelview))) * normal;
+    v_color = color;
+    v_tex_coords = tex_coords;
     gl_Position = perspective * modelview * vec4(position, 1.0);
-}
\ No newline at end of file
+}
@@ -0,0 +1,22 @@
+#version 150
+
+// `position` is where the text was placed and `offset` the glyph corner from there, both in pixels from the top left corner of the window
+in vec3 position;
+in vec2 offset;
+in vec2 tex_coords;
+in vec4 color;
+in vec4 outline_color;
+
+out vec2 v_tex_coords;
+out vec4 v_color;
+out vec4 v_outline_color;
+
+uniform vec2 u_inverse_viewport;
+
+void main() {
+    v_tex_coords = tex_coords;
+    v_color = color;
+    v_outline_color = outline_color;
+    vec2 ndc = (position.xy + offset) * u_inverse_viewport * 2.0 - 1.0;
+    gl_Position = vec4(ndc.x, -ndc.y, 0.0, 1.0);
+}
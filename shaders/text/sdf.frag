@@ -0,0 +1,27 @@
+#version 150
+
+in vec2 v_tex_coords;
+in vec4 v_color;
+in vec4 v_outline_color;
+
+out vec4 color;
+
+// Signed distance field, 0.5 on the glyph's edge and higher inside
+uniform sampler2D u_atlas;
+// Distance value where the outline ends
+uniform float u_outline_edge;
+
+void main() {
+    float distance = texture(u_atlas, v_tex_coords).r;
+    // About one screen pixel of smoothing around the edges, whatever size the text is drawn at
+    float smoothing = 0.7 * length(vec2(dFdx(distance), dFdy(distance)));
+    float fill = smoothstep(0.5 - smoothing, 0.5 + smoothing, distance);
+    float outline = smoothstep(u_outline_edge - smoothing, u_outline_edge + smoothing, distance) * v_outline_color.a;
+
+    // Without an outline the edge fades out in the text's own color rather than darkening towards the outline's
+    vec3 outline_rgb = v_outline_color.a > 0.0 ? v_outline_color.rgb : v_color.rgb;
+    color = mix(vec4(outline_rgb, outline), v_color, fill);
+    if (color.a <= 0.0) {
+        discard;
+    }
+}
@@ -0,0 +1,26 @@
+#version 150
+
+// `position` is the label's point in model space, `offset` the glyph corner from there in view space
+in vec3 position;
+in vec2 offset;
+in vec2 tex_coords;
+in vec4 color;
+in vec4 outline_color;
+
+out vec2 v_tex_coords;
+out vec4 v_color;
+out vec4 v_outline_color;
+
+uniform mat4 perspective;
+uniform mat4 view;
+uniform mat4 model;
+
+void main() {
+    v_tex_coords = tex_coords;
+    v_color = color;
+    v_outline_color = outline_color;
+    // Offsetting after the view transform keeps the text facing the camera and upright, it scales with distance like the scene
+    vec4 view_position = view * model * vec4(position, 1.0);
+    view_position.xy += offset;
+    gl_Position = perspective * view_position;
+}
@@ -0,0 +1,43 @@
+#version 150
+
+// Maps the linear HDR scene into 0..1 and encodes it as sRGB for the screen
+
+uniform sampler2D u_texture;
+uniform sampler2D u_adapted_luminance;
+uniform float u_exposure;
+uniform bool u_auto_exposure;
+// 0 exposure only, 1 Reinhard, 2 ACES filmic
+uniform int u_operator;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+// Exposure that brings the average luminance to middle grey
+const float KEY_VALUE = 0.18;
+
+// Krzysztof Narkowicz's curve fit of the ACES reference rendering transform
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+vec3 linear_to_srgb(vec3 linear) {
+    vec3 low = linear * 12.92;
+    vec3 high = 1.055 * pow(linear, vec3(1.0 / 2.4)) - 0.055;
+    return mix(high, low, vec3(lessThanEqual(linear, vec3(0.0031308))));
+}
+
+void main() {
+    vec4 scene = texture(u_texture, v_tex_coords);
+    float exposure = u_exposure;
+    if (u_auto_exposure) {
+        exposure *= KEY_VALUE / max(texture(u_adapted_luminance, vec2(0.5)).r, 0.0001);
+    }
+    vec3 rgb = scene.rgb * exposure;
+
+    if (u_operator == 1) {
+        rgb = rgb / (1.0 + rgb);
+    } else if (u_operator == 2) {
+        rgb = aces(rgb);
+    }
+    color = vec4(linear_to_srgb(clamp(rgb, 0.0, 1.0)), scene.a);
+}
@@ -0,0 +1,21 @@
+#version 150
+
+// Resolves weighted blended order independent transparency: the weighted average color of all transparent layers,
+// covering the opaque scene as much as the product of their transparencies lets through
+
+uniform sampler2D u_accumulation;
+uniform sampler2D u_revealage;
+
+in vec2 v_tex_coords;
+out vec4 color;
+
+void main() {
+    float revealage = texture(u_revealage, v_tex_coords).r;
+    // Nothing transparent here
+    if (revealage >= 1.0) {
+        discard;
+    }
+    vec4 accumulation = texture(u_accumulation, v_tex_coords);
+    vec3 average = accumulation.rgb / clamp(accumulation.a, 1e-4, 5e4);
+    color = vec4(average, 1.0 - revealage);
+}
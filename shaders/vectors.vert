@@ -0,0 +1,24 @@
+#version 150
+
+// Feeds vectors.geom with view space vertices. The normal goes through exactly the transform
+// teapot_gouraud.vert uses, so the lines show what the lighting actually gets.
+
+in vec3 position;
+in vec3 normal;
+in vec4 tangent;
+
+out vec3 g_normal;
+out vec3 g_tangent;
+out vec3 g_bitangent;
+
+uniform mat4 view;
+uniform mat4 model;
+
+void main() {
+    mat4 modelview = view * model;
+    g_normal = normalize(transpose(inverse(mat3(modelview))) * normal);
+    // Tangents lie in the surface so they transform like positions, the bitangent flips with mirroring transforms
+    g_tangent = normalize(mat3(modelview) * tangent.xyz);
+    g_bitangent = cross(g_normal, g_tangent) * tangent.w * sign(determinant(mat3(modelview)));
+    gl_Position = modelview * vec4(position, 1.0);
+}
@@ -0,0 +1,13 @@
+#version 150
+
+// Used for the edges drawn over shaded geometry, only the position matters
+
+in vec3 position;
+
+uniform mat4 perspective;
+uniform mat4 view;
+uniform mat4 model;
+
+void main() {
+    gl_Position = perspective * view * model * vec4(position, 1.0);
+}
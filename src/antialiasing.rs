@@ -0,0 +1,164 @@
+use std::fmt;
+
+use glium::backend::Facade;
+use glium::CapabilitiesSource;
+use glium::texture::{DepthTexture2d, DepthTexture2dMultisample, MipmapsOption, Texture2d, Texture2dMultisample, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+use glium::Surface;
+
+use crate::fullscreen::FullscreenQuad;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AntiAliasing {
+    Off,
+    // Multisampling with 2, 4 or 8 samples per pixel
+    Msaa(u32),
+    // Post process edge blur, for when multisampling is unavailable or too slow
+    Fxaa,
+}
+
+impl Default for AntiAliasing {
+    fn default() -> Self {
+        AntiAliasing::Msaa(4)
+    }
+}
+
+impl AntiAliasing {
+    // Names accepted by --aa
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(AntiAliasing::Off),
+            "msaa2" => Some(AntiAliasing::Msaa(2)),
+            "msaa4" => Some(AntiAliasing::Msaa(4)),
+            "msaa8" => Some(AntiAliasing::Msaa(8)),
+            "fxaa" => Some(AntiAliasing::Fxaa),
+            _ => None,
+        }
+    }
+
+    // Order the A key steps through
+    pub fn next(self) -> Self {
+        match self {
+            AntiAliasing::Off => AntiAliasing::Msaa(2),
+            AntiAliasing::Msaa(2) => AntiAliasing::Msaa(4),
+            AntiAliasing::Msaa(4) => AntiAliasing::Msaa(8),
+            AntiAliasing::Msaa(_) => AntiAliasing::Fxaa,
+            AntiAliasing::Fxaa => AntiAliasing::Off,
+        }
+    }
+
+    pub fn samples(self) -> u32 {
+        match self {
+            AntiAliasing::Msaa(samples) => samples,
+            _ => 1,
+        }
+    }
+}
+
+impl fmt::Display for AntiAliasing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AntiAliasing::Off => write!(f, "off"),
+            AntiAliasing::Msaa(samples) => write!(f, "MSAA {}x", samples),
+            AntiAliasing::Fxaa => write!(f, "FXAA"),
+        }
+    }
+}
+
+// True when the context can render into multisampled textures with this many samples
+pub fn msaa_supported<F: Facade + ?Sized>(facade: &F, samples: u32) -> bool {
+    let context = facade.get_context();
+    glium::texture::is_texture_2d_multisample_supported(&**context)
+        && context.get_capabilities().max_framebuffer_samples.is_none_or(|max| samples as i32 <= max)
+}
+
+// Color and depth the scene is drawn into before it is presented. Color is half float so values above 1.0 survive
+// until post processing. With more than one sample the scene goes into multisampled textures that resolve() averages
+// into `color` and copies into `depth`. Sizes past the largest texture the context supports are clamped to it,
+// the presenter stretches the smaller target over the window.
+pub struct SceneTarget {
+    // What the target was asked for, size() is what it got
+    pub requested_size: (u32, u32),
+    pub samples: u32,
+    pub color: Texture2d,
+    pub depth: DepthTexture2d,
+    multisampled: Option<(Texture2dMultisample, DepthTexture2dMultisample)>,
+}
+
+impl SceneTarget {
+    pub fn new<F: Facade + ?Sized>(facade: &F, size: (u32, u32), samples: u32) -> Result<Self, glium::texture::TextureCreationError> {
+        let max_size = facade.get_context().get_capabilities().max_texture_size.max(1) as u32;
+        let (width, height) = (size.0.clamp(1, max_size), size.1.clamp(1, max_size));
+        let color = Texture2d::empty_with_format(facade, UncompressedFloatFormat::F16F16F16F16, MipmapsOption::NoMipmap, width, height)?;
+        let depth = DepthTexture2d::empty(facade, width, height)?;
+        let multisampled = if samples > 1 {
+            Some((
+                Texture2dMultisample::empty_with_format(facade, UncompressedFloatFormat::F16F16F16F16, MipmapsOption::NoMipmap, width, height, samples)?,
+                DepthTexture2dMultisample::empty(facade, width, height, samples)?,
+            ))
+        } else {
+            None
+        };
+        Ok(SceneTarget { requested_size: size, samples, color, depth, multisampled })
+    }
+
+    // The clamped size, everything sampling or matching the scene target has to use this
+    pub fn size(&self) -> (u32, u32) {
+        self.color.dimensions()
+    }
+
+    // Where the scene should be drawn this frame
+    pub fn framebuffer<F: Facade + ?Sized>(&self, facade: &F) -> glium::framebuffer::SimpleFrameBuffer<'_> {
+        match &self.multisampled {
+            Some((color, depth)) => glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(facade, color, depth).unwrap(),
+            None => glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(facade, &self.color, &self.depth).unwrap(),
+        }
+    }
+
+    // Averages the samples into the single sampled textures, does nothing without MSAA
+    pub fn resolve<F: Facade + ?Sized>(&self, facade: &F) {
+        let Some((color, depth)) = &self.multisampled else {
+            return;
+        };
+        let source = glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(facade, color, depth).unwrap();
+        let target = glium::framebuffer::SimpleFrameBuffer::with_depth_buffer(facade, &self.color, &self.depth).unwrap();
+        let (width, height) = self.color.dimensions();
+        let rect = glium::Rect { left: 0, bottom: 0, width, height };
+        let blit_target = glium::BlitTarget { left: 0, bottom: 0, width: width as i32, height: height as i32 };
+        // Depth can only be blitted with nearest filtering
+        let mask = glium::BlitMask { color: true, depth: true, stencil: false };
+        target.blit_buffers_from_simple_framebuffer(&source, &rect, &blit_target, MagnifySamplerFilter::Nearest, mask);
+    }
+}
+
+// Draws the resolved scene color, or the output of the post processing chain, to the screen, through FXAA when that is the selected mode
+pub struct Presenter {
+    quad: FullscreenQuad,
+    copy_program: glium::Program,
+    fxaa_program: glium::Program,
+}
+
+impl Presenter {
+    pub fn new<F: Facade + ?Sized>(facade: &F) -> Self {
+        Presenter {
+            quad: FullscreenQuad::new(facade),
+            // The tone mapper has already encoded the image as sRGB
+            copy_program: FullscreenQuad::srgb_program(facade, "shaders/copy.frag"),
+            fxaa_program: FullscreenQuad::srgb_program(facade, "shaders/fxaa.frag"),
+        }
+    }
+
+    pub fn present<S: Surface>(&self, target: &mut S, color: &Texture2d, anti_aliasing: AntiAliasing) {
+        let sampled = color.sampled()
+            .minify_filter(MinifySamplerFilter::Linear)
+            .magnify_filter(MagnifySamplerFilter::Linear)
+            .wrap_function(glium::uniforms::SamplerWrapFunction::Clamp);
+        if anti_aliasing == AntiAliasing::Fxaa {
+            let (width, height) = color.dimensions();
+            let uniforms = uniform! { u_texture: sampled, u_inverse_size: [1.0 / width as f32, 1.0 / height as f32] };
+            self.quad.draw(target, &self.fxaa_program, &uniforms);
+        } else {
+            self.quad.draw(target, &self.copy_program, &uniform! { u_texture: sampled });
+        }
+    }
+}
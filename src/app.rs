@@ -0,0 +1,195 @@
+use std::time::Instant;
+
+use glium::glutin::surface::WindowSurface;
+use glium::winit::event::{ElementState, Event, WindowEvent};
+use glium::winit::keyboard::{KeyCode, PhysicalKey};
+use glium::{Display, Frame};
+
+// A windowed program driven by run(). Simulation happens in update() at a fixed rate, independent of how fast frames
+// are drawn, and render() draws whatever state the last update left, blended towards the next one by `alpha`.
+pub trait App: Sized {
+    // Creates the buffers, programs and textures the app needs, once the window exists
+    fn init(display: &Display<WindowSurface>) -> Self;
+
+    // Advances the simulation by exactly `dt` seconds of scaled time
+    fn update(&mut self, dt: f32);
+
+    // Draws into `frame`. `alpha` is how far real time has got from the last update towards the next one, from 0 to 1,
+    // for interpolating between the previous and current state so motion stays smooth when frames and updates do not line up
+    fn render(&mut self, frame: &mut Frame, alpha: f32);
+
+    // Every window event, after run() has handled closing, resizing and the time controls
+    fn on_event(&mut self, _event: &WindowEvent) {}
+}
+
+// Updates per second unless run_with_rate() asks for another rate
+pub const DEFAULT_UPDATE_RATE: f32 = 60.0;
+
+// Most updates done for one frame. When a frame took longer than that the rest of the time is dropped,
+// the simulation slows down instead of falling further and further behind.
+const MAX_UPDATES_PER_FRAME: u32 = 8;
+
+pub const KEY_HELP: &str = "Space to pause, period to step once while paused, [ and ] to halve or double the time scale, backslash to reset it";
+
+// Turns real time into a number of fixed updates. Scaled time piles up in `accumulator` and is taken out a step at a time.
+#[derive(Debug)]
+pub struct FixedTimestep {
+    // Seconds of simulated time per update
+    pub step: f32,
+    // Simulated seconds per real second
+    pub time_scale: f32,
+    pub paused: bool,
+    // Set to run exactly one update on the next frame while paused
+    pub single_step: bool,
+    accumulator: f32,
+    last_frame: Instant,
+}
+
+impl FixedTimestep {
+    pub fn new(update_rate: f32) -> Self {
+        FixedTimestep { step: 1.0 / update_rate, time_scale: 1.0, paused: false, single_step: false, accumulator: 0.0, last_frame: Instant::now() }
+    }
+
+    // Takes the real time since the last call and returns how many updates to run now and the interpolation alpha for render()
+    pub fn advance(&mut self) -> (u32, f32) {
+        let now = Instant::now();
+        let elapsed = (now - self.last_frame).as_secs_f32();
+        self.last_frame = now;
+        self.advance_by(elapsed)
+    }
+
+    // Same as advance() for `elapsed` real seconds
+    pub fn advance_by(&mut self, elapsed: f32) -> (u32, f32) {
+        // Paused time does not pile up, and the accumulator is left alone so the picture keeps the same interpolation
+        if self.paused {
+            let updates = if std::mem::take(&mut self.single_step) { 1 } else { 0 };
+            return (updates, self.accumulator / self.step);
+        }
+
+        self.accumulator += elapsed * self.time_scale;
+        let updates = ((self.accumulator / self.step) as u32).min(MAX_UPDATES_PER_FRAME);
+        self.accumulator = (self.accumulator - updates as f32 * self.step).min(self.step);
+        (updates, self.accumulator / self.step)
+    }
+
+    // The time control keys of KEY_HELP
+    fn handle_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::Space => {
+                self.paused = !self.paused;
+                println!("{}", if self.paused { "Paused" } else { "Running" });
+            }
+            KeyCode::Period if self.paused => self.single_step = true,
+            KeyCode::BracketLeft | KeyCode::BracketRight => {
+                let factor = if key == KeyCode::BracketLeft { 0.5 } else { 2.0 };
+                self.time_scale = (self.time_scale * factor).clamp(1.0 / 64.0, 64.0);
+                println!("Time scale: {}x", self.time_scale);
+            }
+            KeyCode::Backslash => {
+                self.time_scale = 1.0;
+                println!("Time scale: 1x");
+            }
+            _ => (),
+        }
+    }
+}
+
+// Opens a window titled `title` and runs `A` in it at DEFAULT_UPDATE_RATE updates per second until the window is closed
+pub fn run<A: App>(title: &str) {
+    run_with_rate::<A>(title, DEFAULT_UPDATE_RATE);
+}
+
+pub fn run_with_rate<A: App>(title: &str, update_rate: f32) {
+    let event_loop = glium::winit::event_loop::EventLoop::builder().build().unwrap();
+    let (window, display) = glium::backend::glutin::SimpleWindowBuilder::new().with_title(title).build(&event_loop);
+
+    let mut app = A::init(&display);
+    let mut timestep = FixedTimestep::new(update_rate);
+    println!("Time controls: {}", KEY_HELP);
+
+    #[allow(deprecated)]
+    let _ = event_loop.run(move |event, window_target| {
+        match event {
+            Event::WindowEvent { event, .. } => {
+                match &event {
+                    WindowEvent::CloseRequested => window_target.exit(),
+                    WindowEvent::Resized(window_size) => display.resize((*window_size).into()),
+                    WindowEvent::KeyboardInput { event: key_event, .. } if key_event.state == ElementState::Pressed && !key_event.repeat => {
+                        if let PhysicalKey::Code(key) = key_event.physical_key {
+                            timestep.handle_key(key);
+                        }
+                    }
+                    WindowEvent::RedrawRequested => {
+                        let (updates, alpha) = timestep.advance();
+                        for _ in 0..updates {
+                            app.update(timestep.step);
+                        }
+                        let mut frame = display.draw();
+                        app.render(&mut frame, alpha);
+                        frame.finish().unwrap();
+                    }
+                    _ => (),
+                }
+                app.on_event(&event);
+            }
+            Event::AboutToWait => window.request_redraw(),
+            _ => (),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Quarter second steps keep all the times below exact in binary floating point
+
+    #[test]
+    fn updates_follow_the_elapsed_time() {
+        let mut timestep = FixedTimestep::new(4.0);
+        assert_eq!(timestep.advance_by(0.625), (2, 0.5));
+        // The half step left over carries into the next frame
+        assert_eq!(timestep.advance_by(0.125), (1, 0.0));
+    }
+
+    #[test]
+    fn a_long_frame_is_capped() {
+        let mut timestep = FixedTimestep::new(4.0);
+        assert_eq!(timestep.advance_by(10.0).0, MAX_UPDATES_PER_FRAME);
+        // Only one step of the time that did not fit is kept, the rest is dropped
+        assert_eq!(timestep.advance_by(0.0), (1, 0.0));
+    }
+
+    #[test]
+    fn pausing_stops_updates_and_keeps_alpha() {
+        let mut timestep = FixedTimestep::new(4.0);
+        let (_, alpha) = timestep.advance_by(0.125);
+        assert_eq!(alpha, 0.5);
+        timestep.paused = true;
+        for _ in 0..3 {
+            let (updates, paused_alpha) = timestep.advance_by(1.0);
+            assert_eq!(updates, 0);
+            assert_eq!(paused_alpha, alpha);
+        }
+    }
+
+    #[test]
+    fn single_step_runs_exactly_one_update() {
+        let mut timestep = FixedTimestep::new(4.0);
+        timestep.paused = true;
+        timestep.single_step = true;
+        assert_eq!(timestep.advance_by(1.0).0, 1);
+        assert!(!timestep.single_step);
+        assert_eq!(timestep.advance_by(1.0).0, 0);
+    }
+
+    #[test]
+    fn time_scale_multiplies_the_elapsed_time() {
+        let mut slow = FixedTimestep::new(4.0);
+        slow.time_scale = 0.5;
+        let mut fast = FixedTimestep::new(4.0);
+        fast.time_scale = 2.0;
+        assert_eq!(slow.advance_by(1.0), (2, 0.0));
+        assert_eq!(fast.advance_by(1.0), (8, 0.0));
+    }
+}
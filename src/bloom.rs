@@ -0,0 +1,132 @@
+use glium::backend::Facade;
+use glium::texture::{MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction};
+use glium::winit::keyboard::KeyCode;
+
+use crate::fullscreen::FullscreenQuad;
+
+// Most levels in the chain, the first is half the screen size and each one after that half the one before
+const MAX_LEVELS: usize = 6;
+
+pub const KEY_HELP: &str = "K to toggle bloom, 9 and 0 for its threshold, comma and period for its intensity, - and = for its radius";
+
+// Glow around the parts of the HDR scene brighter than `threshold`. The bright parts are cut out at half
+// resolution, halved over and over, then blurred back up the chain with each level added onto the next bigger one,
+// and finally added onto the scene scaled by `intensity`. `radius` widens the blur at every upsampling step.
+pub struct Bloom {
+    pub enabled: bool,
+    pub threshold: f32,
+    pub intensity: f32,
+    pub radius: f32,
+    quad: FullscreenQuad,
+    prefilter_program: glium::Program,
+    downsample_program: glium::Program,
+    upsample_program: glium::Program,
+    composite_program: glium::Program,
+    levels: Vec<Texture2d>,
+    output: Option<Texture2d>,
+}
+
+impl Bloom {
+    pub fn new<F: Facade + ?Sized>(facade: &F, enabled: bool) -> Self {
+        Bloom {
+            enabled,
+            threshold: 1.0,
+            intensity: 0.05,
+            radius: 1.0,
+            quad: FullscreenQuad::new(facade),
+            prefilter_program: FullscreenQuad::program(facade, "shaders/bloom/prefilter.frag"),
+            downsample_program: FullscreenQuad::program(facade, "shaders/bloom/downsample.frag"),
+            upsample_program: FullscreenQuad::program(facade, "shaders/bloom/upsample.frag"),
+            composite_program: FullscreenQuad::program(facade, "shaders/bloom/composite.frag"),
+            levels: Vec::new(),
+            output: None,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::KeyK => {
+                self.enabled = !self.enabled;
+                println!("Bloom: {}", if self.enabled { "on" } else { "off" });
+            }
+            KeyCode::Digit9 | KeyCode::Digit0 => {
+                self.threshold = (self.threshold + if key == KeyCode::Digit9 { -0.1 } else { 0.1 }).max(0.0);
+                println!("Bloom threshold: {:.1}", self.threshold);
+            }
+            KeyCode::Comma | KeyCode::Period => {
+                self.intensity = (self.intensity + if key == KeyCode::Comma { -0.01 } else { 0.01 }).max(0.0);
+                println!("Bloom intensity: {:.2}", self.intensity);
+            }
+            KeyCode::Minus | KeyCode::Equal => {
+                self.radius = (self.radius + if key == KeyCode::Minus { -0.25 } else { 0.25 }).clamp(0.25, 4.0);
+                println!("Bloom radius: {:.2}", self.radius);
+            }
+            _ => (),
+        }
+    }
+
+    // Adds bloom to `color` and returns the result, which is `color` itself while bloom is off
+    pub fn run<'a, F: Facade + ?Sized>(&'a mut self, facade: &F, color: &'a Texture2d) -> &'a Texture2d {
+        if !self.enabled {
+            return color;
+        }
+
+        let (width, height) = color.dimensions();
+        if self.output.as_ref().is_none_or(|output| output.dimensions() != (width, height)) {
+            let texture = |width: u32, height: u32| {
+                Texture2d::empty_with_format(facade, UncompressedFloatFormat::F16F16F16F16, MipmapsOption::NoMipmap, width, height).unwrap()
+            };
+            self.output = Some(texture(width, height));
+            self.levels.clear();
+            let (mut level_width, mut level_height) = (width / 2, height / 2);
+            while self.levels.len() < MAX_LEVELS && level_width >= 2 && level_height >= 2 {
+                self.levels.push(texture(level_width, level_height));
+                level_width /= 2;
+                level_height /= 2;
+            }
+        }
+        let Some(first) = self.levels.first() else {
+            // Window too small to blur anything
+            return color;
+        };
+
+        let inverse_size = |texture: &Texture2d| [1.0 / texture.width() as f32, 1.0 / texture.height() as f32];
+
+        let uniforms = uniform! { u_texture: sampled(color), u_inverse_size: inverse_size(color), u_threshold: self.threshold };
+        self.quad.draw(&mut first.as_surface(), &self.prefilter_program, &uniforms);
+
+        for pair in self.levels.windows(2) {
+            let uniforms = uniform! { u_texture: sampled(&pair[0]), u_inverse_size: inverse_size(&pair[0]) };
+            self.quad.draw(&mut pair[1].as_surface(), &self.downsample_program, &uniforms);
+        }
+
+        let additive = glium::DrawParameters {
+            blend: glium::Blend {
+                color: glium::BlendingFunction::Addition {
+                    source: glium::LinearBlendingFactor::One,
+                    destination: glium::LinearBlendingFactor::One,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for pair in self.levels.windows(2).rev() {
+            let uniforms = uniform! { u_texture: sampled(&pair[1]), u_inverse_size: inverse_size(&pair[1]), u_radius: self.radius };
+            self.quad.draw_with_parameters(&mut pair[0].as_surface(), &self.upsample_program, &uniforms, &additive);
+        }
+
+        let output = self.output.as_ref().unwrap();
+        let uniforms = uniform! { u_texture: sampled(color), u_bloom: sampled(first), u_intensity: self.intensity };
+        self.quad.draw(&mut output.as_surface(), &self.composite_program, &uniforms);
+        output
+    }
+}
+
+// Every step reads between texels, so all of them sample with linear filtering
+fn sampled(texture: &Texture2d) -> Sampler<'_, Texture2d> {
+    texture.sampled()
+        .minify_filter(MinifySamplerFilter::Linear)
+        .magnify_filter(MagnifySamplerFilter::Linear)
+        .wrap_function(SamplerWrapFunction::Clamp)
+}
@@ -0,0 +1,193 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use glium::backend::Facade;
+use glium::texture::{MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use glium::winit::keyboard::KeyCode;
+
+// Reads a texture back from the GPU as an image with the top row first, GL keeps the bottom row first
+pub fn read_texture(texture: &Texture2d) -> image::RgbaImage {
+    let raw: RawImage2d<u8> = texture.read();
+    let mut image = image::RgbaImage::from_raw(raw.width, raw.height, raw.data.into_owned()).unwrap();
+    image::imageops::flip_vertical_in_place(&mut image);
+    image
+}
+
+// Writes an 8 bit texture, like the one from CaptureTarget, to a PNG file
+pub fn save_png(texture: &Texture2d, path: &Path) -> Result<(), image::ImageError> {
+    read_texture(texture).save_with_format(path, image::ImageFormat::Png)
+}
+
+// `<prefix>-<UTC date and time>.png` in the working directory, milliseconds included so quick presses do not overwrite each other
+pub fn screenshot_path(prefix: &str) -> PathBuf {
+    PathBuf::from(format!("{}-{}.png", prefix, timestamp()))
+}
+
+// Current UTC time as YYYYMMDD-hhmmss-mmm, without pulling in a date crate
+pub fn timestamp() -> String {
+    format_timestamp(SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default())
+}
+
+fn format_timestamp(since_epoch: Duration) -> String {
+    let seconds = since_epoch.as_secs();
+    let (days, time) = (seconds / 86400, seconds % 86400);
+
+    // Days since 1970-01-01 to a civil date, Howard Hinnant's civil_from_days with years starting in March
+    let z = days + 719468;
+    let era = z / 146097;
+    let day_of_era = z % 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = era * 400 + year_of_era + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}{:02}{:02}-{:02}{:02}{:02}-{:03}", year, month, day, time / 3600, time / 60 % 60, time % 60,
+            since_epoch.subsec_millis())
+}
+
+// Offscreen 8 bit target the final image is presented into when it has to be saved, at the capture size rather than the window's
+#[derive(Default)]
+pub struct CaptureTarget {
+    texture: Option<Texture2d>,
+}
+
+impl CaptureTarget {
+    // The target for `size` pixels, remade when the size changes
+    pub fn texture<F: Facade + ?Sized>(&mut self, facade: &F, size: (u32, u32)) -> &Texture2d {
+        if self.texture.as_ref().is_none_or(|texture| texture.dimensions() != size) {
+            self.texture = Some(Texture2d::empty_with_format(facade, UncompressedFloatFormat::U8U8U8U8, MipmapsOption::NoMipmap,
+                                                             size.0, size.1).unwrap());
+        }
+        self.texture.as_ref().unwrap()
+    }
+}
+
+// A numbered PNG sequence being written. Every drawn frame becomes one file and advances the viewer's scene clock,
+// which drives the camera orbit and exposure adaptation, by exactly one frame at `frame_rate`, however long drawing
+// and saving it really took.
+pub struct Recording {
+    pub directory: PathBuf,
+    pub frame_rate: f32,
+    pub frames: u32,
+}
+
+impl Recording {
+    // Creates `directory` if it is missing
+    pub fn start(directory: PathBuf, frame_rate: f32) -> io::Result<Self> {
+        fs::create_dir_all(&directory)?;
+        Ok(Recording { directory, frame_rate, frames: 0 })
+    }
+
+    // Simulated seconds between two recorded frames
+    pub fn time_step(&self) -> f32 {
+        1.0 / self.frame_rate
+    }
+
+    // Path for the next frame, frame_00000.png onwards
+    pub fn next_path(&mut self) -> PathBuf {
+        let path = self.directory.join(format!("frame_{:05}.png", self.frames));
+        self.frames += 1;
+        path
+    }
+}
+
+pub const KEY_HELP: &str = "F12 to save a screenshot, R to start or stop recording a PNG sequence";
+
+// The viewer's screenshots and recordings. F12 saves the next frame, R records every frame until pressed again.
+// Both are drawn at `scale` times the window size.
+pub struct Capture {
+    pub scale: u32,
+    frame_rate: f32,
+    target: CaptureTarget,
+    screenshot_requested: bool,
+    recording: Option<Recording>,
+}
+
+impl Capture {
+    // Starts recording into `record` straight away when one is given
+    pub fn new(scale: u32, frame_rate: f32, record: Option<&str>) -> Self {
+        let mut capture = Capture { scale: scale.max(1), frame_rate, target: CaptureTarget::default(), screenshot_requested: false, recording: None };
+        if let Some(directory) = record {
+            capture.start_recording(PathBuf::from(directory));
+        }
+        capture
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::F12 => self.screenshot_requested = true,
+            KeyCode::KeyR => match self.recording.take() {
+                Some(recording) => println!("Recorded {} frames to {}", recording.frames, recording.directory.display()),
+                None => self.start_recording(PathBuf::from(format!("recording-{}", timestamp()))),
+            },
+            _ => (),
+        }
+    }
+
+    fn start_recording(&mut self, directory: PathBuf) {
+        self.recording = match Recording::start(directory.clone(), self.frame_rate) {
+            Ok(recording) => {
+                println!("Recording to {} at {} fps", directory.display(), recording.frame_rate);
+                Some(recording)
+            }
+            Err(err) => {
+                eprintln!("Could not create {}: {}", directory.display(), err);
+                None
+            }
+        };
+    }
+
+    // Whether the next frame is saved, and so drawn at the capture size
+    pub fn active(&self) -> bool {
+        self.screenshot_requested || self.recording.is_some()
+    }
+
+    // How far the scene moves on in a frame that really took `elapsed` seconds, exactly one frame of the recording while recording
+    pub fn time_step(&self, elapsed: f32) -> f32 {
+        self.recording.as_ref().map_or(elapsed, Recording::time_step)
+    }
+
+    // The target to present the frame into while active()
+    pub fn texture<F: Facade + ?Sized>(&mut self, facade: &F, size: (u32, u32)) -> &Texture2d {
+        self.target.texture(facade, size)
+    }
+
+    // Saves what was presented into texture() as the screenshot asked for and the next frame of the recording
+    pub fn save(&mut self) {
+        let Some(output) = &self.target.texture else {
+            return;
+        };
+        if std::mem::take(&mut self.screenshot_requested) {
+            let path = screenshot_path("screenshot");
+            let (width, height) = output.dimensions();
+            match save_png(output, &path) {
+                Ok(()) => println!("Saved {} ({}x{})", path.display(), width, height),
+                Err(err) => eprintln!("Could not save {}: {}", path.display(), err),
+            }
+        }
+        if let Some(recording) = &mut self.recording {
+            let path = recording.next_path();
+            if let Err(err) = save_png(output, &path) {
+                eprintln!("Could not save {}: {}", path.display(), err);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timestamps_fall_on_the_right_civil_date() {
+        // Leap day of a year divisible by 4, and the day after a leap day in a year divisible by 400
+        assert_eq!(format_timestamp(Duration::from_secs(1709251199) + Duration::from_millis(250)), "20240229-235959-250");
+        assert_eq!(format_timestamp(Duration::from_secs(951782400)), "20000229-000000-000");
+        assert_eq!(format_timestamp(Duration::from_secs(951914096)), "20000301-123456-000");
+        assert_eq!(format_timestamp(Duration::ZERO), "19700101-000000-000");
+    }
+}
@@ -0,0 +1,165 @@
+use std::f32::consts::TAU;
+
+use glium::Surface;
+
+use crate::math::{self, Mat4};
+
+#[derive(Copy, Clone, Debug)]
+struct DebugVertex {
+    position: [f32; 3],
+    color: [f32; 4],
+}
+implement_vertex!(DebugVertex, position, color);
+
+const CIRCLE_SEGMENTS: usize = 32;
+
+pub const RED: [f32; 4] = [1.0, 0.2, 0.2, 1.0];
+pub const GREEN: [f32; 4] = [0.2, 1.0, 0.2, 1.0];
+pub const BLUE: [f32; 4] = [0.2, 0.4, 1.0, 1.0];
+
+// Immediate mode lines for debugging: call the shape functions any time during a frame, then draw() renders
+// everything in one batch on top of the scene and starts over. Shapes added while depth_test is false are drawn
+// after the depth tested ones and show through the scene.
+pub struct DebugDraw {
+    pub depth_test: bool,
+    depth_tested: Vec<DebugVertex>,
+    always_visible: Vec<DebugVertex>,
+    // Reused between frames and only reallocated when a frame needs more room
+    buffer: glium::VertexBuffer<DebugVertex>,
+    program: glium::Program,
+}
+
+impl DebugDraw {
+    pub fn new<F: glium::backend::Facade + ?Sized>(facade: &F) -> Self {
+        let program = glium::Program::from_source(facade, crate::read_shader("shaders/debug_draw.vert").as_str(),
+                                                  crate::read_shader("shaders/debug_draw.frag").as_str(), None).unwrap();
+        DebugDraw {
+            depth_test: true,
+            depth_tested: Vec::new(),
+            always_visible: Vec::new(),
+            buffer: glium::VertexBuffer::empty_dynamic(facade, 1024).unwrap(),
+            program,
+        }
+    }
+
+    pub fn line(&mut self, from: [f32; 3], to: [f32; 3], color: [f32; 4]) {
+        let vertices = if self.depth_test { &mut self.depth_tested } else { &mut self.always_visible };
+        vertices.push(DebugVertex { position: from, color });
+        vertices.push(DebugVertex { position: to, color });
+    }
+
+    // Line with a four pronged head at `to`, sized to a fifth of its length
+    pub fn arrow(&mut self, from: [f32; 3], to: [f32; 3], color: [f32; 4]) {
+        self.line(from, to, color);
+        let direction = math::sub(to, from);
+        let head = math::length(direction) * 0.2;
+        let direction = math::normalize(direction);
+        let (side, up) = perpendicular_axes(direction);
+        let base = math::sub(to, math::scale(direction, head));
+        for offset in [side, math::scale(side, -1.0), up, math::scale(up, -1.0)] {
+            self.line(to, math::add(base, math::scale(offset, head * 0.4)), color);
+        }
+    }
+
+    pub fn aabb(&mut self, min: [f32; 3], max: [f32; 3], color: [f32; 4]) {
+        let corner = |i: usize| [0, 1, 2].map(|axis| if i & (1 << axis) == 0 { min[axis] } else { max[axis] });
+        self.box_edges(corner, color);
+    }
+
+    // Three circles, one around each axis
+    pub fn sphere(&mut self, center: [f32; 3], radius: f32, color: [f32; 4]) {
+        for (a, b) in [([1.0, 0.0, 0.0], [0.0, 1.0, 0.0]), ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0]), ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0])] {
+            self.circle(center, math::scale(a, radius), math::scale(b, radius), color);
+        }
+    }
+
+    // The volume a camera sees, given the inverse of its projection * view matrix. OpenGL clip space runs
+    // from -1 to 1 on every axis, so its corners mapped back into the world are the corners of the frustum.
+    pub fn frustum(&mut self, inverse_view_projection: &Mat4, color: [f32; 4]) {
+        let corner = |i: usize| {
+            let ndc = [0, 1, 2].map(|axis| if i & (1 << axis) == 0 { -1.0 } else { 1.0 });
+            math::transform_point(inverse_view_projection, ndc)
+        };
+        self.box_edges(corner, color);
+    }
+
+    // Red, green and blue lines along the x, y and z axes of a transform
+    pub fn axis_triad(&mut self, transform: &Mat4, size: f32) {
+        let origin = math::transform_point(transform, [0.0, 0.0, 0.0]);
+        for (axis, color) in [([size, 0.0, 0.0], RED), ([0.0, size, 0.0], GREEN), ([0.0, 0.0, size], BLUE)] {
+            self.arrow(origin, math::transform_point(transform, axis), color);
+        }
+    }
+
+    // Square grid in the xz plane around `center`, `divisions` cells along each side
+    pub fn grid(&mut self, center: [f32; 3], size: f32, divisions: u32, color: [f32; 4]) {
+        let divisions = divisions.max(1);
+        let half = size * 0.5;
+        for i in 0..=divisions {
+            let offset = i as f32 / divisions as f32 * size - half;
+            self.line(math::add(center, [offset, 0.0, -half]), math::add(center, [offset, 0.0, half]), color);
+            self.line(math::add(center, [-half, 0.0, offset]), math::add(center, [half, 0.0, offset]), color);
+        }
+    }
+
+    // Circle spanned by two perpendicular radius vectors
+    fn circle(&mut self, center: [f32; 3], a: [f32; 3], b: [f32; 3], color: [f32; 4]) {
+        let point = |i: usize| {
+            let (sin, cos) = (i as f32 / CIRCLE_SEGMENTS as f32 * TAU).sin_cos();
+            math::add(center, math::add(math::scale(a, cos), math::scale(b, sin)))
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    // The twelve edges of a box whose corners are numbered by bits: bit 0 picks x, bit 1 y and bit 2 z
+    fn box_edges(&mut self, corner: impl Fn(usize) -> [f32; 3], color: [f32; 4]) {
+        for i in 0..8 {
+            for bit in [1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corner(i), corner(i | bit), color);
+                }
+            }
+        }
+    }
+
+    // Renders and clears everything added since the last call, into part of the target when given a viewport
+    pub fn draw<F: glium::backend::Facade + ?Sized, S: Surface>(&mut self, facade: &F, target: &mut S, perspective: &Mat4, view: &Mat4, model: &Mat4,
+                                                                viewport: Option<glium::Rect>) {
+        let total = self.depth_tested.len() + self.always_visible.len();
+        if total == 0 {
+            return;
+        }
+        if total > self.buffer.len() {
+            self.buffer = glium::VertexBuffer::empty_dynamic(facade, total.next_power_of_two()).unwrap();
+        }
+
+        let split = self.depth_tested.len();
+        let vertices: Vec<DebugVertex> = self.depth_tested.drain(..).chain(self.always_visible.drain(..)).collect();
+        self.buffer.slice(0..total).unwrap().write(&vertices);
+
+        let uniforms = uniform! { perspective: *perspective, view: *view, model: *model };
+        let lines = glium::index::NoIndices(glium::index::PrimitiveType::LinesList);
+        let always_visible = glium::DrawParameters { viewport, ..Default::default() };
+        let depth_tested = glium::DrawParameters {
+            depth: glium::Depth { test: glium::draw_parameters::DepthTest::IfLessOrEqual, write: true, ..Default::default() },
+            ..always_visible.clone()
+        };
+        if split > 0 {
+            target.draw(self.buffer.slice(0..split).unwrap(), lines, &self.program, &uniforms, &depth_tested).unwrap();
+            crate::stats::count_draw(0);
+        }
+        if total > split {
+            target.draw(self.buffer.slice(split..total).unwrap(), lines, &self.program, &uniforms, &always_visible).unwrap();
+            crate::stats::count_draw(0);
+        }
+    }
+}
+
+// Two unit vectors perpendicular to the direction and to each other
+fn perpendicular_axes(direction: [f32; 3]) -> ([f32; 3], [f32; 3]) {
+    let helper = if direction[1].abs() < 0.9 { [0.0, 1.0, 0.0] } else { [1.0, 0.0, 0.0] };
+    let side = math::normalize(math::cross(direction, helper));
+    (side, math::cross(direction, side))
+}
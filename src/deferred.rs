@@ -0,0 +1,231 @@
+use std::fmt;
+
+use glium::backend::Facade;
+use glium::framebuffer::MultiOutputFrameBuffer;
+use glium::texture::{DepthTexture2d, MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction};
+use glium::Surface;
+use glium::winit::keyboard::KeyCode;
+
+use crate::fullscreen::FullscreenQuad;
+use crate::math::{self, Mat4};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Renderer {
+    // Every object is lit while it is drawn, by the single directional light
+    #[default]
+    Forward,
+    // Objects only fill the G-buffer, lighting happens afterwards once per light over the screen
+    Deferred,
+}
+
+impl Renderer {
+    // Names accepted by --renderer
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "forward" => Some(Renderer::Forward),
+            "deferred" => Some(Renderer::Deferred),
+            _ => None,
+        }
+    }
+}
+
+// Which G-buffer target the visualiser shows in place of the lit image
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum GBufferView {
+    #[default]
+    Off,
+    Albedo,
+    Normals,
+    Material,
+    Depth,
+    Emissive,
+}
+
+impl GBufferView {
+    // Order the X key steps through
+    pub fn next(self) -> Self {
+        match self {
+            GBufferView::Off => GBufferView::Albedo,
+            GBufferView::Albedo => GBufferView::Normals,
+            GBufferView::Normals => GBufferView::Material,
+            GBufferView::Material => GBufferView::Depth,
+            GBufferView::Depth => GBufferView::Emissive,
+            GBufferView::Emissive => GBufferView::Off,
+        }
+    }
+
+    // Value of u_mode in gbuffer_view.frag
+    fn shader_mode(self) -> i32 {
+        match self {
+            GBufferView::Off => 0,
+            GBufferView::Albedo => 1,
+            GBufferView::Normals => 2,
+            GBufferView::Material => 3,
+            GBufferView::Depth => 4,
+            GBufferView::Emissive => 5,
+        }
+    }
+}
+
+impl fmt::Display for GBufferView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GBufferView::Off => write!(f, "off (lit)"),
+            GBufferView::Albedo => write!(f, "albedo"),
+            GBufferView::Normals => write!(f, "view space normals"),
+            GBufferView::Material => write!(f, "metallic and roughness"),
+            GBufferView::Depth => write!(f, "depth"),
+            GBufferView::Emissive => write!(f, "emission"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct PointLight {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    // Distance at which the light has faded out
+    pub radius: f32,
+}
+
+// `count` lights in a ring around the scene bounds, slightly above their center, spread over the color wheel
+pub fn light_ring(count: usize, min: [f32; 3], max: [f32; 3]) -> Vec<PointLight> {
+    let center = math::scale(math::add(min, max), 0.5);
+    let size = math::length(math::sub(max, min)).max(1e-3);
+    (0..count).map(|i| {
+        let angle = i as f32 / count as f32 * std::f32::consts::TAU;
+        let position = math::add(center, [angle.cos() * size * 0.6, size * 0.25, angle.sin() * size * 0.6]);
+        // Hue to RGB, each channel is a triangle wave offset by a third of a turn
+        let color = [0.0, 1.0 / 3.0, 2.0 / 3.0].map(|offset: f32| {
+            let phase = (i as f32 / count as f32 + offset).fract();
+            (1.0 - (phase * 6.0 - 3.0).abs()).clamp(0.0, 1.0) * 2.0
+        });
+        PointLight { position, color, radius: size }
+    }).collect()
+}
+
+struct GBuffer {
+    albedo: Texture2d,
+    normal: Texture2d,
+    // Metallic in red, roughness in green
+    material: Texture2d,
+    emissive: Texture2d,
+}
+
+pub const KEY_HELP: &str = "X to cycle the G-buffer view (deferred renderer)";
+
+// The deferred path. The scene is drawn with `geometry_program` into the G-buffer from geometry_framebuffer(),
+// then light() writes the lit image into the scene color, or show() the target the visualiser was switched to.
+// The G-buffer shares the scene's depth texture so forward drawn overlays still depth test against it.
+pub struct DeferredRenderer {
+    pub view: GBufferView,
+    pub geometry_program: glium::Program,
+    quad: FullscreenQuad,
+    directional_program: glium::Program,
+    point_light_program: glium::Program,
+    view_program: glium::Program,
+    gbuffer: Option<GBuffer>,
+}
+
+impl DeferredRenderer {
+    pub fn new<F: Facade + ?Sized>(facade: &F) -> Self {
+        DeferredRenderer {
+            view: GBufferView::default(),
+            geometry_program: glium::Program::from_source(facade, crate::read_shader("shaders/teapot_gouraud.vert").as_str(),
+                                                          crate::read_shader("shaders/deferred/gbuffer.frag").as_str(), None).unwrap(),
+            quad: FullscreenQuad::new(facade),
+            directional_program: FullscreenQuad::program(facade, "shaders/deferred/directional.frag"),
+            point_light_program: FullscreenQuad::program(facade, "shaders/deferred/point_light.frag"),
+            view_program: FullscreenQuad::program(facade, "shaders/deferred/gbuffer_view.frag"),
+            gbuffer: None,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if key == KeyCode::KeyX {
+            self.view = self.view.next();
+            println!("G-buffer view: {}", self.view);
+        }
+    }
+
+    // Sizes the G-buffer for a scene of `size` pixels, call before geometry_framebuffer()
+    pub fn prepare<F: Facade + ?Sized>(&mut self, facade: &F, size: (u32, u32)) {
+        let (width, height) = size;
+        if self.gbuffer.as_ref().is_none_or(|gbuffer| gbuffer.albedo.dimensions() != (width, height)) {
+            let texture = |format| Texture2d::empty_with_format(facade, format, MipmapsOption::NoMipmap, width, height).unwrap();
+            self.gbuffer = Some(GBuffer {
+                albedo: texture(UncompressedFloatFormat::U8U8U8U8),
+                normal: texture(UncompressedFloatFormat::F16F16F16F16),
+                material: texture(UncompressedFloatFormat::U8U8U8U8),
+                emissive: texture(UncompressedFloatFormat::F16F16F16F16),
+            });
+        }
+    }
+
+    // Cleared G-buffer for the geometry pass, using `depth` as its depth buffer and clearing that too
+    pub fn geometry_framebuffer<'a, F: Facade + ?Sized>(&'a self, facade: &F, depth: &'a DepthTexture2d) -> MultiOutputFrameBuffer<'a> {
+        let gbuffer = self.gbuffer.as_ref().unwrap();
+        let outputs = [("albedo", &gbuffer.albedo), ("normal", &gbuffer.normal), ("material", &gbuffer.material), ("emissive", &gbuffer.emissive)];
+        let mut framebuffer = MultiOutputFrameBuffer::with_depth_buffer(facade, outputs, depth).unwrap();
+        framebuffer.clear_color_and_depth((0.0, 0.0, 0.0, 0.0), 1.0);
+        framebuffer
+    }
+
+    // Draws the G-buffer target picked by `view` into `target`, `depth_range` is the view space depth spread from white to black
+    pub fn show<S: Surface>(&self, target: &mut S, depth: &DepthTexture2d, projection: &Mat4, depth_range: [f32; 2]) {
+        let Some(gbuffer) = &self.gbuffer else {
+            return;
+        };
+        let uniforms = uniform! {
+            u_albedo: nearest(&gbuffer.albedo), u_normal: nearest(&gbuffer.normal), u_material: nearest(&gbuffer.material),
+            u_emissive: nearest(&gbuffer.emissive), u_depth: nearest(depth),
+            u_inverse_projection: math::mat4_inverse(projection).unwrap_or(math::IDENTITY),
+            u_depth_range: depth_range, u_mode: self.view.shader_mode(),
+        };
+        self.quad.draw(target, &self.view_program, &uniforms);
+    }
+
+    // Lights the G-buffer into `target`, adding nothing where no geometry was drawn so the background stays.
+    // `light` is the view space direction of the directional light, point lights are in view space too.
+    pub fn light<S: Surface>(&self, target: &mut S, depth: &DepthTexture2d, occlusion: &Texture2d, projection: &Mat4,
+                             light: [f32; 3], point_lights: &[PointLight]) {
+        let Some(gbuffer) = &self.gbuffer else {
+            return;
+        };
+        let inverse_projection = math::mat4_inverse(projection).unwrap_or(math::IDENTITY);
+
+        let uniforms = uniform! {
+            u_albedo: nearest(&gbuffer.albedo), u_normal: nearest(&gbuffer.normal), u_emissive: nearest(&gbuffer.emissive),
+            u_depth: nearest(depth), u_occlusion: occlusion.sampled().wrap_function(SamplerWrapFunction::Clamp), u_light: light,
+        };
+        self.quad.draw(target, &self.directional_program, &uniforms);
+
+        let additive = glium::DrawParameters {
+            blend: glium::Blend {
+                color: glium::BlendingFunction::Addition {
+                    source: glium::LinearBlendingFactor::One,
+                    destination: glium::LinearBlendingFactor::One,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        for point_light in point_lights {
+            let uniforms = uniform! {
+                u_albedo: nearest(&gbuffer.albedo), u_normal: nearest(&gbuffer.normal), u_material: nearest(&gbuffer.material),
+                u_depth: nearest(depth), u_inverse_projection: inverse_projection,
+                u_light_position: point_light.position, u_light_color: point_light.color, u_light_radius: point_light.radius,
+            };
+            self.quad.draw_with_parameters(target, &self.point_light_program, &uniforms, &additive);
+        }
+    }
+}
+
+// The G-buffer lines up texel for texel with the screen
+fn nearest<T>(texture: &T) -> Sampler<'_, T> {
+    Sampler::new(texture)
+        .minify_filter(MinifySamplerFilter::Nearest)
+        .magnify_filter(MagnifySamplerFilter::Nearest)
+        .wrap_function(SamplerWrapFunction::Clamp)
+}
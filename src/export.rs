@@ -0,0 +1,467 @@
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write as _;
+use std::path::Path;
+
+use crate::math;
+use crate::mesh::{Mesh, MeshError};
+use crate::obj_polygons;
+use crate::scene::{AlphaMode, FaceCulling, Material, Projection, Scene};
+
+// Writes a scene in the format matching the file extension: .obj (plus .mtl), .ply, .gltf or .glb
+pub fn export_scene(scene: &Scene, path: &Path) -> Result<(), MeshError> {
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_lowercase();
+    match extension.as_str() {
+        "obj" => write_obj(scene, path),
+        "ply" => write_ply(scene, path),
+        "gltf" => write_gltf(scene, path, false),
+        "glb" => write_gltf(scene, path, true),
+        _ => Err(MeshError::InvalidData(format!("cannot export to '.{}', use obj, ply, gltf or glb", extension))),
+    }
+}
+
+// Every primitive of every mesh instance with its node transform applied, for formats without a scene graph
+fn flatten(scene: &Scene) -> Vec<(Mesh, Option<usize>)> {
+    let mut result = Vec::new();
+    for (mesh, world) in scene.mesh_instances() {
+        let normal_matrix = math::normal_matrix(&world);
+        for primitive in &scene.meshes[mesh].primitives {
+            let mut baked = primitive.mesh.clone();
+            for vertex in &mut baked.vertices {
+                vertex.position = math::transform_point(&world, vertex.position);
+                vertex.normal = math::normalize(math::transform_vector(&normal_matrix, vertex.normal));
+            }
+            result.push((baked, primitive.material));
+        }
+    }
+    result
+}
+
+// OBJ and MTL need unique names without whitespace to refer to materials
+fn material_names(scene: &Scene) -> Vec<String> {
+    let mut used = HashSet::new();
+    scene.materials.iter().enumerate().map(|(i, material)| {
+        let mut name: String = material.name.chars().map(|c| if c.is_whitespace() { '_' } else { c }).collect();
+        if name.is_empty() || name == "default" || used.contains(&name) {
+            name = format!("material_{}", i);
+        }
+        used.insert(name.clone());
+        name
+    }).collect()
+}
+
+fn save_png(path: &Path, texture: &crate::scene::Texture) -> Result<(), MeshError> {
+    image::save_buffer(path, &texture.rgba, texture.width, texture.height, image::ExtendedColorType::Rgba8)?;
+    Ok(())
+}
+
+fn encode_png(texture: &crate::scene::Texture) -> Result<Vec<u8>, MeshError> {
+    let mut png = Vec::new();
+    let encoder = image::codecs::png::PngEncoder::new(&mut png);
+    image::ImageEncoder::write_image(encoder, &texture.rgba, texture.width, texture.height, image::ExtendedColorType::Rgba8)?;
+    Ok(png)
+}
+
+// Writes an OBJ file with one `usemtl` group per primitive and a .mtl file next to it.
+// Base color textures are saved as PNG files next to the .mtl. OBJ has no vertex colors, those are dropped.
+pub fn write_obj(scene: &Scene, path: &Path) -> Result<(), MeshError> {
+    let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or("model");
+    let mtl_path = path.with_extension("mtl");
+    let names = material_names(scene);
+
+    let mut obj = String::new();
+    writeln!(obj, "# Exported by rust-glium-renderer").unwrap();
+    writeln!(obj, "mtllib {}.mtl", stem).unwrap();
+    writeln!(obj, "o {}", stem).unwrap();
+
+    // OBJ indices are global and start at 1
+    let mut offset = 1;
+    let meshes = flatten(scene);
+    for (mesh, material) in &meshes {
+        for vertex in &mesh.vertices {
+            writeln!(obj, "v {} {} {}", vertex.position[0], vertex.position[1], vertex.position[2]).unwrap();
+        }
+        for vertex in &mesh.vertices {
+            // Back to OBJ's bottom left origin
+            let [u, v] = obj_polygons::flip_v(vertex.tex_coords);
+            writeln!(obj, "vt {} {}", u, v).unwrap();
+        }
+        for vertex in &mesh.vertices {
+            writeln!(obj, "vn {} {} {}", vertex.normal[0], vertex.normal[1], vertex.normal[2]).unwrap();
+        }
+
+        writeln!(obj, "usemtl {}", material.map_or("default", |material| names[material].as_str())).unwrap();
+        for triangle in mesh.indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0], triangle[1], triangle[2]].map(|i| i as usize + offset);
+            writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}").unwrap();
+        }
+        offset += mesh.vertices.len();
+    }
+
+    let mut mtl = String::new();
+    let default_material = Material::default();
+    // Only written when a primitive uses it, so reloading gives back the same materials
+    let uses_default = meshes.iter().any(|(_, material)| material.is_none());
+    let materials = scene.materials.iter().zip(names.iter().map(String::as_str))
+        .chain(uses_default.then_some((&default_material, "default")));
+    for (material, name) in materials {
+        writeln!(mtl, "newmtl {}", name).unwrap();
+        writeln!(mtl, "Kd {} {} {}", material.base_color[0], material.base_color[1], material.base_color[2]).unwrap();
+        writeln!(mtl, "Ke {} {} {}", material.emissive[0], material.emissive[1], material.emissive[2]).unwrap();
+        writeln!(mtl, "d {}", material.base_color[3]).unwrap();
+        if let Some(texture) = material.base_color_texture {
+            let file_name = format!("{}_texture_{}.png", stem, texture);
+            save_png(&mtl_path.with_file_name(&file_name), &scene.textures[texture])?;
+            writeln!(mtl, "map_Kd {}", file_name).unwrap();
+        }
+        writeln!(mtl).unwrap();
+    }
+
+    fs::write(path, obj)?;
+    fs::write(&mtl_path, mtl)?;
+    Ok(())
+}
+
+// Writes a binary little endian PLY with positions, normals, texture coordinates and colors.
+// PLY has no materials, so each material's base color is multiplied into its vertices' colors.
+pub fn write_ply(scene: &Scene, path: &Path) -> Result<(), MeshError> {
+    let meshes = flatten(scene);
+    let vertex_count: usize = meshes.iter().map(|(mesh, _)| mesh.vertices.len()).sum();
+    let face_count: usize = meshes.iter().map(|(mesh, _)| mesh.triangle_count()).sum();
+    u32::try_from(vertex_count).map_err(|_| MeshError::TooManyVertices(vertex_count))?;
+
+    let mut data = Vec::new();
+    write!(data, "ply\nformat binary_little_endian 1.0\ncomment Exported by rust-glium-renderer\n\
+                  element vertex {}\n\
+                  property float x\nproperty float y\nproperty float z\n\
+                  property float nx\nproperty float ny\nproperty float nz\n\
+                  property float s\nproperty float t\n\
+                  property uchar red\nproperty uchar green\nproperty uchar blue\n\
+                  element face {}\nproperty list uchar uint vertex_indices\nend_header\n", vertex_count, face_count)?;
+
+    for (mesh, material) in &meshes {
+        let base_color = material.map_or([1.0; 4], |material| scene.materials[material].base_color);
+        for vertex in &mesh.vertices {
+            // Back to the bottom left origin, like OBJ
+            let tex_coords = obj_polygons::flip_v(vertex.tex_coords);
+            for value in vertex.position.iter().chain(&vertex.normal).chain(&tex_coords) {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            for (channel, factor) in vertex.color.iter().zip(base_color) {
+                data.push(((channel * factor).clamp(0.0, 1.0) * 255.0).round() as u8);
+            }
+        }
+    }
+
+    let mut offset = 0u32;
+    for (mesh, _) in &meshes {
+        for triangle in mesh.indices.chunks_exact(3) {
+            data.push(3);
+            for &index in triangle {
+                data.extend_from_slice(&(index + offset).to_le_bytes());
+            }
+        }
+        offset += mesh.vertices.len() as u32;
+    }
+
+    fs::write(path, data)?;
+    Ok(())
+}
+
+// glTF strings are JSON strings
+fn json_string(value: &str) -> String {
+    let mut escaped = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+fn json_floats(values: &[f32]) -> String {
+    let values: Vec<String> = values.iter().map(|value| format!("{:?}", value)).collect();
+    format!("[{}]", values.join(","))
+}
+
+// Collects the binary buffer of a glTF file together with the bufferViews and accessors pointing into it
+#[derive(Default)]
+struct GltfBuffer {
+    data: Vec<u8>,
+    buffer_views: Vec<String>,
+    accessors: Vec<String>,
+}
+
+impl GltfBuffer {
+    // Appends bytes as a new bufferView aligned to 4 bytes and returns its index
+    fn push_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        while !self.data.len().is_multiple_of(4) {
+            self.data.push(0);
+        }
+        let target = target.map_or(String::new(), |target| format!(",\"target\":{}", target));
+        self.buffer_views.push(format!("{{\"buffer\":0,\"byteOffset\":{},\"byteLength\":{}{}}}", self.data.len(), bytes.len(), target));
+        self.data.extend_from_slice(bytes);
+        self.buffer_views.len() - 1
+    }
+
+    // Float vertex attribute with `components` values per vertex, POSITION also needs its bounds
+    fn push_attribute(&mut self, values: &[f32], components: usize, with_bounds: bool) -> usize {
+        let bytes: Vec<u8> = values.iter().flat_map(|value| value.to_le_bytes()).collect();
+        let view = self.push_view(&bytes, Some(34962));
+        let accessor_type = ["SCALAR", "VEC2", "VEC3", "VEC4"][components - 1];
+
+        let mut bounds = String::new();
+        if with_bounds {
+            let mut min = vec![f32::MAX; components];
+            let mut max = vec![f32::MIN; components];
+            for chunk in values.chunks_exact(components) {
+                for (c, &value) in chunk.iter().enumerate() {
+                    min[c] = min[c].min(value);
+                    max[c] = max[c].max(value);
+                }
+            }
+            bounds = format!(",\"min\":{},\"max\":{}", json_floats(&min), json_floats(&max));
+        }
+
+        self.accessors.push(format!("{{\"bufferView\":{},\"componentType\":5126,\"count\":{},\"type\":\"{}\"{}}}",
+                                    view, values.len() / components, accessor_type, bounds));
+        self.accessors.len() - 1
+    }
+
+    // Same index width selection as Mesh::index_buffer
+    fn push_indices(&mut self, indices: &[u32]) -> usize {
+        let max_index = indices.iter().copied().max().unwrap_or(0);
+        let (bytes, component_type): (Vec<u8>, u32) = if max_index <= u16::MAX as u32 {
+            (indices.iter().flat_map(|&index| (index as u16).to_le_bytes()).collect(), 5123)
+        } else {
+            (indices.iter().flat_map(|&index| index.to_le_bytes()).collect(), 5125)
+        };
+        let view = self.push_view(&bytes, Some(34963));
+        self.accessors.push(format!("{{\"bufferView\":{},\"componentType\":{},\"count\":{},\"type\":\"SCALAR\"}}",
+                                    view, component_type, indices.len()));
+        self.accessors.len() - 1
+    }
+}
+
+// Writes the scene keeping its node hierarchy, materials, textures and cameras.
+// A .gltf file embeds the buffer and PNG images as data URIs so it stays a single file, a .glb stores them in its binary chunk.
+pub fn write_gltf(scene: &Scene, path: &Path, binary: bool) -> Result<(), MeshError> {
+    let mut buffer = GltfBuffer::default();
+
+    let mut meshes = Vec::new();
+    for scene_mesh in &scene.meshes {
+        let mut primitives = Vec::new();
+        for primitive in &scene_mesh.primitives {
+            let vertices = &primitive.mesh.vertices;
+            let positions: Vec<f32> = vertices.iter().flat_map(|vertex| vertex.position).collect();
+            let normals: Vec<f32> = vertices.iter().flat_map(|vertex| vertex.normal).collect();
+            let tex_coords: Vec<f32> = vertices.iter().flat_map(|vertex| vertex.tex_coords).collect();
+            let colors: Vec<f32> = vertices.iter().flat_map(|vertex| vertex.color).collect();
+
+            let position = buffer.push_attribute(&positions, 3, true);
+            let normal = buffer.push_attribute(&normals, 3, false);
+            let tex_coord = buffer.push_attribute(&tex_coords, 2, false);
+            let color = buffer.push_attribute(&colors, 3, false);
+            let indices = buffer.push_indices(&primitive.mesh.indices);
+
+            let material = primitive.material.map_or(String::new(), |material| format!(",\"material\":{}", material));
+            primitives.push(format!("{{\"attributes\":{{\"POSITION\":{},\"NORMAL\":{},\"TEXCOORD_0\":{},\"COLOR_0\":{}}},\"indices\":{},\"mode\":4{}}}",
+                                    position, normal, tex_coord, color, indices, material));
+        }
+        meshes.push(format!("{{\"name\":{},\"primitives\":[{}]}}", json_string(&scene_mesh.name), primitives.join(",")));
+    }
+
+    let mut images = Vec::new();
+    for texture in &scene.textures {
+        let png = encode_png(texture)?;
+        if binary {
+            let view = buffer.push_view(&png, None);
+            images.push(format!("{{\"bufferView\":{},\"mimeType\":\"image/png\"}}", view));
+        } else {
+            images.push(format!("{{\"uri\":\"data:image/png;base64,{}\"}}", base64::encode(&png)));
+        }
+    }
+    let textures: Vec<String> = (0..scene.textures.len()).map(|i| format!("{{\"source\":{}}}", i)).collect();
+
+    let materials: Vec<String> = scene.materials.iter().map(|material| {
+        let texture = material.base_color_texture.map_or(String::new(), |texture| format!(",\"baseColorTexture\":{{\"index\":{}}}", texture));
+        let alpha = match material.alpha_mode {
+            AlphaMode::Opaque => String::from("\"OPAQUE\""),
+            AlphaMode::Mask(cutoff) => format!("\"MASK\",\"alphaCutoff\":{:?}", cutoff),
+            AlphaMode::Blend => String::from("\"BLEND\""),
+        };
+        format!("{{\"name\":{},\"pbrMetallicRoughness\":{{\"baseColorFactor\":{},\"metallicFactor\":{:?},\"roughnessFactor\":{:?}{}}},\
+                 \"emissiveFactor\":{},\"alphaMode\":{},\"doubleSided\":{}}}",
+                json_string(&material.name), json_floats(&material.base_color), material.metallic, material.roughness, texture,
+                json_floats(&material.emissive), alpha, material.culling == FaceCulling::None)
+    }).collect();
+
+    let cameras: Vec<String> = scene.cameras.iter().map(|camera| {
+        let projection = match camera.projection {
+            Projection::Perspective { yfov, aspect_ratio, znear, zfar } => {
+                let aspect_ratio = aspect_ratio.map_or(String::new(), |aspect_ratio| format!(",\"aspectRatio\":{:?}", aspect_ratio));
+                let zfar = zfar.map_or(String::new(), |zfar| format!(",\"zfar\":{:?}", zfar));
+                format!("\"type\":\"perspective\",\"perspective\":{{\"yfov\":{:?},\"znear\":{:?}{}{}}}", yfov, znear, aspect_ratio, zfar)
+            }
+            Projection::Orthographic { xmag, ymag, znear, zfar } =>
+                format!("\"type\":\"orthographic\",\"orthographic\":{{\"xmag\":{:?},\"ymag\":{:?},\"znear\":{:?},\"zfar\":{:?}}}", xmag, ymag, znear, zfar),
+        };
+        format!("{{\"name\":{},{}}}", json_string(&camera.name), projection)
+    }).collect();
+
+    let nodes: Vec<String> = scene.nodes.iter().map(|node| {
+        let mut fields = vec![format!("\"name\":{}", json_string(&node.name))];
+        if node.transform != math::IDENTITY {
+            let matrix: Vec<f32> = node.transform.iter().flatten().copied().collect();
+            fields.push(format!("\"matrix\":{}", json_floats(&matrix)));
+        }
+        if let Some(mesh) = node.mesh {
+            fields.push(format!("\"mesh\":{}", mesh));
+        }
+        if let Some(camera) = node.camera {
+            fields.push(format!("\"camera\":{}", camera));
+        }
+        if !node.children.is_empty() {
+            let children: Vec<String> = node.children.iter().map(usize::to_string).collect();
+            fields.push(format!("\"children\":[{}]", children.join(",")));
+        }
+        format!("{{{}}}", fields.join(","))
+    }).collect();
+
+    while !buffer.data.len().is_multiple_of(4) {
+        buffer.data.push(0);
+    }
+    let buffer_json = if binary {
+        format!("{{\"byteLength\":{}}}", buffer.data.len())
+    } else {
+        format!("{{\"byteLength\":{},\"uri\":\"data:application/octet-stream;base64,{}\"}}", buffer.data.len(), base64::encode(&buffer.data))
+    };
+
+    let roots: Vec<String> = scene.roots.iter().map(usize::to_string).collect();
+    let mut json = format!("{{\"asset\":{{\"version\":\"2.0\",\"generator\":\"rust-glium-renderer\"}},\"scene\":0,\"scenes\":[{{\"nodes\":[{}]}}]",
+                           roots.join(","));
+    // The spec does not allow empty top level arrays, leave them out instead
+    let arrays = [("nodes", &nodes), ("meshes", &meshes), ("materials", &materials), ("textures", &textures), ("images", &images),
+                  ("cameras", &cameras), ("accessors", &buffer.accessors), ("bufferViews", &buffer.buffer_views)];
+    for (name, items) in arrays {
+        if !items.is_empty() {
+            write!(json, ",\"{}\":[{}]", name, items.join(",")).unwrap();
+        }
+    }
+    if !buffer.data.is_empty() {
+        write!(json, ",\"buffers\":[{}]", buffer_json).unwrap();
+    }
+    json.push('}');
+
+    if !binary {
+        fs::write(path, json)?;
+        return Ok(());
+    }
+
+    // GLB: 12 byte header, then a JSON chunk padded with spaces and a BIN chunk padded with zeros
+    while !json.len().is_multiple_of(4) {
+        json.push(' ');
+    }
+    let total_length = 12 + 8 + json.len() + 8 + buffer.data.len();
+    let mut glb = Vec::with_capacity(total_length);
+    glb.extend_from_slice(b"glTF");
+    glb.extend_from_slice(&2u32.to_le_bytes());
+    glb.extend_from_slice(&(total_length as u32).to_le_bytes());
+    glb.extend_from_slice(&(json.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"JSON");
+    glb.extend_from_slice(json.as_bytes());
+    glb.extend_from_slice(&(buffer.data.len() as u32).to_le_bytes());
+    glb.extend_from_slice(b"BIN\0");
+    glb.extend_from_slice(&buffer.data);
+
+    fs::write(path, glb)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::Primitive;
+    use crate::LoadOptions;
+
+    // Every primitive of the scene in one mesh, in file order
+    fn merged(scene: &Scene) -> Mesh {
+        let mut result = Mesh::default();
+        for (mesh, _) in flatten(scene) {
+            let offset = result.vertices.len() as u32;
+            result.indices.extend(mesh.indices.iter().map(|index| index + offset));
+            result.vertices.extend(mesh.vertices);
+        }
+        result
+    }
+
+    // The teapot twice, as two primitives with their own material, texture coordinates and colors. The materials are
+    // white so the colors PLY bakes them into stay the vertex colors.
+    fn two_teapots() -> Scene {
+        let mut scene = crate::load_model("models/obj/teapot.obj", &LoadOptions::default()).unwrap();
+        let mut teapot = scene.meshes[0].primitives[0].mesh.clone();
+        for vertex in &mut teapot.vertices {
+            vertex.tex_coords = [vertex.position[0].abs().fract(), vertex.position[1].abs().fract()];
+            vertex.color = vertex.normal.map(|n| n * 0.5 + 0.5);
+        }
+        let mut flipped = teapot.clone();
+        for vertex in &mut flipped.vertices {
+            vertex.position[1] += 4.0;
+            vertex.tex_coords = [1.0 - vertex.tex_coords[0], vertex.tex_coords[1]];
+            vertex.color = [1.0 - vertex.color[0], vertex.color[1], 0.25];
+        }
+        scene.materials = vec![
+            Material { name: String::from("glazed"), roughness: 0.25, ..Material::default() },
+            Material { name: String::from("glowing"), emissive: [0.5, 0.25, 0.0], ..Material::default() },
+        ];
+        scene.meshes[0].primitives = vec![
+            Primitive { mesh: teapot, material: Some(0) },
+            Primitive { mesh: flipped, material: Some(1) },
+        ];
+        scene
+    }
+
+    #[test]
+    fn scene_round_trips_through_every_format() {
+        let scene = two_teapots();
+        let original = merged(&scene);
+        assert_eq!(original.triangle_count(), 2 * 992);
+        let directory = std::env::temp_dir().join(format!("rust-glium-renderer-export-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+
+        for extension in ["obj", "ply", "gltf", "glb"] {
+            let path = directory.join(format!("teapot.{}", extension));
+            export_scene(&scene, &path).unwrap();
+            let reloaded_scene = crate::load_model(path.to_str().unwrap(), &LoadOptions::default()).unwrap();
+            let reloaded = merged(&reloaded_scene);
+
+            // PLY has neither materials nor primitives, everything else keeps both
+            if extension != "ply" {
+                assert_eq!(reloaded_scene.meshes[0].primitives.len(), 2, "{}", extension);
+                assert_eq!(reloaded_scene.materials.len(), scene.materials.len(), "{}", extension);
+            }
+            assert_eq!(reloaded.triangle_count(), original.triangle_count(), "{}", extension);
+            assert_eq!(reloaded.vertices.len(), original.vertices.len(), "{}", extension);
+            for (a, b) in original.vertices.iter().zip(&reloaded.vertices) {
+                let close = |a: &[f32], b: &[f32], eps: f32| a.iter().zip(b).all(|(a, b)| (a - b).abs() < eps);
+                assert!(close(&a.position, &b.position, 1e-4), "{}: position {:?} came back as {:?}", extension, a.position, b.position);
+                assert!(close(&a.normal, &b.normal, 1e-4), "{}: normal {:?} came back as {:?}", extension, a.normal, b.normal);
+                assert!(close(&a.tex_coords, &b.tex_coords, 1e-4), "{}: tex_coords {:?} came back as {:?}", extension, a.tex_coords, b.tex_coords);
+                // OBJ has no vertex colors, PLY stores them as bytes
+                let color = match extension {
+                    "obj" => crate::mesh::DEFAULT_COLOR.iter().zip(&b.color).all(|(a, b)| a == b),
+                    "ply" => close(&a.color, &b.color, 0.5 / 255.0 + 1e-6),
+                    _ => close(&a.color, &b.color, 1e-6),
+                };
+                assert!(color, "{}: color {:?} came back as {:?}", extension, a.color, b.color);
+            }
+            assert_eq!(reloaded.indices, original.indices, "{}", extension);
+        }
+        fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,41 @@
+use glium::Surface;
+
+#[derive(Copy, Clone, Debug)]
+struct FullscreenVertex {
+    position: [f32; 2],
+}
+implement_vertex!(FullscreenVertex, position);
+
+// Quad covering the viewport, for passes that work on whole images. Programs pair shaders/fullscreen.vert with their own fragment shader.
+pub struct FullscreenQuad {
+    vertices: glium::VertexBuffer<FullscreenVertex>,
+}
+
+impl FullscreenQuad {
+    pub fn new<F: glium::backend::Facade + ?Sized>(facade: &F) -> Self {
+        let corners = [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [1.0, 1.0]].map(|position| FullscreenVertex { position });
+        FullscreenQuad { vertices: glium::VertexBuffer::new(facade, &corners).unwrap() }
+    }
+
+    pub fn program<F: glium::backend::Facade + ?Sized>(facade: &F, fragment_shader_path: &str) -> glium::Program {
+        glium::Program::from_source(facade, crate::read_shader("shaders/fullscreen.vert").as_str(),
+                                    crate::read_shader(fragment_shader_path).as_str(), None).unwrap()
+    }
+
+    // For passes writing to the screen after tone mapping, see crate::srgb_program
+    pub fn srgb_program<F: glium::backend::Facade + ?Sized>(facade: &F, fragment_shader_path: &str) -> glium::Program {
+        crate::srgb_program(facade, "shaders/fullscreen.vert", fragment_shader_path)
+    }
+
+    pub fn draw<S: Surface, U: glium::uniforms::Uniforms>(&self, target: &mut S, program: &glium::Program, uniforms: &U) {
+        self.draw_with_parameters(target, program, uniforms, &Default::default());
+    }
+
+    // For passes that blend onto what is already in the target
+    pub fn draw_with_parameters<S: Surface, U: glium::uniforms::Uniforms>(&self, target: &mut S, program: &glium::Program, uniforms: &U,
+                                                                          parameters: &glium::DrawParameters) {
+        let strip = glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip);
+        target.draw(&self.vertices, strip, program, uniforms, parameters).unwrap();
+        crate::stats::count_draw(2);
+    }
+}
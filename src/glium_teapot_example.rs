@@ -53,10 +53,12 @@ pub fn draw() {
                     let mut target = display.draw();
                     target.clear_color(0.0, 0.0, 1.0, 1.0);
 
+                    // The embedded teapot comes from models/obj/teapot.obj, about 32 units wide against the 183 of the
+                    // one glium's tutorial pasted in, so 0.06 instead of the tutorial's 0.01 fills the window the same way
                     let matrix = [
-                        [0.01, 0.0, 0.0, 0.0],
-                        [0.0, 0.01, 0.0, 0.0],
-                        [0.0, 0.0, 0.01, 0.0],
+                        [0.06, 0.0, 0.0, 0.0],
+                        [0.0, 0.06, 0.0, 0.0],
+                        [0.0, 0.0, 0.06, 0.0],
                         [0.0, 0.0, 0.0, 1.0f32]
                     ];
 
@@ -0,0 +1,179 @@
+use std::path::Path;
+
+use gltf::camera::Projection as GltfProjection;
+use gltf::image::Format;
+use gltf::mesh::Mode;
+
+use crate::mesh::{Mesh, MeshError};
+use crate::normals::{self, NormalMode};
+use crate::scene::{AlphaMode, Camera, FaceCulling, Material, Node, Primitive, Projection, Scene, SceneMesh, Texture};
+use crate::Vertex;
+
+// Loads a .gltf or .glb file. gltf::import resolves buffers and images embedded in data URIs,
+// stored in the GLB binary chunk or kept in external files next to the model.
+pub fn load_gltf(path: &Path) -> Result<Scene, MeshError> {
+    let (document, buffers, images) = gltf::import(path)?;
+
+    // Textures reference images, flatten that so Scene::textures lines up with glTF texture indices
+    let textures = document.textures()
+        .map(|texture| convert_image(&images[texture.source().index()]))
+        .collect();
+
+    let materials: Vec<Material> = document.materials().map(|material| {
+        let pbr = material.pbr_metallic_roughness();
+        Material {
+            name: material.name().unwrap_or("").to_string(),
+            base_color: pbr.base_color_factor(),
+            base_color_texture: pbr.base_color_texture().map(|info| info.texture().index()),
+            metallic: pbr.metallic_factor(),
+            roughness: pbr.roughness_factor(),
+            emissive: material.emissive_factor(),
+            alpha_mode: match material.alpha_mode() {
+                gltf::material::AlphaMode::Opaque => AlphaMode::Opaque,
+                gltf::material::AlphaMode::Mask => AlphaMode::Mask(material.alpha_cutoff().unwrap_or(0.5)),
+                gltf::material::AlphaMode::Blend => AlphaMode::Blend,
+            },
+            culling: if material.double_sided() { FaceCulling::None } else { FaceCulling::Back },
+        }
+    }).collect();
+
+    let mut meshes = Vec::new();
+    for mesh in document.meshes() {
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let Some(positions) = reader.read_positions() else {
+                continue;
+            };
+            let positions: Vec<[f32; 3]> = positions.collect();
+            let normals: Option<Vec<[f32; 3]>> = reader.read_normals().map(|normals| normals.collect());
+            let tangents: Option<Vec<[f32; 4]>> = reader.read_tangents().map(|tangents| tangents.collect());
+            let tex_coords: Vec<[f32; 2]> = reader.read_tex_coords(0)
+                .map_or_else(|| vec![[0.0, 0.0]; positions.len()], |tex_coords| tex_coords.into_f32().collect());
+            let colors: Vec<[f32; 4]> = reader.read_colors(0)
+                .map_or_else(|| vec![[1.0; 4]; positions.len()], |colors| colors.into_rgba_f32().collect());
+            let indices: Vec<u32> = reader.read_indices()
+                .map_or_else(|| (0..positions.len() as u32).collect(), |indices| indices.into_u32().collect());
+
+            let material = primitive.material().index();
+
+            let vertices = positions.iter().enumerate().map(|(i, &position)| Vertex {
+                position,
+                color: [colors[i][0], colors[i][1], colors[i][2]],
+                normal: normals.as_ref().map_or([0.0, 0.0, 0.0], |normals| normals[i]),
+                tex_coords: tex_coords[i],
+                tangent: tangents.as_ref().map_or([0.0; 4], |tangents| tangents[i]),
+            }).collect();
+
+            let indices = match triangle_list(primitive.mode(), &indices) {
+                Some(indices) => indices,
+                // Points and lines are not drawn by the viewer
+                None => continue,
+            };
+            if let Some(&max) = indices.iter().max() {
+                if max as usize >= positions.len() {
+                    return Err(MeshError::IndexOutOfRange);
+                }
+            }
+
+            let mut mesh = Mesh { vertices, indices };
+            // The glTF spec asks for flat normals when a primitive has none
+            if normals.is_none() {
+                mesh = normals::generate_normals(&mesh, NormalMode::Flat);
+            }
+            primitives.push(Primitive { mesh, material });
+        }
+        meshes.push(SceneMesh { name: mesh.name().unwrap_or("").to_string(), primitives });
+    }
+
+    let cameras = document.cameras().map(|camera| Camera {
+        name: camera.name().unwrap_or("").to_string(),
+        projection: match camera.projection() {
+            GltfProjection::Perspective(p) => Projection::Perspective {
+                yfov: p.yfov(),
+                aspect_ratio: p.aspect_ratio(),
+                znear: p.znear(),
+                zfar: p.zfar(),
+            },
+            GltfProjection::Orthographic(o) => Projection::Orthographic {
+                xmag: o.xmag(),
+                ymag: o.ymag(),
+                znear: o.znear(),
+                zfar: o.zfar(),
+            },
+        },
+    }).collect();
+
+    let nodes: Vec<Node> = document.nodes().map(|node| Node {
+        name: node.name().unwrap_or("").to_string(),
+        transform: node.transform().matrix(),
+        mesh: node.mesh().map(|mesh| mesh.index()),
+        camera: node.camera().map(|camera| camera.index()),
+        children: node.children().map(|child| child.index()).collect(),
+    }).collect();
+
+    // Only the default scene is shown, files without one fall back to the first scene or every top level node
+    let roots = match document.default_scene().or_else(|| document.scenes().next()) {
+        Some(gltf_scene) => gltf_scene.nodes().map(|node| node.index()).collect(),
+        None => {
+            let mut has_parent = vec![false; nodes.len()];
+            for node in &nodes {
+                for &child in &node.children {
+                    has_parent[child] = true;
+                }
+            }
+            (0..nodes.len()).filter(|&node| !has_parent[node]).collect()
+        }
+    };
+
+    Ok(Scene { meshes, materials, textures, cameras, nodes, roots })
+}
+
+// Converts strips and fans into a plain triangle list, returns None for point and line primitives
+fn triangle_list(mode: Mode, indices: &[u32]) -> Option<Vec<u32>> {
+    match mode {
+        Mode::Triangles => Some(indices.to_vec()),
+        Mode::TriangleStrip => Some((2..indices.len()).flat_map(|i| {
+            // Every other triangle in a strip is wound the other way
+            if i % 2 == 0 {
+                [indices[i - 2], indices[i - 1], indices[i]]
+            } else {
+                [indices[i - 1], indices[i - 2], indices[i]]
+            }
+        }).collect()),
+        Mode::TriangleFan => Some((2..indices.len()).flat_map(|i| [indices[0], indices[i - 1], indices[i]]).collect()),
+        Mode::Points | Mode::Lines | Mode::LineLoop | Mode::LineStrip => None,
+    }
+}
+
+// Expands any of the pixel formats glTF images decode to into 8 bit RGBA
+fn convert_image(image: &gltf::image::Data) -> Texture {
+    let pixel_count = (image.width * image.height) as usize;
+    let mut rgba = Vec::with_capacity(pixel_count * 4);
+
+    for i in 0..pixel_count {
+        let pixel = match image.format {
+            Format::R8 => { let r = image.pixels[i]; [r, r, r, 255] }
+            Format::R8G8 => [image.pixels[i * 2], image.pixels[i * 2 + 1], 0, 255],
+            Format::R8G8B8 => [image.pixels[i * 3], image.pixels[i * 3 + 1], image.pixels[i * 3 + 2], 255],
+            Format::R8G8B8A8 => [image.pixels[i * 4], image.pixels[i * 4 + 1], image.pixels[i * 4 + 2], image.pixels[i * 4 + 3]],
+            // Wider formats are little endian, keep the high byte of each channel
+            Format::R16 => { let r = image.pixels[i * 2 + 1]; [r, r, r, 255] }
+            Format::R16G16 => [image.pixels[i * 4 + 1], image.pixels[i * 4 + 3], 0, 255],
+            Format::R16G16B16 => [image.pixels[i * 6 + 1], image.pixels[i * 6 + 3], image.pixels[i * 6 + 5], 255],
+            Format::R16G16B16A16 => [image.pixels[i * 8 + 1], image.pixels[i * 8 + 3], image.pixels[i * 8 + 5], image.pixels[i * 8 + 7]],
+            Format::R32G32B32FLOAT | Format::R32G32B32A32FLOAT => {
+                let channels = if image.format == Format::R32G32B32FLOAT { 3 } else { 4 };
+                let channel = |c: usize| {
+                    let offset = (i * channels + c) * 4;
+                    let value = f32::from_le_bytes(image.pixels[offset..offset + 4].try_into().unwrap());
+                    (value.clamp(0.0, 1.0) * 255.0).round() as u8
+                };
+                [channel(0), channel(1), channel(2), if channels == 4 { channel(3) } else { 255 }]
+            }
+        };
+        rgba.extend_from_slice(&pixel);
+    }
+
+    Texture { width: image.width, height: image.height, rgba }
+}
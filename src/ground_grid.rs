@@ -0,0 +1,60 @@
+use glium::Surface;
+
+use crate::math::{self, Mat4};
+
+#[derive(Copy, Clone, Debug)]
+struct GridVertex {
+    position: [f32; 2],
+}
+implement_vertex!(GridVertex, position);
+
+// Shader drawn ground grid reaching to the horizon, see shaders/ground_grid.frag
+pub struct GroundGrid {
+    quad: glium::VertexBuffer<GridVertex>,
+    program: glium::Program,
+}
+
+impl GroundGrid {
+    pub fn new<F: glium::backend::Facade + ?Sized>(facade: &F) -> Self {
+        let corners = [[-1.0, -1.0], [1.0, -1.0], [-1.0, 1.0], [1.0, 1.0]].map(|position| GridVertex { position });
+        GroundGrid {
+            quad: glium::VertexBuffer::new(facade, &corners).unwrap(),
+            program: glium::Program::from_source(facade, crate::read_shader("shaders/ground_grid.vert").as_str(),
+                                                 crate::read_shader("shaders/ground_grid.frag").as_str(), None).unwrap(),
+        }
+    }
+
+    // Draws the grid under a scene with the given bounds, after the opaque geometry so it can blend over the background.
+    // The grid lives in scene space: it sits at the lowest point of the bounds so models stand on it, and
+    // the cell size is the power of ten that gives roughly ten to a hundred cells across the scene.
+    pub fn draw<S: Surface>(&self, target: &mut S, perspective: &Mat4, view: &Mat4, model: &Mat4, bounds: ([f32; 3], [f32; 3])) {
+        let view_projection = math::mat4_mul(perspective, &math::mat4_mul(view, model));
+        let (Some(inverse_view_projection), Some(inverse_view_model)) =
+            (math::mat4_inverse(&view_projection), math::mat4_inverse(&math::mat4_mul(view, model))) else {
+            return;
+        };
+        let camera_position = math::transform_point(&inverse_view_model, [0.0, 0.0, 0.0]);
+
+        let (min, max) = bounds;
+        let radius = (0.5 * math::length(math::sub(max, min))).max(1e-3);
+        let cell_size = 10f32.powf(radius.log10().floor() - 1.0);
+        let center = math::scale(math::add(min, max), 0.5);
+        let fade_distance = (10.0 * radius).max(3.0 * math::length(math::sub(camera_position, center)));
+
+        let uniforms = uniform! {
+            u_inverse_view_projection: inverse_view_projection,
+            u_view_projection: view_projection,
+            u_camera_position: camera_position,
+            u_height: min[1],
+            u_cell_size: cell_size,
+            u_fade_distance: fade_distance,
+        };
+        let params = glium::DrawParameters {
+            depth: glium::Depth { test: glium::draw_parameters::DepthTest::IfLess, write: false, ..Default::default() },
+            blend: glium::Blend::alpha_blending(),
+            ..Default::default()
+        };
+        target.draw(&self.quad, glium::index::NoIndices(glium::index::PrimitiveType::TriangleStrip), &self.program, &uniforms, &params).unwrap();
+        crate::stats::count_draw(2);
+    }
+}
@@ -0,0 +1,129 @@
+use glium::backend::Facade;
+use glium::Surface;
+
+use crate::stats::{self, FrameStats};
+
+#[derive(Copy, Clone, Debug)]
+struct HudVertex {
+    position: [f32; 2],
+    color: [f32; 4],
+}
+implement_vertex!(HudVertex, position, color);
+
+// 5x7 pixel glyphs, one byte per column from left to right with the top row in the lowest bit.
+// Lower case letters are drawn as upper case, anything not in here as a blank.
+const GLYPHS: &[(char, [u8; 5])] = &[
+    ('0', [0x3e, 0x51, 0x49, 0x45, 0x3e]), ('1', [0x00, 0x42, 0x7f, 0x40, 0x00]), ('2', [0x42, 0x61, 0x51, 0x49, 0x46]),
+    ('3', [0x21, 0x41, 0x45, 0x4b, 0x31]), ('4', [0x18, 0x14, 0x12, 0x7f, 0x10]), ('5', [0x27, 0x45, 0x45, 0x45, 0x39]),
+    ('6', [0x3c, 0x4a, 0x49, 0x49, 0x30]), ('7', [0x01, 0x71, 0x09, 0x05, 0x03]), ('8', [0x36, 0x49, 0x49, 0x49, 0x36]),
+    ('9', [0x06, 0x49, 0x49, 0x29, 0x1e]),
+    ('A', [0x7e, 0x11, 0x11, 0x11, 0x7e]), ('B', [0x7f, 0x49, 0x49, 0x49, 0x36]), ('C', [0x3e, 0x41, 0x41, 0x41, 0x22]),
+    ('D', [0x7f, 0x41, 0x41, 0x22, 0x1c]), ('E', [0x7f, 0x49, 0x49, 0x49, 0x41]), ('F', [0x7f, 0x09, 0x09, 0x09, 0x01]),
+    ('G', [0x3e, 0x41, 0x49, 0x49, 0x7a]), ('H', [0x7f, 0x08, 0x08, 0x08, 0x7f]), ('I', [0x00, 0x41, 0x7f, 0x41, 0x00]),
+    ('J', [0x20, 0x40, 0x41, 0x3f, 0x01]), ('K', [0x7f, 0x08, 0x14, 0x22, 0x41]), ('L', [0x7f, 0x40, 0x40, 0x40, 0x40]),
+    ('M', [0x7f, 0x02, 0x0c, 0x02, 0x7f]), ('N', [0x7f, 0x04, 0x08, 0x10, 0x7f]), ('O', [0x3e, 0x41, 0x41, 0x41, 0x3e]),
+    ('P', [0x7f, 0x09, 0x09, 0x09, 0x06]), ('Q', [0x3e, 0x41, 0x51, 0x21, 0x5e]), ('R', [0x7f, 0x09, 0x19, 0x29, 0x46]),
+    ('S', [0x46, 0x49, 0x49, 0x49, 0x31]), ('T', [0x01, 0x01, 0x7f, 0x01, 0x01]), ('U', [0x3f, 0x40, 0x40, 0x40, 0x3f]),
+    ('V', [0x1f, 0x20, 0x40, 0x20, 0x1f]), ('W', [0x3f, 0x40, 0x38, 0x40, 0x3f]), ('X', [0x63, 0x14, 0x08, 0x14, 0x63]),
+    ('Y', [0x07, 0x08, 0x70, 0x08, 0x07]), ('Z', [0x61, 0x51, 0x49, 0x45, 0x43]),
+    ('.', [0x00, 0x60, 0x60, 0x00, 0x00]), (',', [0x00, 0x50, 0x30, 0x00, 0x00]), (':', [0x00, 0x36, 0x36, 0x00, 0x00]),
+    ('%', [0x23, 0x13, 0x08, 0x64, 0x62]), ('/', [0x20, 0x10, 0x08, 0x04, 0x02]), ('-', [0x08, 0x08, 0x08, 0x08, 0x08]),
+    ('+', [0x08, 0x08, 0x3e, 0x08, 0x08]), ('(', [0x00, 0x1c, 0x22, 0x41, 0x00]), (')', [0x00, 0x41, 0x22, 0x1c, 0x00]),
+];
+
+// Screen pixels per font pixel
+const SCALE: f32 = 2.0;
+// A glyph and the gap after it, and a line with the gap under it, in font pixels
+const ADVANCE: f32 = 6.0;
+const LINE_HEIGHT: f32 = 9.0;
+const MARGIN: f32 = 8.0;
+// Height of the frame time graph in pixels and the frame time that fills it
+const GRAPH_HEIGHT: f32 = 60.0;
+const GRAPH_MILLISECONDS: f32 = 33.3;
+
+// Performance overlay in the top left corner: FrameStats::summary() as text over a dark panel, and a bar per recent frame
+// showing its frame time, green up to 60 frames per second, yellow up to 30 and red beyond. Everything is built as
+// colored triangles on the CPU each frame, the text is a few thousand of them at most.
+pub struct Hud {
+    pub visible: bool,
+    program: glium::Program,
+}
+
+impl Hud {
+    pub fn new<F: Facade + ?Sized>(facade: &F, visible: bool) -> Self {
+        Hud {
+            visible,
+            // Drawn over the presented image, its colors are meant as they are on screen
+            program: crate::srgb_program(facade, "shaders/hud.vert", "shaders/debug_draw.frag"),
+        }
+    }
+
+    pub fn draw<F: Facade + ?Sized, S: Surface>(&self, facade: &F, target: &mut S, stats: &FrameStats) {
+        if !self.visible {
+            return;
+        }
+        let lines = stats.summary();
+        let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+        let text_width = columns as f32 * ADVANCE * SCALE;
+        let text_height = lines.len() as f32 * LINE_HEIGHT * SCALE;
+
+        let mut triangles = Vec::new();
+        let panel_size = [text_width + 2.0 * MARGIN, text_height + GRAPH_HEIGHT + 3.0 * MARGIN];
+        rectangle(&mut triangles, [0.0, 0.0], panel_size, [0.0, 0.0, 0.0, 0.6]);
+        for (row, line) in lines.iter().enumerate() {
+            let origin = [MARGIN, MARGIN + row as f32 * LINE_HEIGHT * SCALE];
+            text(&mut triangles, origin, line, [1.0, 1.0, 1.0, 1.0]);
+        }
+
+        // Newest frame on the right
+        let graph_bottom = text_height + 2.0 * MARGIN + GRAPH_HEIGHT;
+        let frame_times: Vec<f32> = stats.frame_time.samples().collect();
+        let bar_width = text_width / frame_times.len().max(1) as f32;
+        for (i, milliseconds) in frame_times.iter().enumerate() {
+            let height = (milliseconds / GRAPH_MILLISECONDS).min(1.0) * GRAPH_HEIGHT;
+            let color = if *milliseconds <= 1000.0 / 59.0 {
+                [0.3, 0.9, 0.3, 1.0]
+            } else if *milliseconds <= 1000.0 / 29.0 {
+                [0.9, 0.9, 0.3, 1.0]
+            } else {
+                [0.9, 0.3, 0.3, 1.0]
+            };
+            rectangle(&mut triangles, [MARGIN + i as f32 * bar_width, graph_bottom - height], [bar_width, height], color);
+        }
+
+        let vertex_buffer = glium::VertexBuffer::new(facade, &triangles).unwrap();
+        let (width, height) = target.get_dimensions();
+        let uniforms = uniform! { u_inverse_viewport: [1.0 / width as f32, 1.0 / height as f32] };
+        let params = glium::DrawParameters { blend: glium::Blend::alpha_blending(), ..Default::default() };
+        target.draw(&vertex_buffer, glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList), &self.program, &uniforms, &params).unwrap();
+        stats::count_draw(triangles.len() / 3);
+    }
+}
+
+// Two triangles covering `size` pixels down and to the right of `corner`
+fn rectangle(triangles: &mut Vec<HudVertex>, corner: [f32; 2], size: [f32; 2], color: [f32; 4]) {
+    let [x0, y0] = corner;
+    let (x1, y1) = (x0 + size[0], y0 + size[1]);
+    for position in [[x0, y0], [x1, y0], [x0, y1], [x0, y1], [x1, y0], [x1, y1]] {
+        triangles.push(HudVertex { position, color });
+    }
+}
+
+// A square per lit font pixel, `origin` is the top left corner of the first glyph
+fn text(triangles: &mut Vec<HudVertex>, origin: [f32; 2], text: &str, color: [f32; 4]) {
+    for (i, character) in text.chars().enumerate() {
+        let character = character.to_ascii_uppercase();
+        let Some((_, columns)) = GLYPHS.iter().find(|(glyph, _)| *glyph == character) else {
+            continue;
+        };
+        let left = origin[0] + i as f32 * ADVANCE * SCALE;
+        for (column, bits) in columns.iter().enumerate() {
+            for row in 0..7 {
+                if bits & (1 << row) != 0 {
+                    let corner = [left + column as f32 * SCALE, origin[1] + row as f32 * SCALE];
+                    rectangle(triangles, corner, [SCALE, SCALE], color);
+                }
+            }
+        }
+    }
+}
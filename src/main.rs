@@ -1,16 +1,65 @@
-use glium::Surface;
 use std::fs;
 use std::env;
-use std::io;
-use obj::load_obj;
-use obj::Obj;
+use std::path::PathBuf;
+
+use antialiasing::AntiAliasing;
+use deferred::Renderer;
+use math::Mat4;
+use mesh::{Mesh, MeshError};
+use model_format::ModelFormat;
+use normals::{NormalMode, NormalWeighting};
+use scene::{AlphaMode, Camera, FaceCulling, Projection, Scene};
+use ssao::SsaoQuality;
+use tone_mapping::ToneMapping;
+use transparency::Transparency;
+use turntable::TurntableOptions;
 
 #[macro_use]
 extern crate glium;
 
+// Pulls in the VERTICES, NORMALS and INDICES constants build.rs generated for a mesh listed in its EMBEDDED_MESHES
+macro_rules! include_mesh {
+    ($name:literal) => {
+        include!(concat!(env!("OUT_DIR"), "/meshes/", $name, ".rs"));
+    };
+}
+
 mod triangle;
 mod glium_teapot;
 mod glium_teapot_example;
+mod math;
+mod mesh;
+mod normals;
+mod mesh_processing;
+mod scene;
+mod gltf_import;
+mod stl_import;
+mod ply_import;
+mod obj_import;
+mod obj_polygons;
+mod model_format;
+mod export;
+mod tangents;
+mod primitives;
+mod render_mode;
+mod winding;
+mod debug_draw;
+mod ground_grid;
+mod fullscreen;
+mod antialiasing;
+mod post_processing;
+mod bloom;
+mod ssao;
+mod deferred;
+mod tone_mapping;
+mod transparency;
+mod capture;
+mod turntable;
+mod app;
+mod stats;
+mod hud;
+mod text;
+mod viewer;
 
 // Define a 2D vertex here
 #[derive(Copy, Clone, Debug)]
@@ -18,16 +67,11 @@ struct Vertex {
   position: [f32; 3],
   color: [f32; 3], //Corresponds to vec3 RGB in GLSL,
   normal: [f32; 3],
-  tex_coords: [f32; 2]
-}
-implement_vertex!(Vertex, position, color, normal, tex_coords);
-
-//Converts from obj vertex to Vertex
-impl From<obj::Vertex> for Vertex {
-    fn from(vertex: obj::Vertex) -> Self {
-        Vertex { position: vertex.position, normal: vertex.normal, color: [1.0, 0.0, 0.0], tex_coords: [0.0, 0.0] }
-    }
+  tex_coords: [f32; 2],
+  // xyz points along +u of the texture, w is the sign of the bitangent (glTF convention). Zero until generated.
+  tangent: [f32; 4]
 }
+implement_vertex!(Vertex, position, color, normal, tex_coords, tangent);
 
 fn view_matrix(position: &[f32; 3], direction: &[f32;3], up: &[f32; 3]) -> [[f32;4]; 4] {
     let f = {
@@ -65,139 +109,450 @@ fn view_matrix(position: &[f32; 3], direction: &[f32;3], up: &[f32; 3]) -> [[f32
 
 // TODO: Can we use generics here to accept other formats such as &String?
 fn read_shader(shader_path: &str) -> String {
-    return fs::read_to_string(std::path::Path::new(&String::from(shader_path))).unwrap()
+    fs::read_to_string(std::path::Path::new(&String::from(shader_path))).unwrap()
 }
 
-// TODO: Improve error handling here by removing unwrap() and handling with ? and returning a result
-fn load_obj_file(file_path: &str) -> Obj {
-    let input = io::BufReader::new(fs::File::open(&file_path).unwrap());
-    return load_obj(input).unwrap();
+// For shaders that already write sRGB encoded colors, like everything drawn after tone mapping. Programs from
+// Program::from_source make glium turn on GL_FRAMEBUFFER_SRGB, which encodes them a second time on an sRGB window.
+fn srgb_program<F: glium::backend::Facade + ?Sized>(facade: &F, vertex_shader_path: &str, fragment_shader_path: &str) -> glium::Program {
+    let (vertex_shader, fragment_shader) = (read_shader(vertex_shader_path), read_shader(fragment_shader_path));
+    glium::Program::new(facade, glium::program::ProgramCreationInput::SourceCode {
+        vertex_shader: &vertex_shader,
+        tessellation_control_shader: None,
+        tessellation_evaluation_shader: None,
+        geometry_shader: None,
+        fragment_shader: &fragment_shader,
+        transform_feedback_varyings: None,
+        outputs_srgb: true,
+        uses_point_size: false,
+    }).unwrap()
 }
 
-fn create_teapot() {
-    let event_loop = glium::winit::event_loop::EventLoopBuilder::new().build().unwrap();
-    let (window, display) = glium::backend::glutin::SimpleWindowBuilder::new().build(&event_loop);
+// Mesh options shared by the viewer and convert
+#[derive(Copy, Clone, Debug, Default)]
+struct LoadOptions {
+    // Regenerate normals even when the file has some
+    normal_mode: Option<NormalMode>,
+    // Run mesh_processing with this welding distance
+    weld_epsilon: Option<f32>,
+    // Make triangle winding consistent, see winding::fix_winding
+    fix_winding: bool,
+}
 
-    
-    let obj_file = load_obj_file("models/obj/teapot.obj");
-    
-    let shape: Vec<Vertex> = obj_file.vertices.into_iter().map(|v| Vertex::from(v)).collect();
-    let indices = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
-    let vertex_buffer = glium::VertexBuffer::new(&display, &shape).unwrap();
-    let indices = glium::IndexBuffer::new(&display, glium::index::PrimitiveType::TrianglesList, &obj_file.indices).unwrap();
-
-    // Create empty texture
-    let texture = glium::texture::Texture2d::empty(&display, 200, 200).unwrap();
-
-    // Default shaders
-    // let vertex_shader_src = read_shader("shaders/teapot.vert");
-    // let fragment_shader_src = read_shader("shaders/teapot.frag");
-
-    // Gouraud shading shaders
-    let vertex_shader_src = read_shader("shaders/teapot_gouraud.vert");
-    let fragment_shader_src = read_shader("shaders/teapot_gouraud.frag");
-
-    let program = glium::Program::from_source(&display, vertex_shader_src.as_str(), fragment_shader_src.as_str(), None).unwrap();
-
-    let light = [-1.0, 0.4, 0.9f32];
-
-
-    let _ = event_loop.run(move |event, window_target| {
-        match event {
-            glium::winit::event::Event::WindowEvent { event, .. } => match event {
-                glium::winit::event::WindowEvent::CloseRequested => window_target.exit(),
-                glium::winit::event::WindowEvent::Resized(window_size) => {
-                    display.resize(window_size.into());
-                },
-                glium::winit::event::WindowEvent::RedrawRequested => {
-                    // Draw code
-                    let mut target = display.draw();
-                    target.clear_color_and_depth((0.0, 0.0, 1.0, 1.0), 1.0);
-                    
-                    // Perspective Matrix and Aspect Ratio
-                    let perspective = {
-                        let (width, height) = target.get_dimensions();
-                        let aspect_ratio = height as f32 / width as f32;
-                        let fov: f32 = std::f32::consts::PI / 3.0;
-                        let zfar = 1024.0;
-                        let znear = 0.1;
-                        let f = 1.0 / (fov / 2.0).tan();
-
-                        [
-                            [f * aspect_ratio, 0.0, 0.0, 0.0],
-                            [0.0, f, 0.0, 0.0],
-                            [0.0, 0.0, (zfar + znear) / (zfar - znear), 1.0],
-                            [0.0, 0.0, -(2.0 * zfar * znear) / (zfar - znear), 0.0],
-                        ]
-                    };
-
-                    let view = view_matrix(&[2.0, -1.0, 1.0], &[-2.0, 1.0, 1.0], &[0.0, 1.0, 0.0]);
-                    
-                    // Set uniform here to be used in the shader code for animating the triangle.
-                    // The naiive approach would be to instead handle t in the event loop to update the vertex but that does not make much sense,
-                    // We can place the handling and animating of the vertexes in different positions of the animations in the shader code to push that workload to the GPU
-                    let x = 0.0;
-
-                    // Remember that in CG most matrices are in column-major order
-                    // So matrix is actually 
-                    // 0.05 0.0 0.0 x
-                    // 0.0 0.05 0.0 0.0
-                    // 0.0 0.0 0.05 0.0
-                    // 0.0 0.0 2.0 1.0
-                    // in row major order
-
-                    // In column major order, order of transformations is inverse that of multiplication
-                    // So for transform: scale, rotate then translate, the order of multiplication is translate * rotate * scale * vector
-                    // In row major order, the order of multiplication is scale * rotate * translate * vector
-                    let uniforms = uniform! { 
-                        model: [
-                            [0.05, 0.0, 0.0, 0.0],
-                            [0.0, 0.05, 0.0, 0.0],
-                            [0.0, 0.0, 0.05, 0.0],
-                            [x, 0.0, 2.0, 1.0f32]
-                        ],
-                        tex: &texture,
-                        u_light: light,
-                        perspective : perspective,
-                        view: view
-                    };
-    
-                    // Add depth testing here
-                    let params = glium::DrawParameters {
-                        depth: glium::Depth {
-                            test: glium::draw_parameters::DepthTest::IfLess,
-                            write: true,
-                            ..Default::default()
-                        },
-                        // backface_culling: glium::draw_parameters::BackfaceCullingMode::CullClockwise,
-                        ..Default::default()
-                    };
-                    
-                    
-                    // We pass t here to the vertex shader using a uniform
-                    // A uniform is a global variable whose value is set when we draw by passing its value to the draw function.
-                    // The easiest way to do so is by using the uniform! macro
-                    target.draw(&vertex_buffer, &indices, &program, &uniforms, &params).unwrap();
-                    target.finish().unwrap();
+// Viewer settings chosen at startup
+#[derive(Clone, Debug, Default)]
+struct ViewOptions {
+    anti_aliasing: AntiAliasing,
+    tone_mapping: ToneMapping,
+    // In stops, added on top of auto exposure when that is on
+    exposure: f32,
+    auto_exposure: bool,
+    bloom: bool,
+    ssao: SsaoQuality,
+    renderer: Renderer,
+    // Lights placed around the scene, only the deferred renderer draws them
+    point_lights: usize,
+    transparency: Transparency,
+    // Screenshots and recorded frames are this many times the window size
+    capture_scale: u32,
+    // Simulated frames per second of a recording
+    frame_rate: f32,
+    // Seconds the camera takes to go once around the scene, it stays put when not given
+    orbit: Option<f32>,
+    // Directory to record a PNG sequence into from the first frame on
+    record: Option<String>,
+    // Show the performance HUD from the start
+    hud: bool,
+    // File to log the statistics of every frame to
+    stats_csv: Option<String>,
+    // TrueType font for labels, one of text::DEFAULT_FONTS when not given
+    font: Option<String>,
+    // Fragment shaders run over the scene in order, see post_processing
+    post_passes: Vec<String>,
+}
+
+// Loads any supported model file into a scene, formats without a scene graph become a single node.
+// Meshes without normals get them generated, passing a normal mode regenerates them even if the file has some.
+// Tangents are generated for every mesh that does not bring its own. OBJ, STL and PLY files say nothing about which way
+// their faces wind, so they are drawn from both sides unless the winding was fixed.
+fn load_model(file_path: &str, options: &LoadOptions) -> Result<Scene, MeshError> {
+    let path = std::path::Path::new(file_path);
+    let data = fs::read(path)?;
+    let normal_mode = options.normal_mode;
+    // Winding goes first since generated normals follow it
+    let fix_winding = |mesh: Mesh| {
+        if !options.fix_winding {
+            return mesh;
+        }
+        let (mesh, report) = winding::fix_winding(&mesh);
+        println!("{}", report);
+        mesh
+    };
+
+    let format = ModelFormat::detect(path, &data);
+    let (mesh, has_normals) = match format {
+        ModelFormat::Gltf | ModelFormat::Obj => {
+            // gltf::import is given the path so it can find external buffers and images next to the file,
+            // the glTF loader already generates flat normals where the file has none
+            let (mut scene, has_normals) = match format {
+                ModelFormat::Gltf => (gltf_import::load_gltf(path)?, true),
+                _ => obj_import::load_obj(path, &data)?,
+            };
+            for primitive in scene.primitives_mut() {
+                primitive.mesh = fix_winding(std::mem::take(&mut primitive.mesh));
+                match normal_mode {
+                    Some(mode) => primitive.mesh = normals::generate_normals(&primitive.mesh, mode),
+                    None if !has_normals => primitive.mesh = normals::generate_normals(&primitive.mesh, NormalMode::default()),
+                    None => (),
                 }
-                _ => (),
-            },
-            glium::winit::event::Event::AboutToWait => {
-                window.request_redraw();
+                // Tangents from the file only stay valid as long as its normals do
+                if normal_mode.is_some() || !tangents::has_tangents(&primitive.mesh) {
+                    tangents::generate_tangents(&mut primitive.mesh);
+                }
+            }
+            if format == ModelFormat::Obj && !options.fix_winding {
+                scene.set_culling(FaceCulling::None);
             }
-            _ => (),
-        };
-    }); 
+            return Ok(scene);
+        }
+        // STL always carries facet normals
+        ModelFormat::Stl => (stl_import::load_stl(&data)?, true),
+        ModelFormat::Ply => ply_import::load_ply(&data)?,
+    };
+
+    let mesh = fix_winding(mesh);
+    let mut mesh = match normal_mode {
+        Some(mode) => normals::generate_normals(&mesh, mode),
+        None if !has_normals => normals::generate_normals(&mesh, NormalMode::default()),
+        None => mesh,
+    };
+    // None of these formats store tangents
+    tangents::generate_tangents(&mut mesh);
+    let mut scene = Scene::from_mesh(file_path, mesh);
+    if !options.fix_winding {
+        scene.set_culling(FaceCulling::None);
+    }
+    Ok(scene)
+}
+
+// Perspective Matrix and Aspect Ratio, aspect_ratio is height / width
+fn perspective_matrix(aspect_ratio: f32, fov: f32, znear: f32, zfar: f32) -> Mat4 {
+    let f = 1.0 / (fov / 2.0).tan();
+
+    [
+        [f * aspect_ratio, 0.0, 0.0, 0.0],
+        [0.0, f, 0.0, 0.0],
+        [0.0, 0.0, (zfar + znear) / (zfar - znear), 1.0],
+        [0.0, 0.0, -(2.0 * zfar * znear) / (zfar - znear), 0.0],
+    ]
+}
+
+// Same depth convention as perspective_matrix, +Z goes into the screen
+fn orthographic_matrix(xmag: f32, ymag: f32, znear: f32, zfar: f32) -> Mat4 {
+    [
+        [1.0 / xmag, 0.0, 0.0, 0.0],
+        [0.0, 1.0 / ymag, 0.0, 0.0],
+        [0.0, 0.0, 2.0 / (zfar - znear), 0.0],
+        [0.0, 0.0, -(zfar + znear) / (zfar - znear), 1.0],
+    ]
+}
+
+// View and projection for a camera placed in a scene. Scene cameras look down -Z while our projection
+// matrices look down +Z, so the camera space is mirrored along Z after inverting the camera's transform.
+fn scene_camera_matrices(camera: &Camera, world: &Mat4, dimensions: (u32, u32)) -> (Mat4, Mat4) {
+    let flip_z = [
+        [1.0, 0.0, 0.0, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [0.0, 0.0, -1.0, 0.0],
+        [0.0, 0.0, 0.0, 1.0],
+    ];
+    let view = math::mat4_mul(&flip_z, &math::mat4_inverse(world).unwrap_or(math::IDENTITY));
+
+    let projection = match camera.projection {
+        Projection::Perspective { yfov, aspect_ratio, znear, zfar } => {
+            let aspect_ratio = aspect_ratio.map_or(dimensions.1 as f32 / dimensions.0 as f32, |aspect_ratio| 1.0 / aspect_ratio);
+            perspective_matrix(aspect_ratio, yfov, znear, zfar.unwrap_or(1024.0))
+        }
+        Projection::Orthographic { xmag, ymag, znear, zfar } => orthographic_matrix(xmag, ymag, znear, zfar),
+    };
+    (view, projection)
+}
+
+// Moves the camera of `view` `angle` radians around the vertical axis through `center`, which stays where it was on screen.
+// Turning the scene the other way about that axis does the same to the picture.
+fn orbit_view(view: &Mat4, center: [f32; 3], angle: f32) -> Mat4 {
+    let (sin, cos) = angle.sin_cos();
+    let turned_center = [cos * center[0] + sin * center[2], center[1], cos * center[2] - sin * center[0]];
+    let turn = [
+        [cos, 0.0, -sin, 0.0],
+        [0.0, 1.0, 0.0, 0.0],
+        [sin, 0.0, cos, 0.0],
+        [center[0] - turned_center[0], 0.0, center[2] - turned_center[2], 1.0],
+    ];
+    math::mat4_mul(view, &turn)
+}
+
+// A scene primitive uploaded to the GPU
+struct GpuPrimitive {
+    vertex_buffer: glium::VertexBuffer<Vertex>,
+    indices: glium::index::IndexBufferAny,
+    base_color: [f32; 4],
+    texture: Option<usize>,
+    emissive: [f32; 3],
+    metallic: f32,
+    roughness: f32,
+    culling: FaceCulling,
+    alpha_mode: AlphaMode,
+    // For the frame statistics
+    triangles: usize,
+    // Middle of the mesh bounds, transparent primitives are sorted by its depth
+    center: [f32; 3],
+}
+
+// Uploads every primitive with its material, nested like scene.meshes. Fails with the name of the mesh that could not be uploaded.
+fn upload_meshes<F: glium::backend::Facade + ?Sized>(facade: &F, scene: &Scene) -> Result<Vec<Vec<GpuPrimitive>>, (String, MeshError)> {
+    let mut meshes: Vec<Vec<GpuPrimitive>> = Vec::new();
+    for scene_mesh in &scene.meshes {
+        let mut primitives = Vec::new();
+        for primitive in &scene_mesh.primitives {
+            let vertex_buffer = glium::VertexBuffer::new(facade, &primitive.mesh.vertices).unwrap();
+            let indices = primitive.mesh.index_buffer(facade, glium::index::PrimitiveType::TrianglesList)
+                .map_err(|err| (scene_mesh.name.clone(), err))?;
+            let material = scene.material(primitive);
+            let (min, max) = primitive.mesh.vertices.iter().fold(([f32::MAX; 3], [f32::MIN; 3]), |(min, max), vertex| {
+                (std::array::from_fn(|i| min[i].min(vertex.position[i])), std::array::from_fn(|i| max[i].max(vertex.position[i])))
+            });
+            primitives.push(GpuPrimitive { vertex_buffer, indices, base_color: material.base_color, texture: material.base_color_texture,
+                                         emissive: material.emissive, metallic: material.metallic, roughness: material.roughness,
+                                         culling: material.culling, alpha_mode: material.alpha_mode, triangles: primitive.mesh.triangle_count(),
+                                         center: math::scale(math::add(min, max), 0.5) });
+        }
+        meshes.push(primitives);
+    }
+    Ok(meshes)
+}
+
+// Color textures are stored as sRGB, sampling them gives the linear values lighting works with
+fn upload_textures<F: glium::backend::Facade + ?Sized>(facade: &F, scene: &Scene) -> Vec<glium::texture::SrgbTexture2d> {
+    scene.textures.iter().map(|texture| {
+        let image = glium::texture::RawImage2d::from_raw_rgba(texture.rgba.clone(), (texture.width, texture.height));
+        glium::texture::SrgbTexture2d::new(facade, image).unwrap()
+    }).collect()
+}
+
+// load_model followed by the optional mesh_processing pass, printing what was loaded and what processing changed
+fn load_scene(model_path: &str, options: &LoadOptions) -> Result<Scene, MeshError> {
+    let mut scene = load_model(model_path, options)?;
+    print!("{}", scene);
+    if let Some(epsilon) = options.weld_epsilon {
+        for primitive in scene.primitives_mut() {
+            let (processed, report) = mesh_processing::process(&primitive.mesh, epsilon);
+            println!("{}", report);
+            primitive.mesh = processed;
+        }
+    }
+    Ok(scene)
+}
+
+// Loads a model, applies the same normal generation and processing options as the viewer and writes it back out
+fn convert_model(input_path: &str, output_path: &str, options: &LoadOptions) {
+    let result = load_scene(input_path, options)
+        .and_then(|scene| export::export_scene(&scene, std::path::Path::new(output_path)));
+    match result {
+        Ok(()) => println!("Wrote {}", output_path),
+        Err(err) => eprintln!("Could not convert {} to {}: {}", input_path, output_path, err),
+    }
+}
+
+// options.weld_epsilon runs every mesh through mesh_processing before uploading it and prints what changed
+fn create_teapot(model_path: &str, options: &LoadOptions, view_options: &ViewOptions) {
+    match load_scene(model_path, options) {
+        Ok(scene) => viewer::view_scene(scene, view_options),
+        Err(err) => eprintln!("Could not load {}: {}", model_path, err),
+    }
+}
+
+// Shows one of the procedural primitives, see primitives::by_name for what `--subdivisions` means for each
+fn view_primitive(name: &str, subdivisions: Option<u32>, view_options: &ViewOptions) {
+    match primitives::by_name(name, subdivisions) {
+        Some(mesh) => {
+            let scene = Scene::from_mesh(name, mesh);
+            print!("{}", scene);
+            viewer::view_scene(scene, view_options);
+        }
+        None => eprintln!("Unknown primitive {}, expected plane, grid, cube, sphere, icosphere, cylinder, cone, torus or capsule", name),
+    }
+}
+
+// Value following a `--flag` on the command line
+fn arg_value<'a>(args: &'a [String], flag: &str) -> Option<&'a String> {
+    args.iter().position(|arg| arg == flag).and_then(|i| args.get(i + 1))
+}
+
+// Parses the value following `flag`, None when the flag is not given. A value `parse` rejects becomes a usage message
+// saying what was `expected`.
+fn parse_arg<T>(args: &[String], flag: &str, expected: &str, parse: impl FnOnce(&str) -> Option<T>) -> Result<Option<T>, String> {
+    arg_value(args, flag).map(|value| parse(value).ok_or_else(|| format!("{} expects {}, got {}", flag, expected, value))).transpose()
+}
+
+// Prints a bad command line value like the usage messages of the subcommands and stops before anything is loaded
+fn or_exit<T>(result: Result<T, String>) -> T {
+    result.unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        std::process::exit(2);
+    })
+}
+
+// Parses `--normals <flat|area|angle>` and `--crease <degrees>` for the viewer
+fn parse_normal_mode(args: &[String]) -> Result<Option<NormalMode>, String> {
+    let crease_angle = parse_arg(args, "--crease", "an angle in degrees", |degrees| degrees.parse::<f32>().ok())?
+        .unwrap_or(60.0)
+        .to_radians();
+    parse_arg(args, "--normals", "flat, area or angle", |name| match name {
+        "flat" => Some(NormalMode::Flat),
+        "area" => Some(NormalMode::Smooth { weighting: NormalWeighting::Area, crease_angle }),
+        "angle" => Some(NormalMode::Smooth { weighting: NormalWeighting::Angle, crease_angle }),
+        _ => None,
+    })
+}
+
+// Reads the mesh options, see the usage comment above main
+fn parse_load_options(args: &[String]) -> Result<LoadOptions, String> {
+    let weld_epsilon = parse_arg(args, "--weld-epsilon", "a distance", |epsilon| epsilon.parse::<f32>().ok())?
+        .or(if args.iter().any(|arg| arg == "--optimize") { Some(1e-5) } else { None });
+    Ok(LoadOptions {
+        normal_mode: parse_normal_mode(args)?,
+        weld_epsilon,
+        fix_winding: args.iter().any(|arg| arg == "--fix-winding"),
+    })
+}
+
+// Reads the viewer settings, see the usage comment above main
+fn parse_view_options(args: &[String]) -> Result<ViewOptions, String> {
+    let anti_aliasing = parse_arg(args, "--aa", "off, msaa2, msaa4, msaa8 or fxaa", AntiAliasing::parse)?.unwrap_or_default();
+    let post_passes = arg_value(args, "--post")
+        .map_or_else(Vec::new, |paths| paths.split(',').map(str::to_string).collect());
+    let ssao = parse_arg(args, "--ssao", "off, low, medium or high", SsaoQuality::parse)?.unwrap_or_default();
+    let renderer = parse_arg(args, "--renderer", "forward or deferred", Renderer::parse)?.unwrap_or_default();
+    let point_lights = parse_arg(args, "--point-lights", "a whole number", |count| count.parse::<usize>().ok())?.unwrap_or(0);
+    let transparency = parse_arg(args, "--transparency", "sorted or oit", Transparency::parse)?.unwrap_or_default();
+    let capture_scale = parse_arg(args, "--capture-scale", "a whole number from 1 up", |scale| {
+        scale.parse::<u32>().ok().filter(|&scale| scale > 0)
+    })?.unwrap_or(1);
+    let frame_rate = parse_arg(args, "--frame-rate", "frames per second", |rate| {
+        rate.parse::<f32>().ok().filter(|&rate| rate > 0.0)
+    })?.unwrap_or(30.0);
+    let orbit = parse_arg(args, "--orbit", "seconds per turn", |seconds| {
+        seconds.parse::<f32>().ok().filter(|&seconds| seconds != 0.0 && seconds.is_finite())
+    })?;
+    let tone_mapping = parse_arg(args, "--tone-map", "exposure, reinhard or aces", ToneMapping::parse)?.unwrap_or_default();
+    let exposure = parse_arg(args, "--exposure", "a number of stops", |exposure| exposure.parse::<f32>().ok())?.unwrap_or(0.0);
+    Ok(ViewOptions { anti_aliasing, tone_mapping, exposure, auto_exposure: args.iter().any(|arg| arg == "--auto-exposure"),
+                     bloom: args.iter().any(|arg| arg == "--bloom"), ssao, renderer, point_lights, transparency, capture_scale, frame_rate, orbit,
+                     record: arg_value(args, "--record").cloned(), hud: args.iter().any(|arg| arg == "--hud"),
+                     stats_csv: arg_value(args, "--stats-csv").cloned(), font: arg_value(args, "--font").cloned(), post_passes })
+}
+
+// Reads the turntable settings, see the usage comment above main
+fn parse_turntable_options(args: &[String]) -> Result<TurntableOptions, String> {
+    let defaults = TurntableOptions::default();
+    let angles = parse_arg(args, "--angles", "a whole number from 1 up", |angles| {
+        angles.parse::<u32>().ok().filter(|&angles| angles > 0)
+    })?.unwrap_or(defaults.angles);
+    let size = parse_arg(args, "--size", "<width>x<height>, like 512x512", |size| {
+        size.split_once('x')
+            .and_then(|(width, height)| Some((width.parse::<u32>().ok()?, height.parse::<u32>().ok()?)))
+            .filter(|&(width, height)| width > 0 && height > 0)
+    })?.unwrap_or(defaults.size);
+    Ok(TurntableOptions {
+        angles,
+        size,
+        output: arg_value(args, "--output").map_or(defaults.output, PathBuf::from),
+        contact_sheet: args.iter().any(|arg| arg == "--contact-sheet"),
+    })
 }
 
 // Note: Remember that matrices in OpenGL are in column-major order
+// Usage: rust-glium-renderer [triangle | example | path/to/model.(obj|gltf|glb|stl|ply)] [--normals <flat|area|angle>] [--crease <degrees>]
+//                            [--optimize] [--weld-epsilon <distance>] [--fix-winding] [--aa <off|msaa2|msaa4|msaa8|fxaa>]
+//                            [--post <shader.frag>[,<shader.frag>...]] [--tone-map <exposure|reinhard|aces>] [--exposure <stops>] [--auto-exposure] [--bloom]
+//                            [--ssao <off|low|medium|high>] [--renderer <forward|deferred>] [--point-lights <n>]
+//                            [--transparency <sorted|oit>] [--record <directory>] [--frame-rate <fps>] [--capture-scale <n>] [--orbit <seconds>]
+//                            [--hud] [--stats-csv <file.csv>] [--font <file.ttf>]
+//        rust-glium-renderer primitive <plane|grid|cube|sphere|icosphere|cylinder|cone|torus|capsule> [--subdivisions <n>] [view options as above]
+//        rust-glium-renderer convert <input> <output.(obj|ply|gltf|glb)> [same mesh options as above]
+//        rust-glium-renderer turntable <model.obj | directory> [--angles <n>] [--size <width>x<height>] [--output <directory>]
+//                            [--contact-sheet] [same mesh options as above]
 fn main() {
+    let args: Vec<String> = env::args().collect();
+    let options = or_exit(parse_load_options(&args));
+    let view_options = or_exit(parse_view_options(&args));
 
-    // crate::triangle::create_triangle_with_colored_vertices();
-    // crate::glium_teapot_example::draw();
-
-    //My own implementation of viewing teapot with reading shaders from file and loading obj from file
-    create_teapot();
+    match args.get(1).map(String::as_str) {
+        Some("triangle") => crate::triangle::create_triangle_with_colored_vertices(),
+        Some("example") => crate::glium_teapot_example::draw(),
+        Some("primitive") => match args.get(2) {
+            Some(name) => {
+                let subdivisions = or_exit(parse_arg(&args, "--subdivisions", "a whole number", |subdivisions| subdivisions.parse::<u32>().ok()));
+                view_primitive(name, subdivisions, &view_options)
+            }
+            None => eprintln!("Usage: rust-glium-renderer primitive <name> [--subdivisions <n>]"),
+        },
+        Some("convert") => match (args.get(2), args.get(3)) {
+            (Some(input), Some(output)) => convert_model(input, output, &options),
+            _ => eprintln!("Usage: rust-glium-renderer convert <input> <output.(obj|ply|gltf|glb)>"),
+        },
+        Some("turntable") => match args.get(2) {
+            Some(input) => turntable::render_turntable(input, &or_exit(parse_turntable_options(&args)), &options),
+            None => eprintln!("Usage: rust-glium-renderer turntable <model.obj | directory> [--angles <n>] [--size <width>x<height>] [--output <directory>] [--contact-sheet]"),
+        },
+        // My own implementation of viewing teapot with reading shaders from file and loading obj from file
+        Some(path) if !path.starts_with("--") => create_teapot(path, &options, &view_options),
+        _ => create_teapot("models/obj/teapot.obj", &options, &view_options),
+    }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn bad_option_values_become_usage_messages() {
+        for (line, message) in [
+            ("viewer --aa msaa3", "--aa expects off, msaa2, msaa4, msaa8 or fxaa, got msaa3"),
+            ("viewer --tone-map filmic", "--tone-map expects exposure, reinhard or aces, got filmic"),
+            ("viewer --exposure bright", "--exposure expects a number of stops, got bright"),
+            ("viewer --frame-rate 0", "--frame-rate expects frames per second, got 0"),
+        ] {
+            assert_eq!(parse_view_options(&args(line)).err().as_deref(), Some(message));
+        }
+        assert_eq!(parse_load_options(&args("viewer --normals bumpy")).err().as_deref(), Some("--normals expects flat, area or angle, got bumpy"));
+        assert_eq!(parse_turntable_options(&args("turntable model.obj --size 512")).err().as_deref(),
+                   Some("--size expects <width>x<height>, like 512x512, got 512"));
+    }
+
+    #[test]
+    fn missing_options_keep_their_defaults() {
+        let options = parse_view_options(&args("viewer --ssao high")).unwrap();
+        assert_eq!(options.ssao, SsaoQuality::High);
+        assert_eq!(options.capture_scale, 1);
+        assert_eq!(options.frame_rate, 30.0);
+        assert_eq!(parse_turntable_options(&args("turntable model.obj --angles 8")).unwrap().angles, 8);
+    }
 
+    #[test]
+    fn orbiting_keeps_the_centre_in_place() {
+        let view = view_matrix(&[2.0, -1.0, 1.0], &[-2.0, 1.0, 1.0], &[0.0, 1.0, 0.0]);
+        let center = [0.5, 0.25, 2.0];
+        let quarter_turn = orbit_view(&view, center, std::f32::consts::FRAC_PI_2);
+        let close = |a: [f32; 3], b: [f32; 3]| (0..3).all(|i| (a[i] - b[i]).abs() < 1e-5);
+        assert!(close(math::transform_point(&quarter_turn, center), math::transform_point(&view, center)));
+        // A point in front of the centre along +z is seen where one on its +x side was before
+        let front = math::add(center, [0.0, 0.0, 1.0]);
+        assert!(close(math::transform_point(&quarter_turn, front), math::transform_point(&view, math::add(center, [1.0, 0.0, 0.0]))));
+    }
+}
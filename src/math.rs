@@ -0,0 +1,145 @@
+// Small vector helpers shared by the mesh processing code.
+// Vectors are plain arrays so they can be copied straight into Vertex fields.
+
+pub fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1],
+     a[2] * b[0] - a[0] * b[2],
+     a[0] * b[1] - a[1] * b[0]]
+}
+
+pub fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+// Returns the zero vector for degenerate input instead of NaNs
+pub fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let len = length(a);
+    if len > f32::EPSILON {
+        scale(a, 1.0 / len)
+    } else {
+        [0.0, 0.0, 0.0]
+    }
+}
+
+// Column-major 4x4 matrices, laid out the same way glium uniforms expect them
+pub type Mat4 = [[f32; 4]; 4];
+
+pub const IDENTITY: Mat4 = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+// a * b, so b is applied first
+pub fn mat4_mul(a: &Mat4, b: &Mat4) -> Mat4 {
+    let mut result = [[0.0; 4]; 4];
+    for (column, b_column) in b.iter().enumerate() {
+        for row in 0..4 {
+            result[column][row] = (0..4).map(|k| a[k][row] * b_column[k]).sum();
+        }
+    }
+    result
+}
+
+pub fn mat4_transpose(m: &Mat4) -> Mat4 {
+    let mut result = [[0.0; 4]; 4];
+    for (column, values) in m.iter().enumerate() {
+        for (row, &value) in values.iter().enumerate() {
+            result[row][column] = value;
+        }
+    }
+    result
+}
+
+// Applies the full transform including translation and the perspective divide
+pub fn transform_point(m: &Mat4, p: [f32; 3]) -> [f32; 3] {
+    let w = m[0][3] * p[0] + m[1][3] * p[1] + m[2][3] * p[2] + m[3][3];
+    [
+        (m[0][0] * p[0] + m[1][0] * p[1] + m[2][0] * p[2] + m[3][0]) / w,
+        (m[0][1] * p[0] + m[1][1] * p[1] + m[2][1] * p[2] + m[3][1]) / w,
+        (m[0][2] * p[0] + m[1][2] * p[1] + m[2][2] * p[2] + m[3][2]) / w,
+    ]
+}
+
+// Applies only the upper 3x3 part, for directions
+pub fn transform_vector(m: &Mat4, v: [f32; 3]) -> [f32; 3] {
+    [
+        m[0][0] * v[0] + m[1][0] * v[1] + m[2][0] * v[2],
+        m[0][1] * v[0] + m[1][1] * v[1] + m[2][1] * v[2],
+        m[0][2] * v[0] + m[1][2] * v[1] + m[2][2] * v[2],
+    ]
+}
+
+// Same as transpose(inverse(mat3(m))) in teapot_gouraud.vert, keeps normals perpendicular under non-uniform scaling
+pub fn normal_matrix(m: &Mat4) -> Mat4 {
+    mat4_transpose(&mat4_inverse(m).unwrap_or(IDENTITY))
+}
+
+// Axis aligned box (min, max) around the eight corners of the box min..max after transforming them by m
+pub fn transform_bounds(m: &Mat4, min: [f32; 3], max: [f32; 3]) -> ([f32; 3], [f32; 3]) {
+    let mut result = (transform_point(m, min), transform_point(m, min));
+    for corner in 0..8 {
+        let p = transform_point(m, [0, 1, 2].map(|i| if corner & (1 << i) == 0 { min[i] } else { max[i] }));
+        result.0 = [0, 1, 2].map(|i| result.0[i].min(p[i]));
+        result.1 = [0, 1, 2].map(|i| result.1[i].max(p[i]));
+    }
+    result
+}
+
+// Determinant of the upper left 3x3, negative when the transform mirrors
+pub fn mat3_determinant(m: &Mat4) -> f32 {
+    let column = |i: usize| [m[i][0], m[i][1], m[i][2]];
+    dot(column(0), cross(column(1), column(2)))
+}
+
+// General inverse by cofactor expansion, returns None for singular matrices
+pub fn mat4_inverse(m: &Mat4) -> Option<Mat4> {
+    // Flatten so the usual row/column index formulas can be used directly, a[i] = m[i / 4][i % 4]
+    let a: Vec<f32> = m.iter().flatten().copied().collect();
+    let mut inv = [0.0f32; 16];
+
+    inv[0] = a[5] * a[10] * a[15] - a[5] * a[11] * a[14] - a[9] * a[6] * a[15] + a[9] * a[7] * a[14] + a[13] * a[6] * a[11] - a[13] * a[7] * a[10];
+    inv[4] = -a[4] * a[10] * a[15] + a[4] * a[11] * a[14] + a[8] * a[6] * a[15] - a[8] * a[7] * a[14] - a[12] * a[6] * a[11] + a[12] * a[7] * a[10];
+    inv[8] = a[4] * a[9] * a[15] - a[4] * a[11] * a[13] - a[8] * a[5] * a[15] + a[8] * a[7] * a[13] + a[12] * a[5] * a[11] - a[12] * a[7] * a[9];
+    inv[12] = -a[4] * a[9] * a[14] + a[4] * a[10] * a[13] + a[8] * a[5] * a[14] - a[8] * a[6] * a[13] - a[12] * a[5] * a[10] + a[12] * a[6] * a[9];
+    inv[1] = -a[1] * a[10] * a[15] + a[1] * a[11] * a[14] + a[9] * a[2] * a[15] - a[9] * a[3] * a[14] - a[13] * a[2] * a[11] + a[13] * a[3] * a[10];
+    inv[5] = a[0] * a[10] * a[15] - a[0] * a[11] * a[14] - a[8] * a[2] * a[15] + a[8] * a[3] * a[14] + a[12] * a[2] * a[11] - a[12] * a[3] * a[10];
+    inv[9] = -a[0] * a[9] * a[15] + a[0] * a[11] * a[13] + a[8] * a[1] * a[15] - a[8] * a[3] * a[13] - a[12] * a[1] * a[11] + a[12] * a[3] * a[9];
+    inv[13] = a[0] * a[9] * a[14] - a[0] * a[10] * a[13] - a[8] * a[1] * a[14] + a[8] * a[2] * a[13] + a[12] * a[1] * a[10] - a[12] * a[2] * a[9];
+    inv[2] = a[1] * a[6] * a[15] - a[1] * a[7] * a[14] - a[5] * a[2] * a[15] + a[5] * a[3] * a[14] + a[13] * a[2] * a[7] - a[13] * a[3] * a[6];
+    inv[6] = -a[0] * a[6] * a[15] + a[0] * a[7] * a[14] + a[4] * a[2] * a[15] - a[4] * a[3] * a[14] - a[12] * a[2] * a[7] + a[12] * a[3] * a[6];
+    inv[10] = a[0] * a[5] * a[15] - a[0] * a[7] * a[13] - a[4] * a[1] * a[15] + a[4] * a[3] * a[13] + a[12] * a[1] * a[7] - a[12] * a[3] * a[5];
+    inv[14] = -a[0] * a[5] * a[14] + a[0] * a[6] * a[13] + a[4] * a[1] * a[14] - a[4] * a[2] * a[13] - a[12] * a[1] * a[6] + a[12] * a[2] * a[5];
+    inv[3] = -a[1] * a[6] * a[11] + a[1] * a[7] * a[10] + a[5] * a[2] * a[11] - a[5] * a[3] * a[10] - a[9] * a[2] * a[7] + a[9] * a[3] * a[6];
+    inv[7] = a[0] * a[6] * a[11] - a[0] * a[7] * a[10] - a[4] * a[2] * a[11] + a[4] * a[3] * a[10] + a[8] * a[2] * a[7] - a[8] * a[3] * a[6];
+    inv[11] = -a[0] * a[5] * a[11] + a[0] * a[7] * a[9] + a[4] * a[1] * a[11] - a[4] * a[3] * a[9] - a[8] * a[1] * a[7] + a[8] * a[3] * a[5];
+    inv[15] = a[0] * a[5] * a[10] - a[0] * a[6] * a[9] - a[4] * a[1] * a[10] + a[4] * a[2] * a[9] + a[8] * a[1] * a[6] - a[8] * a[2] * a[5];
+
+    let det = a[0] * inv[0] + a[1] * inv[4] + a[2] * inv[8] + a[3] * inv[12];
+    if det.abs() <= f32::EPSILON * f32::EPSILON {
+        return None;
+    }
+
+    let mut result = [[0.0; 4]; 4];
+    for (i, value) in inv.iter().enumerate() {
+        result[i / 4][i % 4] = value / det;
+    }
+    Some(result)
+}
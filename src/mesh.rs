@@ -0,0 +1,175 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use glium::backend::Facade;
+use glium::index::{IndexBuffer, IndexBufferAny, IndexType, PrimitiveType};
+use obj::raw::object::Polygon;
+use obj::raw::RawObj;
+
+use crate::obj_polygons;
+use crate::Vertex;
+
+// Color given to vertices of formats that do not store one
+pub const DEFAULT_COLOR: [f32; 3] = [1.0, 0.0, 0.0];
+
+#[derive(Debug)]
+pub enum MeshError {
+    Io(io::Error),
+    Obj(obj::ObjError),
+    Gltf(gltf::Error),
+    Image(image::ImageError),
+    // The file is not in the format it looks like, or is truncated
+    InvalidData(String),
+    // A face referenced a position, texture coordinate or normal the file does not contain
+    IndexOutOfRange,
+    // More vertices than a u32 index can address
+    TooManyVertices(usize),
+    // The index buffer could not be created, e.g. the context does not support u32 indices
+    IndexBuffer(IndexType, glium::index::BufferCreationError),
+}
+
+impl fmt::Display for MeshError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MeshError::Io(err) => write!(f, "could not read mesh: {}", err),
+            MeshError::Obj(err) => write!(f, "could not parse OBJ: {}", err),
+            MeshError::Gltf(err) => write!(f, "could not load glTF: {}", err),
+            MeshError::Image(err) => write!(f, "could not read or write image: {}", err),
+            MeshError::InvalidData(message) => write!(f, "invalid mesh data: {}", message),
+            MeshError::IndexOutOfRange => write!(f, "a face references a vertex attribute that does not exist"),
+            MeshError::TooManyVertices(count) => write!(f, "mesh has {} vertices but at most {} can be indexed", count, u32::MAX as u64 + 1),
+            MeshError::IndexBuffer(index_type, err) => write!(f, "could not create {:?} index buffer: {}", index_type, err),
+        }
+    }
+}
+
+impl Error for MeshError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            MeshError::Io(err) => Some(err),
+            MeshError::Obj(err) => Some(err),
+            MeshError::Gltf(err) => Some(err),
+            MeshError::Image(err) => Some(err),
+            MeshError::IndexBuffer(_, err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for MeshError {
+    fn from(err: io::Error) -> Self {
+        MeshError::Io(err)
+    }
+}
+
+impl From<obj::ObjError> for MeshError {
+    fn from(err: obj::ObjError) -> Self {
+        MeshError::Obj(err)
+    }
+}
+
+impl From<gltf::Error> for MeshError {
+    fn from(err: gltf::Error) -> Self {
+        MeshError::Gltf(err)
+    }
+}
+
+impl From<image::ImageError> for MeshError {
+    fn from(err: image::ImageError) -> Self {
+        MeshError::Image(err)
+    }
+}
+
+// CPU side copy of a model so it can be processed before being sent to a vertex buffer.
+// Indices always describe a triangle list.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    // Builds a triangle list out of some polygons of a parsed OBJ file, fanning out any polygon with more than 3 corners.
+    // Unlike obj::Obj<obj::Vertex> this does not fail when the file has no `vn` entries,
+    // the second value returned tells the caller whether every corner had a normal.
+    // Texture coordinates are flipped to the top left origin, see obj_polygons::flip_v.
+    pub fn from_raw_obj<'a>(raw: &RawObj, polygons: impl IntoIterator<Item = &'a Polygon>) -> Result<(Mesh, bool), MeshError> {
+        let (corners, indices) = obj_polygons::triangulate(polygons);
+        u32::try_from(corners.len()).map_err(|_| MeshError::TooManyVertices(corners.len()))?;
+        let has_normals = corners.iter().all(|corner| corner.2.is_some());
+
+        let mut mesh = Mesh { vertices: Vec::with_capacity(corners.len()), indices: indices.into_iter().map(|index| index as u32).collect() };
+        for (p, t, n) in corners {
+            let position = raw.positions.get(p).ok_or(MeshError::IndexOutOfRange)?;
+            let tex_coords = match t {
+                Some(t) => raw.tex_coords.get(t).map(|t| obj_polygons::flip_v([t.0, t.1])).ok_or(MeshError::IndexOutOfRange)?,
+                None => [0.0, 0.0],
+            };
+            let normal = match n {
+                Some(n) => raw.normals.get(n).map(|n| [n.0, n.1, n.2]).ok_or(MeshError::IndexOutOfRange)?,
+                None => [0.0, 0.0, 0.0],
+            };
+            mesh.vertices.push(Vertex {
+                position: [position.0, position.1, position.2],
+                color: DEFAULT_COLOR,
+                normal,
+                tex_coords,
+                tangent: [0.0; 4],
+            });
+        }
+
+        Ok((mesh, has_normals))
+    }
+
+    // Uploads the indices using the narrowest type that can address every vertex, so small meshes keep using
+    // u16 while meshes over 65,536 vertices get u32. u8 is skipped on purpose as many drivers convert it on the CPU.
+    pub fn index_buffer<F: Facade + ?Sized>(&self, facade: &F, primitives: PrimitiveType) -> Result<IndexBufferAny, MeshError> {
+        let max_index = self.indices.iter().copied().max().unwrap_or(0);
+
+        match index_type(self.vertices.len(), max_index)? {
+            IndexType::U16 => {
+                let indices: Vec<u16> = self.indices.iter().map(|&i| i as u16).collect();
+                IndexBuffer::new(facade, primitives, &indices)
+                    .map(IndexBufferAny::from)
+                    .map_err(|err| MeshError::IndexBuffer(IndexType::U16, err))
+            }
+            _ => IndexBuffer::new(facade, primitives, &self.indices)
+                .map(IndexBufferAny::from)
+                .map_err(|err| MeshError::IndexBuffer(IndexType::U32, err)),
+        }
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+}
+
+// Index type for a mesh with `vertex_count` vertices whose indices go up to `max_index`, see Mesh::index_buffer
+fn index_type(vertex_count: usize, max_index: u32) -> Result<IndexType, MeshError> {
+    if vertex_count as u64 > u32::MAX as u64 + 1 {
+        Err(MeshError::TooManyVertices(vertex_count))
+    } else if max_index <= u16::MAX as u32 {
+        Ok(IndexType::U16)
+    } else {
+        Ok(IndexType::U32)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn index_type_switches_to_u32_past_65535() {
+        assert_eq!(index_type(65536, 65535).unwrap(), IndexType::U16);
+        assert_eq!(index_type(65537, 65536).unwrap(), IndexType::U32);
+        assert_eq!(index_type(0, 0).unwrap(), IndexType::U16);
+    }
+
+    #[test]
+    fn index_type_rejects_more_vertices_than_u32_can_address() {
+        assert_eq!(index_type(u32::MAX as usize + 1, u32::MAX).unwrap(), IndexType::U32);
+        assert!(matches!(index_type(u32::MAX as usize + 2, u32::MAX), Err(MeshError::TooManyVertices(count)) if count == u32::MAX as usize + 2));
+    }
+}
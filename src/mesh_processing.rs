@@ -0,0 +1,326 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::math;
+use crate::mesh::Mesh;
+use crate::Vertex;
+
+// Size of the simulated post-transform cache used for the statistics, roughly what GPUs have had for a while
+const STATS_CACHE_SIZE: usize = 16;
+
+// Tuning values from Tom Forsyth's "Linear-Speed Vertex Cache Optimisation"
+const FORSYTH_CACHE_SIZE: usize = 32;
+const CACHE_DECAY_POWER: f32 = 1.5;
+const LAST_TRIANGLE_SCORE: f32 = 0.75;
+const VALENCE_BOOST_SCALE: f32 = 2.0;
+const VALENCE_BOOST_POWER: f32 = 0.5;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MeshStats {
+    pub vertex_count: usize,
+    pub triangle_count: usize,
+    // Average cache miss ratio: vertex shader invocations per triangle, 0.5 is the best a regular grid can get and 3.0 the worst
+    pub acmr: f32,
+    // Average transform to vertex ratio: vertex shader invocations per vertex, 1.0 is optimal
+    pub atvr: f32,
+}
+
+impl MeshStats {
+    pub fn of(mesh: &Mesh) -> Self {
+        // FIFO cache like the fixed function hardware caches the ratios are usually quoted against
+        let mut cache: Vec<u32> = Vec::with_capacity(STATS_CACHE_SIZE);
+        let mut misses = 0;
+        for &index in &mesh.indices {
+            if !cache.contains(&index) {
+                misses += 1;
+                if cache.len() == STATS_CACHE_SIZE {
+                    cache.remove(0);
+                }
+                cache.push(index);
+            }
+        }
+
+        let triangle_count = mesh.triangle_count();
+        MeshStats {
+            vertex_count: mesh.vertices.len(),
+            triangle_count,
+            acmr: if triangle_count > 0 { misses as f32 / triangle_count as f32 } else { 0.0 },
+            atvr: if mesh.vertices.is_empty() { 0.0 } else { misses as f32 / mesh.vertices.len() as f32 },
+        }
+    }
+}
+
+impl fmt::Display for MeshStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} vertices, {} triangles, ACMR {:.3}, ATVR {:.3}", self.vertex_count, self.triangle_count, self.acmr, self.atvr)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+pub struct ProcessingReport {
+    pub before: MeshStats,
+    pub after: MeshStats,
+    pub welded_vertices: usize,
+    pub degenerate_triangles: usize,
+    pub unreferenced_vertices: usize,
+}
+
+impl fmt::Display for ProcessingReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "before: {}", self.before)?;
+        writeln!(f, "after:  {}", self.after)?;
+        write!(f, "welded {} vertices, removed {} degenerate triangles and {} unreferenced vertices",
+               self.welded_vertices, self.degenerate_triangles, self.unreferenced_vertices)
+    }
+}
+
+// Runs the whole cleanup pipeline: weld, drop degenerate triangles, reorder for the vertex cache, then drop unused vertices
+pub fn process(mesh: &Mesh, epsilon: f32) -> (Mesh, ProcessingReport) {
+    let before = MeshStats::of(mesh);
+
+    let (mesh, welded_vertices) = weld_vertices(mesh, epsilon);
+    let (mesh, degenerate_triangles) = remove_degenerate_triangles(&mesh);
+    // Last, so the vertices end up sorted by first use in the optimized triangle order
+    let mesh = optimize_vertex_cache(&mesh);
+    let (mesh, unreferenced_vertices) = remove_unreferenced_vertices(&mesh);
+
+    let report = ProcessingReport {
+        before,
+        after: MeshStats::of(&mesh),
+        welded_vertices,
+        degenerate_triangles,
+        unreferenced_vertices,
+    };
+    (mesh, report)
+}
+
+fn vertices_match(a: &Vertex, b: &Vertex, epsilon: f32) -> bool {
+    let close = |a: &[f32], b: &[f32]| a.iter().zip(b).all(|(x, y)| (x - y).abs() <= epsilon);
+    close(&a.position, &b.position) && close(&a.normal, &b.normal) && close(&a.color, &b.color) && close(&a.tex_coords, &b.tex_coords) && close(&a.tangent, &b.tangent)
+}
+
+// Merges vertices whose attributes all lie within epsilon of each other, keeping the first one seen.
+// Positions are bucketed in a grid of epsilon sized cells so only neighbouring cells need to be compared.
+// Returns the welded mesh and how many vertices were merged away.
+pub fn weld_vertices(mesh: &Mesh, epsilon: f32) -> (Mesh, usize) {
+    let cell_size = epsilon.max(f32::MIN_POSITIVE);
+    let cell_of = |position: [f32; 3]| position.map(|x| (x / cell_size).floor() as i64);
+
+    let mut grid: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+    let mut remap = Vec::with_capacity(mesh.vertices.len());
+    let mut vertices: Vec<Vertex> = Vec::new();
+
+    for vertex in &mesh.vertices {
+        let cell = cell_of(vertex.position);
+        let mut found = None;
+        'search: for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    if let Some(candidates) = grid.get(&[cell[0] + dx, cell[1] + dy, cell[2] + dz]) {
+                        if let Some(&index) = candidates.iter().find(|&&i| vertices_match(&vertices[i as usize], vertex, epsilon)) {
+                            found = Some(index);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+        }
+
+        let index = found.unwrap_or_else(|| {
+            vertices.push(*vertex);
+            let index = (vertices.len() - 1) as u32;
+            grid.entry(cell).or_default().push(index);
+            index
+        });
+        remap.push(index);
+    }
+
+    let welded = mesh.vertices.len() - vertices.len();
+    let indices = mesh.indices.iter().map(|&i| remap[i as usize]).collect();
+    (Mesh { vertices, indices }, welded)
+}
+
+// Drops triangles that reference the same vertex twice or have no area. Returns the mesh and the number of triangles removed.
+pub fn remove_degenerate_triangles(mesh: &Mesh) -> (Mesh, usize) {
+    let mut indices = Vec::with_capacity(mesh.indices.len());
+    for triangle in mesh.indices.chunks_exact(3) {
+        let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+        if a == b || b == c || a == c {
+            continue;
+        }
+        let [pa, pb, pc] = [a, b, c].map(|i| mesh.vertices[i as usize].position);
+        if math::cross(math::sub(pb, pa), math::sub(pc, pa)) == [0.0, 0.0, 0.0] {
+            continue;
+        }
+        indices.extend_from_slice(triangle);
+    }
+
+    let removed = (mesh.indices.len() - indices.len()) / 3;
+    (Mesh { vertices: mesh.vertices.clone(), indices }, removed)
+}
+
+// Drops vertices no triangle uses, such as the dummy first vertex of glium_teapot::VERTICES.
+// Remaining vertices are stored in the order the index buffer first touches them, which also helps the pre-transform fetch.
+pub fn remove_unreferenced_vertices(mesh: &Mesh) -> (Mesh, usize) {
+    let mut remap = vec![u32::MAX; mesh.vertices.len()];
+    let mut vertices = Vec::new();
+    let indices = mesh.indices.iter().map(|&i| {
+        if remap[i as usize] == u32::MAX {
+            remap[i as usize] = vertices.len() as u32;
+            vertices.push(mesh.vertices[i as usize]);
+        }
+        remap[i as usize]
+    }).collect();
+
+    let removed = mesh.vertices.len() - vertices.len();
+    (Mesh { vertices, indices }, removed)
+}
+
+fn forsyth_vertex_score(cache_position: Option<usize>, remaining_triangles: usize) -> f32 {
+    if remaining_triangles == 0 {
+        return -1.0;
+    }
+
+    let cache_score = match cache_position {
+        None => 0.0,
+        // The three vertices of the triangle just added get a fixed score so the next triangle does not simply reuse one edge
+        Some(position) if position < 3 => LAST_TRIANGLE_SCORE,
+        Some(position) => {
+            let scaler = 1.0 / (FORSYTH_CACHE_SIZE - 3) as f32;
+            (1.0 - (position - 3) as f32 * scaler).powf(CACHE_DECAY_POWER)
+        }
+    };
+    // Vertices with few triangles left are finished off first so they can leave the cache for good
+    let valence_boost = VALENCE_BOOST_SCALE * (remaining_triangles as f32).powf(-VALENCE_BOOST_POWER);
+    cache_score + valence_boost
+}
+
+// Reorders triangles for the post-transform vertex cache using Tom Forsyth's greedy algorithm.
+// Vertices are left where they are, call remove_unreferenced_vertices afterwards to sort them by first use.
+pub fn optimize_vertex_cache(mesh: &Mesh) -> Mesh {
+    let triangle_count = mesh.triangle_count();
+    let vertex_count = mesh.vertices.len();
+
+    let mut vertex_triangles: Vec<Vec<usize>> = vec![Vec::new(); vertex_count];
+    for (triangle, corners) in mesh.indices.chunks_exact(3).enumerate() {
+        for &index in corners {
+            vertex_triangles[index as usize].push(triangle);
+        }
+    }
+
+    let mut vertex_scores: Vec<f32> = vertex_triangles.iter().map(|triangles| forsyth_vertex_score(None, triangles.len())).collect();
+    let triangle_score = |triangle: usize, vertex_scores: &[f32]| -> f32 {
+        mesh.indices[triangle * 3..triangle * 3 + 3].iter().map(|&i| vertex_scores[i as usize]).sum()
+    };
+    let mut triangle_scores: Vec<f32> = (0..triangle_count).map(|t| triangle_score(t, &vertex_scores)).collect();
+    let mut triangle_added = vec![false; triangle_count];
+
+    let mut cache: Vec<u32> = Vec::with_capacity(FORSYTH_CACHE_SIZE + 3);
+    let mut indices = Vec::with_capacity(mesh.indices.len());
+    let mut best_triangle = (0..triangle_count).max_by(|&a, &b| triangle_scores[a].total_cmp(&triangle_scores[b]));
+
+    while let Some(triangle) = best_triangle {
+        triangle_added[triangle] = true;
+        let corners = &mesh.indices[triangle * 3..triangle * 3 + 3];
+        indices.extend_from_slice(corners);
+
+        // Move the triangle's vertices to the front of the LRU cache, the ones pushed past the end fall out
+        for &index in corners.iter().rev() {
+            cache.retain(|&cached| cached != index);
+            cache.insert(0, index);
+            vertex_triangles[index as usize].retain(|&t| t != triangle);
+        }
+        let evicted: Vec<u32> = cache.split_off(cache.len().min(FORSYTH_CACHE_SIZE));
+
+        for &index in &evicted {
+            vertex_scores[index as usize] = forsyth_vertex_score(None, vertex_triangles[index as usize].len());
+        }
+        for (position, &index) in cache.iter().enumerate() {
+            vertex_scores[index as usize] = forsyth_vertex_score(Some(position), vertex_triangles[index as usize].len());
+        }
+
+        // Only triangles touching a changed vertex need rescoring, and the next pick is nearly always one of them
+        best_triangle = None;
+        let mut best_score = f32::MIN;
+        for &index in cache.iter().chain(&evicted) {
+            for &t in &vertex_triangles[index as usize] {
+                triangle_scores[t] = triangle_score(t, &vertex_scores);
+                if triangle_scores[t] > best_score {
+                    best_score = triangle_scores[t];
+                    best_triangle = Some(t);
+                }
+            }
+        }
+
+        // Nothing left around the cache, fall back to the best remaining triangle anywhere in the mesh
+        if best_triangle.is_none() {
+            best_triangle = (0..triangle_count)
+                .filter(|&t| !triangle_added[t])
+                .max_by(|&a, &b| triangle_scores[a].total_cmp(&triangle_scores[b]));
+        }
+    }
+
+    Mesh { vertices: mesh.vertices.clone(), indices }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vertex(position: [f32; 3]) -> Vertex {
+        Vertex { position, color: [1.0; 3], normal: [0.0, 0.0, 1.0], tex_coords: [0.0; 2], tangent: [0.0; 4] }
+    }
+
+    fn teapot() -> Mesh {
+        let scene = crate::load_model("models/obj/teapot.obj", &crate::LoadOptions::default()).unwrap();
+        scene.meshes[0].primitives[0].mesh.clone()
+    }
+
+    #[test]
+    fn weld_merges_vertices_within_epsilon_only() {
+        let mesh = Mesh {
+            vertices: vec![vertex([0.0, 0.0, 0.0]), vertex([0.0005, 0.0, 0.0]), vertex([0.01, 0.0, 0.0]), vertex([0.0, 1.0, 0.0])],
+            indices: vec![0, 2, 3, 1, 2, 3],
+        };
+        let (welded, merged) = weld_vertices(&mesh, 0.001);
+        assert_eq!(merged, 1);
+        assert_eq!(welded.vertices.len(), 3);
+        assert_eq!(welded.indices, vec![0, 1, 2, 0, 1, 2]);
+
+        // Differing attributes keep vertices apart even at the same position
+        let mesh = Mesh { vertices: vec![vertex([0.0; 3]), Vertex { normal: [0.0, 1.0, 0.0], ..vertex([0.0; 3]) }], indices: Vec::new() };
+        assert_eq!(weld_vertices(&mesh, 0.001).1, 0);
+    }
+
+    #[test]
+    fn degenerate_triangles_are_removed() {
+        let mesh = Mesh {
+            vertices: vec![vertex([0.0, 0.0, 0.0]), vertex([1.0, 0.0, 0.0]), vertex([0.0, 1.0, 0.0]), vertex([2.0, 0.0, 0.0])],
+            // A good triangle, one repeating a vertex and one with its corners on a line
+            indices: vec![0, 1, 2, 0, 0, 1, 0, 1, 3],
+        };
+        let (cleaned, removed) = remove_degenerate_triangles(&mesh);
+        assert_eq!(removed, 2);
+        assert_eq!(cleaned.indices, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn processing_the_teapot_lowers_acmr() {
+        let mesh = teapot();
+        let (processed, report) = process(&mesh, 1e-5);
+        assert_eq!(report.before, MeshStats::of(&mesh));
+        assert_eq!(report.after.triangle_count, report.before.triangle_count - report.degenerate_triangles);
+        assert!(report.after.acmr < report.before.acmr, "{}", report);
+        assert!(report.after.acmr < 1.0, "{}", report);
+
+        // Vertices come out in the order the optimized index buffer first uses them
+        let mut next = 0;
+        for &index in &processed.indices {
+            assert!(index <= next);
+            if index == next {
+                next += 1;
+            }
+        }
+        assert_eq!(next as usize, processed.vertices.len());
+    }
+}
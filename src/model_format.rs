@@ -0,0 +1,78 @@
+use std::path::Path;
+
+use crate::ply_import;
+use crate::stl_import;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum ModelFormat {
+    Obj,
+    // Either .gltf JSON or .glb binary, gltf::import handles both
+    Gltf,
+    Stl,
+    Ply,
+}
+
+impl ModelFormat {
+    // Magic bytes win over the extension since files get renamed or downloaded without one.
+    // ASCII STL and glTF JSON only have weak signatures, so those are checked after the extension.
+    pub fn detect(path: &Path, data: &[u8]) -> ModelFormat {
+        if data.starts_with(b"glTF") {
+            return ModelFormat::Gltf;
+        }
+        if ply_import::is_ply(data) {
+            return ModelFormat::Ply;
+        }
+        if stl_import::is_binary_stl(data) {
+            return ModelFormat::Stl;
+        }
+
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_lowercase();
+        match extension.as_str() {
+            "obj" => ModelFormat::Obj,
+            "gltf" | "glb" => ModelFormat::Gltf,
+            "stl" => ModelFormat::Stl,
+            "ply" => ModelFormat::Ply,
+            _ if stl_import::is_ascii_stl(data) => ModelFormat::Stl,
+            _ if data.trim_ascii_start().starts_with(b"{") => ModelFormat::Gltf,
+            _ => ModelFormat::Obj,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary_stl() -> Vec<u8> {
+        let mut data = vec![0u8; 80];
+        data.extend_from_slice(&1u32.to_le_bytes());
+        data.resize(80 + 4 + 50, 0);
+        data
+    }
+
+    #[test]
+    fn extension_decides_without_magic_bytes() {
+        let text = b"v 0 0 0\n";
+        assert_eq!(ModelFormat::detect(Path::new("model.obj"), text), ModelFormat::Obj);
+        assert_eq!(ModelFormat::detect(Path::new("model.OBJ"), text), ModelFormat::Obj);
+        assert_eq!(ModelFormat::detect(Path::new("model.gltf"), b"{}"), ModelFormat::Gltf);
+        assert_eq!(ModelFormat::detect(Path::new("model.stl"), b"solid x"), ModelFormat::Stl);
+        assert_eq!(ModelFormat::detect(Path::new("model.ply"), text), ModelFormat::Ply);
+    }
+
+    #[test]
+    fn magic_bytes_win_over_the_extension() {
+        assert_eq!(ModelFormat::detect(Path::new("model.obj"), b"glTF\x02\0\0\0"), ModelFormat::Gltf);
+        assert_eq!(ModelFormat::detect(Path::new("model.stl"), b"ply\nformat ascii 1.0\n"), ModelFormat::Ply);
+        assert_eq!(ModelFormat::detect(Path::new("model.obj"), &binary_stl()), ModelFormat::Stl);
+    }
+
+    #[test]
+    fn weak_signatures_only_apply_without_a_known_extension() {
+        // "solid" inside an OBJ named .obj stays an OBJ
+        assert_eq!(ModelFormat::detect(Path::new("model.obj"), b"solid\n"), ModelFormat::Obj);
+        assert_eq!(ModelFormat::detect(Path::new("download"), b"solid x\nendsolid x\n"), ModelFormat::Stl);
+        assert_eq!(ModelFormat::detect(Path::new("download"), b"  {\"asset\":{}}"), ModelFormat::Gltf);
+        assert_eq!(ModelFormat::detect(Path::new("download"), b"v 0 0 0\n"), ModelFormat::Obj);
+    }
+}
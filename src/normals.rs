@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use crate::math;
+use crate::mesh::Mesh;
+use crate::Vertex;
+
+// How much each face contributes to a shared vertex normal
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NormalWeighting {
+    // Larger faces pull the normal towards them
+    Area,
+    // Faces contribute by the angle of the corner touching the vertex, which does not depend on how the surface was triangulated
+    Angle,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NormalMode {
+    // Every triangle uses its face normal, vertices are split wherever the faces around them are not coplanar
+    Flat,
+    // Normals are averaged over the faces around a position. Faces whose normals differ by more than
+    // crease_angle (radians) are not averaged together, so the vertex is split along that hard edge.
+    Smooth { weighting: NormalWeighting, crease_angle: f32 },
+}
+
+impl Default for NormalMode {
+    fn default() -> Self {
+        NormalMode::Smooth { weighting: NormalWeighting::Angle, crease_angle: 60f32.to_radians() }
+    }
+}
+
+// Replaces the normals of a mesh. Only the positions and the index list of the input are used to compute normals,
+// colors and texture coordinates are carried over to the vertices of the result.
+pub fn generate_normals(mesh: &Mesh, mode: NormalMode) -> Mesh {
+    match mode {
+        NormalMode::Flat => flat_normals(mesh),
+        NormalMode::Smooth { weighting, crease_angle } => smooth_normals(mesh, weighting, crease_angle),
+    }
+}
+
+// Unnormalized face normal, its length is twice the area of the triangle
+fn face_normal(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    math::cross(math::sub(b, a), math::sub(c, a))
+}
+
+// Angle at corner a of the triangle abc
+fn corner_angle(a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> f32 {
+    let ab = math::normalize(math::sub(b, a));
+    let ac = math::normalize(math::sub(c, a));
+    math::dot(ab, ac).clamp(-1.0, 1.0).acos()
+}
+
+// Hashable normal for welding, with -0.0 and 0.0 counted as the same value
+fn normal_key(normal: [f32; 3]) -> [u32; 3] {
+    normal.map(|value| (value + 0.0).to_bits())
+}
+
+fn flat_normals(mesh: &Mesh) -> Mesh {
+    let mut result = Mesh::default();
+    // Coplanar neighbours sharing a source vertex still share it, e.g. the two triangles of a cube face
+    let mut split_vertices: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+    for triangle in mesh.indices.chunks_exact(3) {
+        let position = |i: usize| mesh.vertices[triangle[i] as usize].position;
+        let normal = math::normalize(face_normal(position(0), position(1), position(2)));
+        for &source in triangle {
+            let index = *split_vertices.entry((source, normal_key(normal))).or_insert_with(|| {
+                result.vertices.push(Vertex { normal, ..mesh.vertices[source as usize] });
+                (result.vertices.len() - 1) as u32
+            });
+            result.indices.push(index);
+        }
+    }
+    result
+}
+
+fn smooth_normals(mesh: &Mesh, weighting: NormalWeighting, crease_angle: f32) -> Mesh {
+    let position = |corner: usize| mesh.vertices[mesh.indices[corner] as usize].position;
+    let triangle_count = mesh.triangle_count();
+
+    let face_normals: Vec<[f32; 3]> = (0..triangle_count)
+        .map(|face| face_normal(position(face * 3), position(face * 3 + 1), position(face * 3 + 2)))
+        .collect();
+    let unit_face_normals: Vec<[f32; 3]> = face_normals.iter().map(|&n| math::normalize(n)).collect();
+
+    // Weight of every corner of every face for the faces around its position
+    let corner_weights: Vec<f32> = (0..triangle_count * 3)
+        .map(|corner| {
+            let face = corner / 3;
+            match weighting {
+                NormalWeighting::Area => math::length(face_normals[face]),
+                NormalWeighting::Angle => {
+                    let next = face * 3 + (corner + 1) % 3;
+                    let prev = face * 3 + (corner + 2) % 3;
+                    corner_angle(position(corner), position(next), position(prev))
+                }
+            }
+        })
+        .collect();
+
+    // Group corners by position rather than by vertex index so that texture seams do not break the smoothing
+    let mut corners_at_position: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+    for corner in 0..triangle_count * 3 {
+        let key = position(corner).map(f32::to_bits);
+        corners_at_position.entry(key).or_default().push(corner);
+    }
+
+    let cos_crease = crease_angle.cos();
+    let mut result = Mesh::default();
+    let mut split_vertices: HashMap<(u32, [u32; 3]), u32> = HashMap::new();
+
+    for corner in 0..triangle_count * 3 {
+        let own_normal = unit_face_normals[corner / 3];
+        let degenerate = own_normal == [0.0, 0.0, 0.0];
+
+        let mut sum = [0.0, 0.0, 0.0];
+        for &other in &corners_at_position[&position(corner).map(f32::to_bits)] {
+            let other_normal = unit_face_normals[other / 3];
+            if degenerate || math::dot(own_normal, other_normal) >= cos_crease {
+                sum = math::add(sum, math::scale(other_normal, corner_weights[other]));
+            }
+        }
+        let normal = math::normalize(sum);
+
+        // Corners sharing a source vertex and ending up with the same normal are welded back together
+        let source = mesh.indices[corner];
+        let index = *split_vertices.entry((source, normal_key(normal))).or_insert_with(|| {
+            result.vertices.push(Vertex { normal, ..mesh.vertices[source as usize] });
+            (result.vertices.len() - 1) as u32
+        });
+        result.indices.push(index);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mesh(positions: &[[f32; 3]], indices: &[u32]) -> Mesh {
+        let vertices = positions.iter().map(|&position| Vertex { position, color: [1.0; 3], normal: [0.0; 3], tex_coords: [0.0; 2], tangent: [0.0; 4] }).collect();
+        Mesh { vertices, indices: indices.to_vec() }
+    }
+
+    // Unit cube with its 8 corners shared by all 12 triangles, wound counter-clockwise seen from outside
+    fn shared_cube() -> Mesh {
+        let positions: Vec<[f32; 3]> = (0..8).map(|i| [(i & 1) as f32, (i >> 1 & 1) as f32, (i >> 2 & 1) as f32]).collect();
+        let indices = [
+            0, 2, 1, 1, 2, 3, // -z
+            4, 5, 6, 5, 7, 6, // +z
+            0, 1, 4, 1, 5, 4, // -y
+            2, 6, 3, 3, 6, 7, // +y
+            0, 4, 2, 2, 4, 6, // -x
+            1, 3, 5, 3, 7, 5, // +x
+        ];
+        mesh(&positions, &indices)
+    }
+
+    fn smooth(weighting: NormalWeighting, degrees: f32) -> NormalMode {
+        NormalMode::Smooth { weighting, crease_angle: degrees.to_radians() }
+    }
+
+    fn assert_close(a: [f32; 3], b: [f32; 3]) {
+        assert!((0..3).all(|i| (a[i] - b[i]).abs() < 1e-5), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn flat_cube_splits_into_faces() {
+        let result = generate_normals(&shared_cube(), NormalMode::Flat);
+        assert_eq!(result.vertices.len(), 24);
+        assert_eq!(result.indices.len(), 36);
+        for triangle in result.indices.chunks_exact(3) {
+            let normals: Vec<[f32; 3]> = triangle.iter().map(|&index| result.vertices[index as usize].normal).collect();
+            assert!(normals.iter().all(|&normal| normal == normals[0]));
+            assert_eq!(normals[0].iter().filter(|value| value.abs() == 1.0).count(), 1);
+        }
+    }
+
+    #[test]
+    fn crease_angle_below_90_degrees_keeps_cube_faces_split() {
+        let result = generate_normals(&shared_cube(), smooth(NormalWeighting::Angle, 80.0));
+        assert_eq!(result.vertices.len(), 24);
+    }
+
+    #[test]
+    fn crease_angle_above_90_degrees_welds_cube_corners() {
+        let result = generate_normals(&shared_cube(), smooth(NormalWeighting::Angle, 100.0));
+        assert_eq!(result.vertices.len(), 8);
+        for vertex in &result.vertices {
+            let outwards = vertex.position.map(|value| value - 0.5);
+            assert_close(vertex.normal, math::normalize(outwards));
+        }
+    }
+
+    #[test]
+    fn area_and_angle_weighting_differ_on_an_asymmetric_fan() {
+        // A large triangle facing +z and a small one facing +x, both with a right angle at the shared vertex 0
+        let fan = mesh(&[[0.0, 0.0, 0.0], [10.0, 0.0, 0.0], [0.0, 10.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]], &[0, 1, 2, 0, 3, 4]);
+        let normal_at_origin = |weighting| {
+            let result = generate_normals(&fan, smooth(weighting, 180.0));
+            result.vertices[result.indices[0] as usize].normal
+        };
+        // Areas 50 and 0.5
+        assert_close(normal_at_origin(NormalWeighting::Area), math::normalize([0.5, 0.0, 50.0]));
+        // Equal angles
+        assert_close(normal_at_origin(NormalWeighting::Angle), math::normalize([1.0, 0.0, 1.0]));
+    }
+}
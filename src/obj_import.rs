@@ -0,0 +1,168 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use obj::raw::material::{Material as MtlMaterial, MtlColor};
+
+use crate::mesh::{Mesh, MeshError};
+use crate::scene::{AlphaMode, FaceCulling, Material, Node, Primitive, Scene, SceneMesh, Texture};
+
+// Loads an OBJ file together with the materials of its MTL libraries, one primitive per `usemtl` material.
+// Files without materials become a single primitive with the default material. The second value tells whether
+// every face had normals. Missing MTL files or textures are reported and skipped rather than failing the load.
+pub fn load_obj(path: &Path, data: &[u8]) -> Result<(Scene, bool), MeshError> {
+    let raw = obj::raw::parse_obj(data)?;
+    let name = path.to_string_lossy();
+    if raw.material_libraries.is_empty() {
+        let (mesh, has_normals) = Mesh::from_raw_obj(&raw, &raw.polygons)?;
+        return Ok((Scene::from_mesh(&name, mesh), has_normals));
+    }
+
+    let directory = path.parent().unwrap_or(Path::new(""));
+    let mut scene = Scene::default();
+    let mut material_indices: HashMap<String, usize> = HashMap::new();
+    for library in &raw.material_libraries {
+        let mtl = match fs::read(directory.join(library)) {
+            Ok(mtl) => obj::raw::parse_mtl(mtl.as_slice())?,
+            Err(err) => {
+                eprintln!("Could not read material library {}: {}", library, err);
+                continue;
+            }
+        };
+        // Sorted so material indices stay the same between runs
+        let mut materials: Vec<(String, MtlMaterial)> = mtl.materials.into_iter().collect();
+        materials.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, mtl_material) in materials {
+            material_indices.insert(name.clone(), scene.materials.len());
+            let material = convert_material(name, &mtl_material, directory, &mut scene.textures);
+            scene.materials.push(material);
+        }
+    }
+
+    // Material groups in the order they first appear in the file
+    let mut groups: Vec<(&String, Vec<usize>)> = raw.meshes.iter().map(|(name, group)| {
+        (name, group.polygons.iter().flat_map(|range| range.start..range.end).collect())
+    }).collect();
+    groups.sort_by_key(|(_, polygons)| polygons.first().copied());
+
+    let mut primitives = Vec::new();
+    let mut has_normals = true;
+    let mut grouped = HashSet::new();
+    for (material_name, polygons) in &groups {
+        grouped.extend(polygons.iter().copied());
+        let (mesh, group_has_normals) = Mesh::from_raw_obj(&raw, polygons.iter().map(|&i| &raw.polygons[i]))?;
+        has_normals &= group_has_normals;
+        // obj-rs files faces before the first usemtl under an empty name
+        if !material_name.is_empty() && !material_indices.contains_key(*material_name) {
+            eprintln!("Material {} is not in any material library, using the default", material_name);
+        }
+        primitives.push(Primitive { mesh, material: material_indices.get(*material_name).copied() });
+    }
+    // Anything obj-rs left out of the material groups
+    let ungrouped: Vec<_> = raw.polygons.iter().enumerate().filter(|(i, _)| !grouped.contains(i)).map(|(_, polygon)| polygon).collect();
+    if !ungrouped.is_empty() {
+        let (mesh, ungrouped_has_normals) = Mesh::from_raw_obj(&raw, ungrouped)?;
+        has_normals &= ungrouped_has_normals;
+        primitives.push(Primitive { mesh, material: None });
+    }
+    primitives.retain(|primitive| !primitive.mesh.indices.is_empty());
+
+    scene.meshes.push(SceneMesh { name: name.to_string(), primitives });
+    scene.nodes.push(Node { name: name.to_string(), transform: crate::math::IDENTITY, mesh: Some(0), camera: None, children: Vec::new() });
+    scene.roots.push(0);
+    Ok((scene, has_normals))
+}
+
+fn convert_material(name: String, mtl: &MtlMaterial, directory: &Path, textures: &mut Vec<Texture>) -> Material {
+    let rgb = |color: &Option<MtlColor>, default: [f32; 3]| match color {
+        Some(MtlColor::Rgb(r, g, b)) => [*r, *g, *b],
+        // XYZ and spectral colors are not worth converting for a viewer
+        _ => default,
+    };
+    let diffuse = rgb(&mtl.diffuse, [1.0, 1.0, 1.0]);
+    let opacity = mtl.dissolve.unwrap_or(1.0);
+
+    let texture = mtl.diffuse_map.as_ref().and_then(|map| match load_texture(&directory.join(&map.file)) {
+        Ok(texture) => Some(texture),
+        Err(err) => {
+            eprintln!("Could not load texture {}: {}", map.file, err);
+            None
+        }
+    });
+    // Textures with see-through texels need blending just like a material with `d` below 1
+    let translucent_texture = texture.as_ref().is_some_and(|texture| texture.rgba.chunks_exact(4).any(|texel| texel[3] < 255));
+    let base_color_texture = texture.map(|texture| {
+        textures.push(texture);
+        textures.len() - 1
+    });
+
+    Material {
+        name,
+        base_color: [diffuse[0], diffuse[1], diffuse[2], opacity],
+        base_color_texture,
+        metallic: 0.0,
+        // Rough match of a Phong exponent to a roughness
+        roughness: mtl.specular_exponent.map_or(1.0, |exponent| (2.0 / (exponent.max(0.0) + 2.0)).sqrt()),
+        emissive: rgb(&mtl.emissive, [0.0, 0.0, 0.0]),
+        alpha_mode: if opacity < 1.0 || translucent_texture { AlphaMode::Blend } else { AlphaMode::Opaque },
+        culling: FaceCulling::Back,
+    }
+}
+
+fn load_texture(path: &Path) -> Result<Texture, MeshError> {
+    let image = image::open(path)?.to_rgba8();
+    Ok(Texture { width: image.width(), height: image.height(), rgba: image.into_raw() })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OBJ: &str = "mtllib materials.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vn 0 0 1
+usemtl solid
+f 1/1/1 2/2/1 3/3/1
+usemtl glass
+f 1/1/1 3/3/1 4/2/1
+usemtl solid
+f 2/2/1 3/3/1 4/1/1
+";
+
+    const MTL: &str = "newmtl solid
+Kd 1 0 0
+newmtl glass
+Kd 0 0 1
+d 0.5
+";
+
+    #[test]
+    fn faces_are_grouped_per_material_and_translucent_ones_blend() {
+        let directory = std::env::temp_dir().join(format!("rust-glium-renderer-obj-import-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(directory.join("materials.mtl"), MTL).unwrap();
+        let (scene, has_normals) = load_obj(&directory.join("model.obj"), OBJ.as_bytes()).unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+        assert!(has_normals);
+
+        // Both `usemtl solid` runs end up in one primitive, which comes first since its faces do
+        let primitives = &scene.meshes[0].primitives;
+        assert_eq!(primitives.len(), 2);
+        let solid = scene.material(&primitives[0]);
+        let glass = scene.material(&primitives[1]);
+        assert_eq!((solid.name.as_str(), primitives[0].mesh.triangle_count()), ("solid", 2));
+        assert_eq!((glass.name.as_str(), primitives[1].mesh.triangle_count()), ("glass", 1));
+
+        assert_eq!(solid.alpha_mode, AlphaMode::Opaque);
+        assert_eq!(glass.alpha_mode, AlphaMode::Blend);
+        assert_eq!(glass.base_color, [0.0, 0.0, 1.0, 0.5]);
+        // v is flipped to the top left origin
+        assert_eq!(primitives[0].mesh.vertices[2].tex_coords, [1.0, 0.0]);
+    }
+}
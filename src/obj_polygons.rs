@@ -0,0 +1,39 @@
+use std::collections::HashMap;
+
+use obj::raw::object::Polygon;
+
+// Indices of one corner of an OBJ polygon into the position, texture coordinate and normal lists
+pub type Corner = (usize, Option<usize>, Option<usize>);
+
+// OBJ and PLY texture coordinates start at the bottom of the image, glTF and Scene::textures start at the top.
+// Flipping v is its own inverse, so this converts both ways.
+pub fn flip_v(tex_coords: [f32; 2]) -> [f32; 2] {
+    [tex_coords[0], 1.0 - tex_coords[1]]
+}
+
+// Fans OBJ polygons into a triangle list, polygons in OBJ files are expected to be convex. Corners using the same
+// position, texture coordinate and normal become one vertex. Returns the vertices' corners in the order they are first
+// used and the triangle indices into them. build.rs embeds meshes with this too, so it only depends on the obj crate.
+pub fn triangulate<'a>(polygons: impl IntoIterator<Item = &'a Polygon>) -> (Vec<Corner>, Vec<usize>) {
+    let mut corners: Vec<Corner> = Vec::new();
+    let mut indices = Vec::new();
+    let mut cache: HashMap<Corner, usize> = HashMap::new();
+
+    for polygon in polygons {
+        let polygon_corners: Vec<Corner> = match polygon {
+            Polygon::P(vec) => vec.iter().map(|&p| (p, None, None)).collect(),
+            Polygon::PT(vec) => vec.iter().map(|&(p, t)| (p, Some(t), None)).collect(),
+            Polygon::PN(vec) => vec.iter().map(|&(p, n)| (p, None, Some(n))).collect(),
+            Polygon::PTN(vec) => vec.iter().map(|&(p, t, n)| (p, Some(t), Some(n))).collect(),
+        };
+        let polygon_indices: Vec<usize> = polygon_corners.into_iter().map(|corner| *cache.entry(corner).or_insert_with(|| {
+            corners.push(corner);
+            corners.len() - 1
+        })).collect();
+
+        for i in 1..polygon_indices.len().saturating_sub(1) {
+            indices.extend_from_slice(&[polygon_indices[0], polygon_indices[i], polygon_indices[i + 1]]);
+        }
+    }
+    (corners, indices)
+}
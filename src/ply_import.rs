@@ -0,0 +1,313 @@
+use std::str::SplitAsciiWhitespace;
+
+use crate::mesh::{Mesh, MeshError, DEFAULT_COLOR};
+use crate::obj_polygons;
+use crate::Vertex;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Encoding {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum ScalarType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Result<Self, MeshError> {
+        Ok(match name {
+            "char" | "int8" => ScalarType::I8,
+            "uchar" | "uint8" => ScalarType::U8,
+            "short" | "int16" => ScalarType::I16,
+            "ushort" | "uint16" => ScalarType::U16,
+            "int" | "int32" => ScalarType::I32,
+            "uint" | "uint32" => ScalarType::U32,
+            "float" | "float32" => ScalarType::F32,
+            "double" | "float64" => ScalarType::F64,
+            _ => return Err(invalid(&format!("unknown property type {}", name))),
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::I8 | ScalarType::U8 => 1,
+            ScalarType::I16 | ScalarType::U16 => 2,
+            ScalarType::I32 | ScalarType::U32 | ScalarType::F32 => 4,
+            ScalarType::F64 => 8,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Property {
+    Scalar { name: String, ty: ScalarType },
+    List { name: String, count_ty: ScalarType, item_ty: ScalarType },
+}
+
+#[derive(Clone, Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+fn invalid(message: &str) -> MeshError {
+    MeshError::InvalidData(format!("PLY: {}", message))
+}
+
+pub fn is_ply(data: &[u8]) -> bool {
+    data.starts_with(b"ply\n") || data.starts_with(b"ply\r\n")
+}
+
+// Reads values from the body in whichever encoding the header declared
+enum Body<'a> {
+    Ascii(SplitAsciiWhitespace<'a>),
+    Binary { data: &'a [u8], position: usize, big_endian: bool },
+}
+
+impl Body<'_> {
+    fn read(&mut self, ty: ScalarType) -> Result<f64, MeshError> {
+        match self {
+            Body::Ascii(tokens) => tokens.next()
+                .and_then(|token| token.parse::<f64>().ok())
+                .ok_or_else(|| invalid("expected a number")),
+            Body::Binary { data, position, big_endian } => {
+                let bytes = data.get(*position..*position + ty.size()).ok_or_else(|| invalid("unexpected end of file"))?;
+                *position += ty.size();
+
+                let mut buffer = [0u8; 8];
+                buffer[..bytes.len()].copy_from_slice(bytes);
+                if *big_endian {
+                    buffer[..bytes.len()].reverse();
+                }
+                Ok(match ty {
+                    ScalarType::I8 => buffer[0] as i8 as f64,
+                    ScalarType::U8 => buffer[0] as f64,
+                    ScalarType::I16 => i16::from_le_bytes(buffer[..2].try_into().unwrap()) as f64,
+                    ScalarType::U16 => u16::from_le_bytes(buffer[..2].try_into().unwrap()) as f64,
+                    ScalarType::I32 => i32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+                    ScalarType::U32 => u32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+                    ScalarType::F32 => f32::from_le_bytes(buffer[..4].try_into().unwrap()) as f64,
+                    ScalarType::F64 => f64::from_le_bytes(buffer[..8].try_into().unwrap()),
+                })
+            }
+        }
+    }
+}
+
+// Loads the vertex and face elements of a PLY file. Positions, normals, texture coordinates and per-vertex colors
+// are read when present, any other element (edges, materials...) is skipped.
+// The second value returned tells whether the file had normals.
+pub fn load_ply(data: &[u8]) -> Result<(Mesh, bool), MeshError> {
+    if !is_ply(data) {
+        return Err(invalid("missing 'ply' magic"));
+    }
+    let header_end = data.windows(10).position(|window| window == b"end_header")
+        .ok_or_else(|| invalid("missing end_header"))?;
+    let header = String::from_utf8_lossy(&data[..header_end]);
+    // The body starts right after the line ending of end_header
+    let mut body_start = header_end + 10;
+    if data.get(body_start) == Some(&b'\r') {
+        body_start += 1;
+    }
+    body_start += 1;
+
+    let mut encoding = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in header.lines().skip(1) {
+        let words: Vec<&str> = line.split_ascii_whitespace().collect();
+        match words.as_slice() {
+            ["format", "ascii", ..] => encoding = Some(Encoding::Ascii),
+            ["format", "binary_little_endian", ..] => encoding = Some(Encoding::BinaryLittleEndian),
+            ["format", "binary_big_endian", ..] => encoding = Some(Encoding::BinaryBigEndian),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| invalid("bad element count"))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_ty, item_ty, name] => elements.last_mut()
+                .ok_or_else(|| invalid("property before element"))?
+                .properties.push(Property::List {
+                    name: name.to_string(),
+                    count_ty: ScalarType::parse(count_ty)?,
+                    item_ty: ScalarType::parse(item_ty)?,
+                }),
+            ["property", ty, name] => elements.last_mut()
+                .ok_or_else(|| invalid("property before element"))?
+                .properties.push(Property::Scalar { name: name.to_string(), ty: ScalarType::parse(ty)? }),
+            _ => (),
+        }
+    }
+
+    let body_data = data.get(body_start..).unwrap_or(&[]);
+    let text;
+    let mut body = match encoding.ok_or_else(|| invalid("missing format line"))? {
+        Encoding::Ascii => {
+            text = String::from_utf8_lossy(body_data);
+            Body::Ascii(text.split_ascii_whitespace())
+        }
+        Encoding::BinaryLittleEndian => Body::Binary { data: body_data, position: 0, big_endian: false },
+        Encoding::BinaryBigEndian => Body::Binary { data: body_data, position: 0, big_endian: true },
+    };
+
+    let mut mesh = Mesh::default();
+    let mut has_normals = false;
+
+    for element in &elements {
+        let names: Vec<&str> = element.properties.iter().map(|property| match property {
+            Property::Scalar { name, .. } | Property::List { name, .. } => name.as_str(),
+        }).collect();
+        let find = |candidates: &[&str]| candidates.iter().find_map(|c| names.iter().position(|name| name == c));
+
+        let position_slots = [find(&["x"]), find(&["y"]), find(&["z"])];
+        let normal_slots = [find(&["nx"]), find(&["ny"]), find(&["nz"])];
+        let color_slots = [find(&["red", "r", "diffuse_red"]), find(&["green", "g", "diffuse_green"]), find(&["blue", "b", "diffuse_blue"])];
+        let tex_slots = [find(&["s", "u", "texture_u"]), find(&["t", "v", "texture_v"])];
+        let face_slot = find(&["vertex_indices", "vertex_index"]);
+        if element.name == "vertex" {
+            has_normals = normal_slots.iter().all(Option::is_some);
+        }
+
+        for _ in 0..element.count {
+            // Scalars of the current row by property index, lists are kept separately
+            let mut values = vec![0.0f64; element.properties.len()];
+            let mut lists: Vec<Vec<f64>> = vec![Vec::new(); element.properties.len()];
+            for (i, property) in element.properties.iter().enumerate() {
+                match property {
+                    Property::Scalar { ty, .. } => values[i] = body.read(*ty)?,
+                    Property::List { count_ty, item_ty, .. } => {
+                        let count = body.read(*count_ty)? as usize;
+                        lists[i] = (0..count).map(|_| body.read(*item_ty)).collect::<Result<_, _>>()?;
+                    }
+                }
+            }
+
+            match element.name.as_str() {
+                "vertex" => {
+                    let value = |slot: Option<usize>| slot.map_or(0.0, |i| values[i] as f32);
+                    // Integer colors are 0-255, float colors already 0-1
+                    let color_value = |slot: Option<usize>| slot.map(|i| match &element.properties[i] {
+                        Property::Scalar { ty: ScalarType::F32 | ScalarType::F64, .. } => values[i] as f32,
+                        _ => values[i] as f32 / 255.0,
+                    });
+                    let color = match color_slots.map(color_value) {
+                        [Some(r), Some(g), Some(b)] => [r, g, b],
+                        _ => DEFAULT_COLOR,
+                    };
+                    mesh.vertices.push(Vertex {
+                        position: position_slots.map(value),
+                        color,
+                        normal: normal_slots.map(value),
+                        // Files without texture coordinates keep the [0, 0] every other loader uses
+                        tex_coords: if tex_slots.iter().any(Option::is_some) { obj_polygons::flip_v(tex_slots.map(value)) } else { [0.0, 0.0] },
+                        tangent: [0.0; 4],
+                    });
+                }
+                "face" => {
+                    let polygon = face_slot.map(|slot| &lists[slot]).ok_or_else(|| invalid("face element without vertex_indices"))?;
+                    // Indices come in as f64 like every other value, `as u32` would quietly turn -1 into 0
+                    let polygon: Vec<u32> = polygon.iter().map(|&index| {
+                        if index >= 0.0 && index.fract() == 0.0 && index <= u32::MAX as f64 { Ok(index as u32) } else { Err(MeshError::IndexOutOfRange) }
+                    }).collect::<Result<_, _>>()?;
+                    for i in 1..polygon.len().saturating_sub(1) {
+                        mesh.indices.extend_from_slice(&[polygon[0], polygon[i], polygon[i + 1]]);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+
+    u32::try_from(mesh.vertices.len()).map_err(|_| MeshError::TooManyVertices(mesh.vertices.len()))?;
+    if mesh.indices.iter().any(|&index| index as usize >= mesh.vertices.len()) {
+        return Err(MeshError::IndexOutOfRange);
+    }
+    Ok((mesh, has_normals))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII_QUAD: &str = "ply\nformat ascii 1.0\ncomment a unit quad\nelement vertex 4\nproperty float x\nproperty float y\nproperty float z\n\
+                              property uchar red\nproperty uchar green\nproperty uchar blue\nelement face 1\nproperty list uchar int vertex_indices\n\
+                              end_header\n0 0 0 255 0 0\n1 0 0 0 255 0\n1 1 0 0 0 255\n0 1 0 255 255 255\n4 0 1 2 3\n";
+
+    // The same triangle in either binary encoding, with normals and a ushort index count to exercise more types
+    fn binary_triangle(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian { "binary_big_endian" } else { "binary_little_endian" };
+        let mut data = format!("ply\r\nformat {} 1.0\r\nelement vertex 3\r\nproperty float x\r\nproperty float y\r\nproperty float z\r\n\
+                                property double nx\r\nproperty double ny\r\nproperty double nz\r\nelement face 1\r\n\
+                                property list ushort uint vertex_indices\r\nend_header\r\n", format).into_bytes();
+        let f32_bytes = |value: f32| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+        let f64_bytes = |value: f64| if big_endian { value.to_be_bytes() } else { value.to_le_bytes() };
+        for position in [[0.0, 0.0, 0.0], [2.0, 0.0, 0.0], [0.0, 3.0, -1.5f32]] {
+            position.iter().for_each(|&value| data.extend_from_slice(&f32_bytes(value)));
+            [0.0, 0.0, 1.0].iter().for_each(|&value| data.extend_from_slice(&f64_bytes(value)));
+        }
+        data.extend_from_slice(&if big_endian { 3u16.to_be_bytes() } else { 3u16.to_le_bytes() });
+        for index in [0u32, 1, 2] {
+            data.extend_from_slice(&if big_endian { index.to_be_bytes() } else { index.to_le_bytes() });
+        }
+        data
+    }
+
+    #[test]
+    fn ascii_quad_is_fanned_into_triangles() {
+        let (mesh, has_normals) = load_ply(ASCII_QUAD.as_bytes()).unwrap();
+        assert!(!has_normals);
+        assert_eq!(mesh.vertices.len(), 4);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 0, 2, 3]);
+        assert_eq!(mesh.vertices[2].position, [1.0, 1.0, 0.0]);
+        assert_eq!(mesh.vertices[1].color, [0.0, 1.0, 0.0]);
+    }
+
+    #[test]
+    fn binary_files_read_the_same_in_both_byte_orders() {
+        for big_endian in [false, true] {
+            let (mesh, has_normals) = load_ply(&binary_triangle(big_endian)).unwrap();
+            assert!(has_normals);
+            assert_eq!(mesh.indices, vec![0, 1, 2]);
+            assert_eq!(mesh.vertices[2].position, [0.0, 3.0, -1.5]);
+            assert_eq!(mesh.vertices[0].normal, [0.0, 0.0, 1.0]);
+            assert_eq!(mesh.vertices[0].color, DEFAULT_COLOR);
+        }
+    }
+
+    #[test]
+    fn texture_coordinates_start_at_the_top() {
+        let data = "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\nproperty float z\n\
+                    property float s\nproperty float t\nelement face 1\nproperty list uchar int vertex_indices\nend_header\n\
+                    0 0 0 0 0\n1 0 0 1 0.25\n1 1 0 1 1\n3 0 1 2\n";
+        let (mesh, _) = load_ply(data.as_bytes()).unwrap();
+        assert_eq!(mesh.vertices[0].tex_coords, [0.0, 1.0]);
+        assert_eq!(mesh.vertices[1].tex_coords, [1.0, 0.75]);
+        assert_eq!(mesh.vertices[2].tex_coords, [1.0, 0.0]);
+    }
+
+    #[test]
+    fn negative_and_fractional_indices_are_rejected() {
+        for face in ["3 0 1 -1", "3 0 1 1.5"] {
+            let data = ASCII_QUAD.replace("4 0 1 2 3", face);
+            assert!(matches!(load_ply(data.as_bytes()), Err(MeshError::IndexOutOfRange)), "{}", face);
+        }
+        let data = ASCII_QUAD.replace("4 0 1 2 3", "3 0 1 4");
+        assert!(matches!(load_ply(data.as_bytes()), Err(MeshError::IndexOutOfRange)));
+    }
+
+    #[test]
+    fn truncated_binary_body_is_an_error() {
+        let data = binary_triangle(false);
+        assert!(matches!(load_ply(&data[..data.len() - 2]), Err(MeshError::InvalidData(_))));
+    }
+}
@@ -0,0 +1,82 @@
+use std::path::Path;
+
+use glium::backend::Facade;
+use glium::texture::{DepthTexture2d, MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use glium::winit::keyboard::KeyCode;
+
+use crate::fullscreen::FullscreenQuad;
+
+// One full screen fragment shader in the chain. Every pass gets the output of the pass before it as
+// u_texture, the scene depth as u_depth and the size of one texel as u_inverse_size.
+pub struct PostPass {
+    pub name: String,
+    pub enabled: bool,
+    program: glium::Program,
+}
+
+pub const KEY_HELP: &str = "P to toggle post processing";
+
+// Ordered list of passes run over the scene color. Passes take turns writing into two HDR buffers,
+// each one reading what the previous one wrote.
+pub struct PostProcessing {
+    pub enabled: bool,
+    pub passes: Vec<PostPass>,
+    quad: FullscreenQuad,
+    buffers: Option<[Texture2d; 2]>,
+}
+
+impl PostProcessing {
+    pub fn new<F: Facade + ?Sized>(facade: &F) -> Self {
+        PostProcessing { enabled: true, passes: Vec::new(), quad: FullscreenQuad::new(facade), buffers: None }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if key == KeyCode::KeyP {
+            self.enabled = !self.enabled;
+            println!("Post processing: {}", if self.enabled { "on" } else { "off" });
+        }
+    }
+
+    // Appends a pass named after its shader file
+    pub fn add_pass<F: Facade + ?Sized>(&mut self, facade: &F, fragment_shader_path: &str) {
+        let name = Path::new(fragment_shader_path).file_stem().map_or_else(|| fragment_shader_path.to_string(), |stem| stem.to_string_lossy().into_owned());
+        let program = FullscreenQuad::program(facade, fragment_shader_path);
+        self.passes.push(PostPass { name, enabled: true, program });
+    }
+
+    // Runs every enabled pass over `color` and returns the texture holding the result,
+    // which is `color` itself when nothing ran
+    pub fn run<'a, F: Facade + ?Sized>(&'a mut self, facade: &F, color: &'a Texture2d, depth: &DepthTexture2d) -> &'a Texture2d {
+        if !self.enabled || !self.passes.iter().any(|pass| pass.enabled) {
+            return color;
+        }
+
+        let (width, height) = color.dimensions();
+        if self.buffers.as_ref().is_none_or(|buffers| buffers[0].dimensions() != (width, height)) {
+            let buffer = || Texture2d::empty_with_format(facade, UncompressedFloatFormat::F16F16F16F16, MipmapsOption::NoMipmap, width, height).unwrap();
+            self.buffers = Some([buffer(), buffer()]);
+        }
+        let buffers = self.buffers.as_ref().unwrap();
+
+        let depth = depth.sampled()
+            .minify_filter(MinifySamplerFilter::Nearest)
+            .magnify_filter(MagnifySamplerFilter::Nearest)
+            .wrap_function(SamplerWrapFunction::Clamp);
+        let inverse_size = [1.0 / width as f32, 1.0 / height as f32];
+
+        let mut source = color;
+        for (i, pass) in self.passes.iter().filter(|pass| pass.enabled).enumerate() {
+            let destination = &buffers[i % 2];
+            let sampled = source.sampled()
+                .minify_filter(MinifySamplerFilter::Linear)
+                .magnify_filter(MagnifySamplerFilter::Linear)
+                .wrap_function(SamplerWrapFunction::Clamp);
+            let uniforms = uniform! { u_texture: sampled, u_depth: depth, u_inverse_size: inverse_size };
+            let mut target = destination.as_surface();
+            self.quad.draw(&mut target, &pass.program, &uniforms);
+            source = destination;
+        }
+        source
+    }
+}
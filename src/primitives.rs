@@ -0,0 +1,314 @@
+use std::collections::HashMap;
+use std::f32::consts::{FRAC_PI_2, PI, TAU};
+
+use crate::math;
+use crate::mesh::{Mesh, DEFAULT_COLOR};
+use crate::tangents;
+use crate::Vertex;
+
+// Procedural meshes centred on the origin with +y up. Every generator fills in normals, texture coordinates and tangents,
+// and winds its triangles counter-clockwise seen from the side the normals point to.
+
+// Builds a primitive by name for the command line, `subdivisions` replaces the main detail parameter of each shape:
+// grid cells per side, cube cells per face edge, icosphere subdivision levels, segments around the others.
+// The plane is always a single quad.
+pub fn by_name(name: &str, subdivisions: Option<u32>) -> Option<Mesh> {
+    let segments = subdivisions.unwrap_or(32).max(3);
+    Some(match name {
+        "plane" => plane(2.0, 2.0),
+        "grid" => grid(2.0, 2.0, subdivisions.unwrap_or(16), subdivisions.unwrap_or(16)),
+        "cube" => cube(1.5, subdivisions.unwrap_or(1)),
+        "sphere" => uv_sphere(1.0, segments, segments / 2),
+        "icosphere" => icosphere(1.0, subdivisions.unwrap_or(3)),
+        "cylinder" => cylinder(1.0, 2.0, segments, 1),
+        "cone" => cone(1.0, 2.0, segments, 1),
+        "torus" => torus(1.0, 0.35, segments, segments / 2),
+        "capsule" => capsule(0.6, 1.2, segments, segments / 4),
+        _ => return None,
+    })
+}
+
+fn vertex(position: [f32; 3], normal: [f32; 3], tex_coords: [f32; 2]) -> Vertex {
+    Vertex { position, color: DEFAULT_COLOR, normal, tex_coords, tangent: [0.0; 4] }
+}
+
+fn fraction(i: u32, n: u32) -> f32 {
+    i as f32 / n as f32
+}
+
+// Point on the unit sphere at a latitude (-pi/2 at the bottom) and an angle around +y, counter-clockwise seen from above
+fn sphere_direction(latitude: f32, angle: f32) -> [f32; 3] {
+    [latitude.cos() * angle.cos(), latitude.sin(), -latitude.cos() * angle.sin()]
+}
+
+// (columns + 1) x (rows + 1) vertices from a function of the column and row, each cell split into two triangles.
+// The first and last column are separate vertices on closed shapes so the texture coordinates can wrap.
+// Triangles face along (+column) x (+row), so every generator runs its columns and rows such that this points outwards,
+// which also keeps the texture from being mirrored. A row whose vertices all meet in one point (a pole or an apex)
+// only keeps the triangle of each cell that has an area, giving a single fan around that point.
+fn surface(columns: u32, rows: u32, point: impl Fn(u32, u32) -> Vertex) -> Mesh {
+    let mut mesh = Mesh::default();
+    for j in 0..=rows {
+        for i in 0..=columns {
+            mesh.vertices.push(point(i, j));
+        }
+    }
+    let stride = columns + 1;
+    let collapsed: Vec<bool> = mesh.vertices.chunks_exact(stride as usize).map(|row| {
+        row.iter().all(|vertex| math::length(math::sub(vertex.position, row[0].position)) < 1e-6)
+    }).collect();
+    for j in 0..rows {
+        for i in 0..columns {
+            let corner = j * stride + i;
+            if !collapsed[j as usize] {
+                mesh.indices.extend_from_slice(&[corner, corner + 1, corner + stride + 1]);
+            }
+            if !collapsed[j as usize + 1] {
+                mesh.indices.extend_from_slice(&[corner, corner + stride + 1, corner + stride]);
+            }
+        }
+    }
+    mesh
+}
+
+// Flat disc at height y facing up or down, a fan around a centre vertex with the texture mapped straight down onto it
+fn disc(radius: f32, y: f32, facing_up: bool, segments: u32) -> Mesh {
+    let normal = [0.0, if facing_up { 1.0 } else { -1.0 }, 0.0];
+    let mut mesh = Mesh::default();
+    mesh.vertices.push(vertex([0.0, y, 0.0], normal, [0.5, 0.5]));
+    for i in 0..segments {
+        let angle = fraction(i, segments) * TAU;
+        let (sin, cos) = angle.sin_cos();
+        mesh.vertices.push(vertex([radius * cos, y, -radius * sin], normal, [0.5 + 0.5 * cos, 0.5 + 0.5 * sin]));
+        // The rim runs counter-clockwise seen from above
+        let (current, next) = (1 + i, 1 + (i + 1) % segments);
+        mesh.indices.extend_from_slice(&if facing_up { [0, current, next] } else { [0, next, current] });
+    }
+    mesh
+}
+
+fn append(mesh: &mut Mesh, other: Mesh) {
+    let offset = mesh.vertices.len() as u32;
+    mesh.vertices.extend(other.vertices);
+    mesh.indices.extend(other.indices.iter().map(|index| index + offset));
+}
+
+fn with_tangents(mut mesh: Mesh) -> Mesh {
+    tangents::generate_tangents(&mut mesh);
+    mesh
+}
+
+// Flat grid in the xz plane split into columns along x and rows along z
+pub fn grid(width: f32, depth: f32, columns: u32, rows: u32) -> Mesh {
+    let (columns, rows) = (columns.max(1), rows.max(1));
+    with_tangents(surface(columns, rows, |i, j| {
+        let (u, v) = (fraction(i, columns), fraction(j, rows));
+        vertex([(u - 0.5) * width, 0.0, (0.5 - v) * depth], [0.0, 1.0, 0.0], [u, v])
+    }))
+}
+
+pub fn plane(width: f32, depth: f32) -> Mesh {
+    grid(width, depth, 1, 1)
+}
+
+// Each face is its own grid so the edges stay hard and every face gets the whole texture
+pub fn cube(size: f32, subdivisions: u32) -> Mesh {
+    let subdivisions = subdivisions.max(1);
+    // (normal, direction of +u, direction of +v), with u x v = normal so no face has a mirrored texture
+    let faces: [([f32; 3], [f32; 3], [f32; 3]); 6] = [
+        ([1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        ([-1.0, 0.0, 0.0], [0.0, 0.0, 1.0], [0.0, 1.0, 0.0]),
+        ([0.0, 1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, -1.0]),
+        ([0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]),
+        ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+        ([0.0, 0.0, -1.0], [-1.0, 0.0, 0.0], [0.0, 1.0, 0.0]),
+    ];
+
+    let mut mesh = Mesh::default();
+    for (normal, u_axis, v_axis) in faces {
+        append(&mut mesh, surface(subdivisions, subdivisions, |i, j| {
+            let (u, v) = (fraction(i, subdivisions), fraction(j, subdivisions));
+            let offset = math::add(math::scale(u_axis, 2.0 * u - 1.0), math::scale(v_axis, 2.0 * v - 1.0));
+            vertex(math::scale(math::add(normal, offset), size * 0.5), normal, [u, v])
+        }));
+    }
+    with_tangents(mesh)
+}
+
+// Longitude/latitude sphere, `rings` bands from pole to pole
+pub fn uv_sphere(radius: f32, segments: u32, rings: u32) -> Mesh {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+    with_tangents(surface(segments, rings, |i, j| {
+        let (u, v) = (fraction(i, segments), fraction(j, rings));
+        let normal = sphere_direction((v - 0.5) * PI, u * TAU);
+        vertex(math::scale(normal, radius), normal, [u, v])
+    }))
+}
+
+// Subdivided icosahedron, evenly sized triangles unlike the UV sphere. Texture coordinates use the same
+// longitude/latitude mapping, triangles crossing the seam get u values past 1 so they do not stretch over the whole texture.
+pub fn icosphere(radius: f32, subdivisions: u32) -> Mesh {
+    let t = (1.0 + 5f32.sqrt()) / 2.0;
+    let mut positions: Vec<[f32; 3]> = [
+        [-1.0, t, 0.0], [1.0, t, 0.0], [-1.0, -t, 0.0], [1.0, -t, 0.0],
+        [0.0, -1.0, t], [0.0, 1.0, t], [0.0, -1.0, -t], [0.0, 1.0, -t],
+        [t, 0.0, -1.0], [t, 0.0, 1.0], [-t, 0.0, -1.0], [-t, 0.0, 1.0],
+    ].iter().map(|&position| math::normalize(position)).collect();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5], [0, 5, 1], [0, 1, 7], [0, 7, 10], [0, 10, 11],
+        [1, 5, 9], [5, 11, 4], [11, 10, 2], [10, 7, 6], [7, 1, 8],
+        [3, 9, 4], [3, 4, 2], [3, 2, 6], [3, 6, 8], [3, 8, 9],
+        [4, 9, 5], [2, 4, 11], [6, 2, 10], [8, 6, 7], [9, 8, 1],
+    ];
+
+    for _ in 0..subdivisions {
+        // Edges are shared by two triangles, so midpoints are looked up by their sorted end points
+        let mut midpoints: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut midpoint = |a: u32, b: u32| -> u32 {
+            *midpoints.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                positions.push(math::normalize(math::add(positions[a as usize], positions[b as usize])));
+                positions.len() as u32 - 1
+            })
+        };
+        triangles = triangles.iter().flat_map(|&[a, b, c]| {
+            let (ab, bc, ca) = (midpoint(a, b), midpoint(b, c), midpoint(c, a));
+            [[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]
+        }).collect();
+    }
+
+    let mut mesh = Mesh::default();
+    // Positions are split where the seam or a pole needs different texture coordinates
+    let mut split_vertices: HashMap<(u32, [u32; 2]), u32> = HashMap::new();
+    for triangle in triangles {
+        let mut tex_coords = triangle.map(|index| {
+            let [x, y, z] = positions[index as usize];
+            [((-z).atan2(x) / TAU).rem_euclid(1.0), y.clamp(-1.0, 1.0).asin() / PI + 0.5]
+        });
+        if tex_coords.iter().any(|uv| uv[0] > 0.75) && tex_coords.iter().any(|uv| uv[0] < 0.25) {
+            for uv in &mut tex_coords {
+                if uv[0] < 0.5 {
+                    uv[0] += 1.0;
+                }
+            }
+        }
+        // The longitude of a pole is meaningless, use the middle of the triangle's other two corners
+        for corner in 0..3 {
+            let [x, _, z] = positions[triangle[corner] as usize];
+            if x.abs() < 1e-6 && z.abs() < 1e-6 {
+                tex_coords[corner][0] = (tex_coords[(corner + 1) % 3][0] + tex_coords[(corner + 2) % 3][0]) / 2.0;
+            }
+        }
+
+        for (index, uv) in triangle.into_iter().zip(tex_coords) {
+            let vertex_index = *split_vertices.entry((index, uv.map(f32::to_bits))).or_insert_with(|| {
+                let normal = positions[index as usize];
+                mesh.vertices.push(vertex(math::scale(normal, radius), normal, uv));
+                mesh.vertices.len() as u32 - 1
+            });
+            mesh.indices.push(vertex_index);
+        }
+    }
+    with_tangents(mesh)
+}
+
+// Capped cylinder along y, `height_segments` bands along the side
+pub fn cylinder(radius: f32, height: f32, segments: u32, height_segments: u32) -> Mesh {
+    let (segments, height_segments) = (segments.max(3), height_segments.max(1));
+    let mut mesh = surface(segments, height_segments, |i, j| {
+        let (u, v) = (fraction(i, segments), fraction(j, height_segments));
+        let normal = sphere_direction(0.0, u * TAU);
+        vertex(math::add(math::scale(normal, radius), [0.0, (v - 0.5) * height, 0.0]), normal, [u, v])
+    });
+    append(&mut mesh, disc(radius, height * 0.5, true, segments));
+    append(&mut mesh, disc(radius, -height * 0.5, false, segments));
+    with_tangents(mesh)
+}
+
+// Cone with its base cap. The apex is one vertex per segment so each side keeps its own normal.
+pub fn cone(radius: f32, height: f32, segments: u32, height_segments: u32) -> Mesh {
+    let (segments, height_segments) = (segments.max(3), height_segments.max(1));
+    let mut mesh = surface(segments, height_segments, |i, j| {
+        let (u, v) = (fraction(i, segments), fraction(j, height_segments));
+        let outwards = sphere_direction(0.0, u * TAU);
+        // The side normal leans up by the slope of the cone
+        let normal = math::normalize(math::add(math::scale(outwards, height), [0.0, radius, 0.0]));
+        let ring_radius = (1.0 - v) * radius;
+        vertex(math::add(math::scale(outwards, ring_radius), [0.0, (v - 0.5) * height, 0.0]), normal, [u, v])
+    });
+    append(&mut mesh, disc(radius, -height * 0.5, false, segments));
+    with_tangents(mesh)
+}
+
+// Torus around +y, `segments` around the ring and `sides` around the tube
+pub fn torus(major_radius: f32, minor_radius: f32, segments: u32, sides: u32) -> Mesh {
+    let (segments, sides) = (segments.max(3), sides.max(3));
+    with_tangents(surface(segments, sides, |i, j| {
+        let (u, v) = (fraction(i, segments), fraction(j, sides));
+        let normal = sphere_direction(v * TAU, u * TAU);
+        let centre = math::scale(sphere_direction(0.0, u * TAU), major_radius);
+        vertex(math::add(centre, math::scale(normal, minor_radius)), normal, [u, v])
+    }))
+}
+
+// Cylinder of the given height with a hemisphere of `rings` bands on each end. v follows the arc length from
+// bottom to top so the texture is not squashed on the caps.
+pub fn capsule(radius: f32, height: f32, segments: u32, rings: u32) -> Mesh {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    let length = PI * radius + height;
+    with_tangents(surface(segments, 2 * rings + 1, |i, j| {
+        let u = fraction(i, segments);
+        // Rows up to `rings` are the bottom hemisphere, the rest the top one, the band in between is the cylinder
+        let (latitude, centre_y, distance) = if j <= rings {
+            let latitude = fraction(j, rings) * FRAC_PI_2 - FRAC_PI_2;
+            (latitude, -height * 0.5, radius * (latitude + FRAC_PI_2))
+        } else {
+            let latitude = fraction(j - rings - 1, rings) * FRAC_PI_2;
+            (latitude, height * 0.5, radius * FRAC_PI_2 + height + radius * latitude)
+        };
+        let normal = sphere_direction(latitude, u * TAU);
+        let position = math::add([0.0, centre_y, 0.0], math::scale(normal, radius));
+        vertex(position, normal, [u, distance / length])
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 9] = ["plane", "grid", "cube", "sphere", "icosphere", "cylinder", "cone", "torus", "capsule"];
+
+    fn triangles(mesh: &Mesh) -> impl Iterator<Item = [Vertex; 3]> + '_ {
+        mesh.indices.chunks_exact(3).map(|triangle| [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize]))
+    }
+
+    #[test]
+    fn triangles_wind_counter_clockwise_around_their_normals() {
+        for name in NAMES {
+            let mesh = by_name(name, Some(8)).unwrap();
+            for [a, b, c] in triangles(&mesh) {
+                let face_normal = math::cross(math::sub(b.position, a.position), math::sub(c.position, a.position));
+                let vertex_normal = math::add(math::add(a.normal, b.normal), c.normal);
+                assert!(math::dot(face_normal, vertex_normal) > 0.0, "{} has a triangle facing inwards", name);
+            }
+        }
+    }
+
+    #[test]
+    fn no_triangle_is_degenerate() {
+        for name in NAMES {
+            let mesh = by_name(name, Some(8)).unwrap();
+            for [a, b, c] in triangles(&mesh) {
+                let area = math::length(math::cross(math::sub(b.position, a.position), math::sub(c.position, a.position)));
+                assert!(area > 1e-6, "{} has a zero-area triangle", name);
+            }
+        }
+    }
+
+    #[test]
+    fn sphere_poles_are_single_fans() {
+        let (segments, rings) = (12, 6);
+        let mesh = uv_sphere(1.0, segments, rings);
+        // One triangle per segment in each pole row, two in every band between them
+        assert_eq!(mesh.triangle_count(), (segments * 2 + segments * (rings - 2) * 2) as usize);
+    }
+}
@@ -0,0 +1,206 @@
+use std::fmt;
+
+use glium::draw_parameters::{BackfaceCullingMode, DepthTest, PolygonMode, PolygonOffset};
+use glium::winit::keyboard::KeyCode;
+
+use crate::scene::FaceCulling;
+
+// How the viewer rasterises the scene, switched at runtime with the number keys (Tab cycles)
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum RenderMode {
+    #[default]
+    Solid,
+    Wireframe,
+    Points,
+    // Shaded geometry with its edges drawn on top in a second pass
+    SolidWireframe,
+}
+
+pub const KEY_HELP: &str = "1 solid, 2 wireframe, 3 points, 4 solid + wireframe, Tab to cycle, \
+                            C to override material culling, F to color front and back faces, \
+                            M to cycle debug shading, V to cycle normal/tangent lines";
+
+const POINT_SIZE: f32 = 3.0;
+
+impl RenderMode {
+    // The mode a key switches to when the current mode is `self`
+    pub fn for_key(self, key: KeyCode) -> Option<Self> {
+        match key {
+            KeyCode::Digit1 => Some(RenderMode::Solid),
+            KeyCode::Digit2 => Some(RenderMode::Wireframe),
+            KeyCode::Digit3 => Some(RenderMode::Points),
+            KeyCode::Digit4 => Some(RenderMode::SolidWireframe),
+            KeyCode::Tab => Some(match self {
+                RenderMode::Solid => RenderMode::Wireframe,
+                RenderMode::Wireframe => RenderMode::Points,
+                RenderMode::Points => RenderMode::SolidWireframe,
+                RenderMode::SolidWireframe => RenderMode::Solid,
+            }),
+            _ => None,
+        }
+    }
+
+    // Parameters for the shaded pass
+    pub fn shaded_parameters<'a>(self, params: &glium::DrawParameters<'a>) -> glium::DrawParameters<'a> {
+        let mut params = params.clone();
+        match self {
+            RenderMode::Solid => (),
+            RenderMode::Wireframe => params.polygon_mode = PolygonMode::Line,
+            RenderMode::Points => {
+                params.polygon_mode = PolygonMode::Point;
+                params.point_size = Some(POINT_SIZE);
+            }
+            // Pushes the filled triangles back a little so the edges drawn over them win the depth test
+            RenderMode::SolidWireframe => params.polygon_offset = PolygonOffset { factor: 1.0, units: 1.0, fill: true, ..Default::default() },
+        }
+        params
+    }
+
+    // Parameters for the edge pass, None when the mode has no overlay
+    pub fn overlay_parameters<'a>(self, params: &glium::DrawParameters<'a>) -> Option<glium::DrawParameters<'a>> {
+        if self != RenderMode::SolidWireframe {
+            return None;
+        }
+        let mut params = params.clone();
+        params.polygon_mode = PolygonMode::Line;
+        params.depth.test = DepthTest::IfLessOrEqual;
+        params.depth.write = false;
+        Some(params)
+    }
+}
+
+impl fmt::Display for RenderMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            RenderMode::Solid => "solid",
+            RenderMode::Wireframe => "wireframe",
+            RenderMode::Points => "points",
+            RenderMode::SolidWireframe => "solid + wireframe",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Culling for one draw. front_clockwise is true when the draw's transforms leave front faces (counter-clockwise
+// in the model) clockwise on screen, which happens with the fixed view and with mirroring node transforms.
+pub fn backface_culling(culling: FaceCulling, front_clockwise: bool) -> BackfaceCullingMode {
+    match (culling, front_clockwise) {
+        (FaceCulling::None, _) => BackfaceCullingMode::CullingDisabled,
+        (FaceCulling::Back, false) | (FaceCulling::Front, true) => BackfaceCullingMode::CullClockwise,
+        (FaceCulling::Back, true) | (FaceCulling::Front, false) => BackfaceCullingMode::CullCounterClockwise,
+    }
+}
+
+// C cycles from each material's own culling through forcing back, front and no culling on everything
+pub fn next_culling_override(culling: Option<FaceCulling>) -> Option<FaceCulling> {
+    match culling {
+        None => Some(FaceCulling::Back),
+        Some(FaceCulling::Back) => Some(FaceCulling::Front),
+        Some(FaceCulling::Front) => Some(FaceCulling::None),
+        Some(FaceCulling::None) => None,
+    }
+}
+
+// What the fragment shader outputs, M cycles through them
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ShadingMode {
+    #[default]
+    Lit,
+    Normals,
+    TexCoords,
+    Depth,
+    WorldPosition,
+}
+
+impl ShadingMode {
+    pub fn next(self) -> Self {
+        match self {
+            ShadingMode::Lit => ShadingMode::Normals,
+            ShadingMode::Normals => ShadingMode::TexCoords,
+            ShadingMode::TexCoords => ShadingMode::Depth,
+            ShadingMode::Depth => ShadingMode::WorldPosition,
+            ShadingMode::WorldPosition => ShadingMode::Lit,
+        }
+    }
+
+    // u_mode in debug_shading.frag
+    pub fn shader_mode(self) -> i32 {
+        self as i32
+    }
+}
+
+impl fmt::Display for ShadingMode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            ShadingMode::Lit => "lit",
+            ShadingMode::Normals => "view space normals",
+            ShadingMode::TexCoords => "texture coordinates",
+            ShadingMode::Depth => "linear depth",
+            ShadingMode::WorldPosition => "world position",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+// Per vertex lines drawn by vectors.geom, V cycles through them
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum VectorDisplay {
+    #[default]
+    Off,
+    Normals,
+    // Normals, tangents and bitangents
+    TangentFrames,
+}
+
+impl VectorDisplay {
+    pub fn next(self) -> Self {
+        match self {
+            VectorDisplay::Off => VectorDisplay::Normals,
+            VectorDisplay::Normals => VectorDisplay::TangentFrames,
+            VectorDisplay::TangentFrames => VectorDisplay::Off,
+        }
+    }
+}
+
+// How the viewer draws the scene's primitives, changed with the keys of KEY_HELP
+#[derive(Copy, Clone, Debug, Default)]
+pub struct RenderSettings {
+    pub mode: RenderMode,
+    // Used for every primitive instead of its material's culling while set
+    pub culling_override: Option<FaceCulling>,
+    // Draws front faces blue and back faces red instead of shading them
+    pub show_facing: bool,
+    pub shading: ShadingMode,
+    pub vectors: VectorDisplay,
+}
+
+impl RenderSettings {
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if let Some(mode) = self.mode.for_key(key) {
+            self.mode = mode;
+            println!("Render mode: {}", self.mode);
+        }
+        match key {
+            KeyCode::KeyC => {
+                self.culling_override = next_culling_override(self.culling_override);
+                match self.culling_override {
+                    Some(culling) => println!("Culling: {:?} for every material", culling),
+                    None => println!("Culling: per material"),
+                }
+            }
+            KeyCode::KeyF => {
+                self.show_facing = !self.show_facing;
+                println!("Front/back face colors: {}", if self.show_facing { "on" } else { "off" });
+            }
+            KeyCode::KeyM => {
+                self.shading = self.shading.next();
+                println!("Shading: {}", self.shading);
+            }
+            KeyCode::KeyV => {
+                self.vectors = self.vectors.next();
+                println!("Vector lines: {:?}", self.vectors);
+            }
+            _ => (),
+        }
+    }
+}
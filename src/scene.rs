@@ -0,0 +1,272 @@
+use std::fmt;
+
+use crate::math::{self, Mat4};
+use crate::mesh::Mesh;
+
+// Decoded image kept on the CPU until the viewer uploads it, always 8 bit RGBA with the first row at the top
+#[derive(Clone, Debug)]
+pub struct Texture {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlphaMode {
+    Opaque,
+    // Fragments with alpha below the cutoff are discarded
+    Mask(f32),
+    Blend,
+}
+
+// Which faces of a material are skipped when drawing. glTF only knows back face culling or double sided.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum FaceCulling {
+    #[default]
+    Back,
+    Front,
+    None,
+}
+
+#[derive(Clone, Debug)]
+pub struct Material {
+    pub name: String,
+    // Linear RGBA, multiplied with the vertex color and base_color_texture when shading
+    pub base_color: [f32; 4],
+    // Index into Scene::textures
+    pub base_color_texture: Option<usize>,
+    // Metallic/roughness workflow parameters, only the deferred point lights use them
+    pub metallic: f32,
+    pub roughness: f32,
+    pub emissive: [f32; 3],
+    pub alpha_mode: AlphaMode,
+    pub culling: FaceCulling,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            name: String::from("default"),
+            base_color: [1.0, 1.0, 1.0, 1.0],
+            base_color_texture: None,
+            // A dielectric like the OBJ importer's materials, a metal would get no diffuse light from point lights
+            metallic: 0.0,
+            roughness: 1.0,
+            emissive: [0.0, 0.0, 0.0],
+            alpha_mode: AlphaMode::Opaque,
+            culling: FaceCulling::Back,
+        }
+    }
+}
+
+// Part of a mesh drawn with a single material
+#[derive(Clone, Debug)]
+pub struct Primitive {
+    pub mesh: Mesh,
+    // Index into Scene::materials, None uses Material::default()
+    pub material: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SceneMesh {
+    pub name: String,
+    pub primitives: Vec<Primitive>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Projection {
+    // aspect_ratio of None means use the window's
+    Perspective { yfov: f32, aspect_ratio: Option<f32>, znear: f32, zfar: Option<f32> },
+    Orthographic { xmag: f32, ymag: f32, znear: f32, zfar: f32 },
+}
+
+// Cameras look down their local -Z axis with +Y up
+#[derive(Clone, Debug)]
+pub struct Camera {
+    pub name: String,
+    pub projection: Projection,
+}
+
+#[derive(Clone, Debug)]
+pub struct Node {
+    pub name: String,
+    // Relative to the parent node
+    pub transform: Mat4,
+    pub mesh: Option<usize>,
+    pub camera: Option<usize>,
+    pub children: Vec<usize>,
+}
+
+// Everything a model file can describe, flattened into index based lists like glTF does
+#[derive(Clone, Debug, Default)]
+pub struct Scene {
+    pub meshes: Vec<SceneMesh>,
+    pub materials: Vec<Material>,
+    pub textures: Vec<Texture>,
+    pub cameras: Vec<Camera>,
+    pub nodes: Vec<Node>,
+    // Nodes without a parent
+    pub roots: Vec<usize>,
+}
+
+impl Scene {
+    // Wraps a single mesh, as loaded from formats without a scene graph, in a one node scene
+    pub fn from_mesh(name: &str, mesh: Mesh) -> Self {
+        Scene {
+            meshes: vec![SceneMesh { name: name.to_string(), primitives: vec![Primitive { mesh, material: None }] }],
+            nodes: vec![Node { name: name.to_string(), transform: math::IDENTITY, mesh: Some(0), camera: None, children: Vec::new() }],
+            roots: vec![0],
+            ..Default::default()
+        }
+    }
+
+    // Sets the culling of every material, primitives without a material get a copy of the default one to carry it
+    pub fn set_culling(&mut self, culling: FaceCulling) {
+        let mut default_material = None;
+        for primitive in self.meshes.iter_mut().flat_map(|mesh| &mut mesh.primitives) {
+            if primitive.material.is_none() {
+                primitive.material = Some(*default_material.get_or_insert_with(|| {
+                    self.materials.push(Material::default());
+                    self.materials.len() - 1
+                }));
+            }
+        }
+        for material in &mut self.materials {
+            material.culling = culling;
+        }
+    }
+
+    // World transform of every node, indexed like Scene::nodes. None for nodes that cannot be reached from `roots`,
+    // such as nodes of another glTF scene or orphans, those are not part of the scene being shown.
+    pub fn world_transforms(&self) -> Vec<Option<Mat4>> {
+        let mut world = vec![None; self.nodes.len()];
+        let mut stack: Vec<(usize, Mat4)> = self.roots.iter().map(|&root| (root, math::IDENTITY)).collect();
+        while let Some((node, parent)) = stack.pop() {
+            let transform = math::mat4_mul(&parent, &self.nodes[node].transform);
+            world[node] = Some(transform);
+            stack.extend(self.nodes[node].children.iter().map(|&child| (child, transform)));
+        }
+        world
+    }
+
+    // Every mesh instance in the scene with its world transform
+    pub fn mesh_instances(&self) -> Vec<(usize, Mat4)> {
+        let world = self.world_transforms();
+        self.nodes.iter().zip(world)
+            .filter_map(|(node, world)| Some((node.mesh?, world?)))
+            .collect()
+    }
+
+    // The first camera in the scene with its world transform
+    pub fn first_camera(&self) -> Option<(&Camera, Mat4)> {
+        let world = self.world_transforms();
+        self.nodes.iter().zip(world)
+            .find_map(|(node, world)| Some((&self.cameras[node.camera?], world?)))
+    }
+
+    // World space axis aligned bounding box (min, max) of every mesh instance, None for a scene without vertices
+    pub fn bounds(&self) -> Option<([f32; 3], [f32; 3])> {
+        let mut bounds: Option<([f32; 3], [f32; 3])> = None;
+        for (mesh, world) in self.mesh_instances() {
+            for primitive in &self.meshes[mesh].primitives {
+                for vertex in &primitive.mesh.vertices {
+                    let p = math::transform_point(&world, vertex.position);
+                    let (min, max) = bounds.get_or_insert((p, p));
+                    for i in 0..3 {
+                        min[i] = min[i].min(p[i]);
+                        max[i] = max[i].max(p[i]);
+                    }
+                }
+            }
+        }
+        bounds
+    }
+
+    pub fn material(&self, primitive: &Primitive) -> Material {
+        primitive.material.map_or_else(Material::default, |material| self.materials[material].clone())
+    }
+
+    pub fn primitives_mut(&mut self) -> impl Iterator<Item = &mut Primitive> {
+        self.meshes.iter_mut().flat_map(|mesh| mesh.primitives.iter_mut())
+    }
+}
+
+// Prints the node hierarchy with what each node holds, useful to check what an importer picked up
+impl fmt::Display for Scene {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{} meshes, {} materials, {} textures, {} cameras, {} nodes",
+                 self.meshes.len(), self.materials.len(), self.textures.len(), self.cameras.len(), self.nodes.len())?;
+
+        let mut stack: Vec<(usize, usize)> = self.roots.iter().rev().map(|&root| (root, 0)).collect();
+        while let Some((index, depth)) = stack.pop() {
+            let node = &self.nodes[index];
+            write!(f, "{:indent$}node \"{}\"", "", node.name, indent = depth * 2)?;
+            if let Some(mesh) = node.mesh {
+                let mesh = &self.meshes[mesh];
+                let materials: Vec<String> = mesh.primitives.iter().map(|primitive| self.material(primitive).name).collect();
+                write!(f, " mesh \"{}\" materials [{}]", mesh.name, materials.join(", "))?;
+            }
+            if let Some(camera) = node.camera {
+                write!(f, " camera \"{}\"", self.cameras[camera].name)?;
+            }
+            writeln!(f)?;
+            stack.extend(node.children.iter().rev().map(|&child| (child, depth + 1)));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn node(name: &str, mesh: Option<usize>, camera: Option<usize>, children: Vec<usize>) -> Node {
+        let mut transform = math::IDENTITY;
+        transform[3] = [1.0, 2.0, 3.0, 1.0];
+        Node { name: name.to_string(), transform, mesh, camera, children }
+    }
+
+    #[test]
+    fn nodes_outside_the_roots_are_left_out() {
+        let mesh = Mesh {
+            vertices: vec![crate::Vertex { position: [0.0; 3], color: [1.0; 3], normal: [0.0; 3], tex_coords: [0.0; 2], tangent: [0.0; 4] }],
+            indices: Vec::new(),
+        };
+        let scene = Scene {
+            meshes: vec![SceneMesh { name: "mesh".to_string(), primitives: vec![Primitive { mesh, material: None }] }],
+            cameras: vec![Camera { name: "camera".to_string(), projection: Projection::Orthographic { xmag: 1.0, ymag: 1.0, znear: 0.1, zfar: 10.0 } }],
+            // 0 is an orphan with a camera, 1 a root with a child, 3 sits in another scene
+            nodes: vec![node("orphan", Some(0), Some(0), Vec::new()), node("root", Some(0), None, vec![2]),
+                        node("child", Some(0), Some(0), Vec::new()), node("other scene", Some(0), None, Vec::new())],
+            roots: vec![1],
+            ..Default::default()
+        };
+
+        let world = scene.world_transforms();
+        assert!(world[0].is_none() && world[3].is_none());
+        assert_eq!(world[2].unwrap()[3], [2.0, 4.0, 6.0, 1.0]);
+        assert_eq!(scene.mesh_instances().len(), 2);
+        assert_eq!(scene.first_camera().unwrap().1[3], [2.0, 4.0, 6.0, 1.0]);
+        assert_eq!(scene.bounds(), Some(([1.0, 2.0, 3.0], [2.0, 4.0, 6.0])));
+    }
+
+    #[test]
+    fn set_culling_reaches_primitives_on_the_default_material() {
+        let mut scene = Scene::from_mesh("mesh", Mesh::default());
+        scene.meshes[0].primitives.push(Primitive { mesh: Mesh::default(), material: None });
+        scene.set_culling(FaceCulling::None);
+
+        // Both primitives share one copy of the default material
+        assert_eq!(scene.materials.len(), 1);
+        for primitive in &scene.meshes[0].primitives {
+            assert_eq!(primitive.material, Some(0));
+            assert_eq!(scene.material(primitive).culling, FaceCulling::None);
+        }
+    }
+
+    #[test]
+    fn default_material_is_a_dielectric() {
+        // Point lights only add diffuse light to non metals
+        assert_eq!(Material::default().metallic, 0.0);
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt;
+
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{DepthTexture2d, MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction};
+use glium::Surface;
+use glium::winit::keyboard::KeyCode;
+
+use crate::fullscreen::FullscreenQuad;
+use crate::math::{self, Mat4};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum SsaoQuality {
+    Off,
+    // 8 samples at half resolution
+    Low,
+    // 16 samples
+    #[default]
+    Medium,
+    // 32 samples
+    High,
+}
+
+impl SsaoQuality {
+    // Names accepted by --ssao
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "off" => Some(SsaoQuality::Off),
+            "low" => Some(SsaoQuality::Low),
+            "medium" => Some(SsaoQuality::Medium),
+            "high" => Some(SsaoQuality::High),
+            _ => None,
+        }
+    }
+
+    // Order the O key steps through
+    pub fn next(self) -> Self {
+        match self {
+            SsaoQuality::Off => SsaoQuality::Low,
+            SsaoQuality::Low => SsaoQuality::Medium,
+            SsaoQuality::Medium => SsaoQuality::High,
+            SsaoQuality::High => SsaoQuality::Off,
+        }
+    }
+
+    fn sample_count(self) -> usize {
+        match self {
+            SsaoQuality::Off => 0,
+            SsaoQuality::Low => 8,
+            SsaoQuality::Medium => 16,
+            SsaoQuality::High => 32,
+        }
+    }
+
+    // The occlusion buffers are the scene size divided by this
+    fn resolution_divisor(self) -> u32 {
+        if self == SsaoQuality::Low { 2 } else { 1 }
+    }
+}
+
+impl fmt::Display for SsaoQuality {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SsaoQuality::Off => write!(f, "off"),
+            _ => write!(f, "{:?}, {} samples", self, self.sample_count()),
+        }
+    }
+}
+
+// Width and height of the tiled noise texture
+const NOISE_SIZE: u32 = 4;
+
+struct SsaoTargets {
+    size: (u32, u32),
+    normals: Texture2d,
+    depth: DepthTexture2d,
+    occlusion: Texture2d,
+    blurred: Texture2d,
+}
+
+pub const KEY_HELP: &str = "O to cycle ambient occlusion quality";
+
+// Screen space ambient occlusion. Each frame the scene is drawn once more with `prepass_program` into the depth and
+// view space normal buffers from prepass(), then occlusion() turns those into a texture that is 1.0 where ambient light
+// reaches freely and darker in creases, for the lighting shader to multiply its ambient term with.
+pub struct Ssao {
+    pub quality: SsaoQuality,
+    pub prepass_program: glium::Program,
+    quad: FullscreenQuad,
+    ssao_program: glium::Program,
+    blur_program: glium::Program,
+    // Made for the quality it was last prepared for
+    kernel: Option<(SsaoQuality, Texture2d)>,
+    noise: Texture2d,
+    // Bound while SSAO is off so the lighting shader needs no separate path
+    white: Texture2d,
+    targets: Option<SsaoTargets>,
+}
+
+impl Ssao {
+    pub fn new<F: Facade + ?Sized>(facade: &F, quality: SsaoQuality) -> Self {
+        // Random directions in the xy plane, the hemisphere is spun around the normal by these
+        let mut seed = 0x2545_f491;
+        let noise: Vec<Vec<(f32, f32, f32)>> = (0..NOISE_SIZE).map(|_| {
+            (0..NOISE_SIZE).map(|_| (random(&mut seed) * 2.0 - 1.0, random(&mut seed) * 2.0 - 1.0, 0.0)).collect()
+        }).collect();
+
+        Ssao {
+            quality,
+            prepass_program: glium::Program::from_source(facade, crate::read_shader("shaders/ssao/prepass.vert").as_str(),
+                                                         crate::read_shader("shaders/ssao/prepass.frag").as_str(), None).unwrap(),
+            quad: FullscreenQuad::new(facade),
+            ssao_program: FullscreenQuad::program(facade, "shaders/ssao/ssao.frag"),
+            blur_program: FullscreenQuad::program(facade, "shaders/ssao/blur.frag"),
+            kernel: None,
+            noise: Texture2d::with_format(facade, noise, UncompressedFloatFormat::F32F32F32, MipmapsOption::NoMipmap).unwrap(),
+            white: Texture2d::new(facade, vec![vec![(1.0f32, 1.0, 1.0, 1.0)]]).unwrap(),
+            targets: None,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        if key == KeyCode::KeyO {
+            self.quality = self.quality.next();
+            println!("Ambient occlusion: {}", self.quality);
+        }
+    }
+
+    // Sizes the buffers for a scene of `size` pixels at the current quality, call before prepass()
+    pub fn prepare<F: Facade + ?Sized>(&mut self, facade: &F, size: (u32, u32)) {
+        if self.quality == SsaoQuality::Off {
+            return;
+        }
+        if self.kernel.as_ref().is_none_or(|(quality, _)| *quality != self.quality) {
+            self.kernel = Some((self.quality, kernel_texture(facade, self.quality.sample_count())));
+        }
+
+        let divisor = self.quality.resolution_divisor();
+        let size = ((size.0 / divisor).max(1), (size.1 / divisor).max(1));
+        if self.targets.as_ref().is_none_or(|targets| targets.size != size) {
+            let (width, height) = size;
+            let texture = |format| Texture2d::empty_with_format(facade, format, MipmapsOption::NoMipmap, width, height).unwrap();
+            self.targets = Some(SsaoTargets {
+                size,
+                normals: texture(UncompressedFloatFormat::F16F16F16F16),
+                depth: DepthTexture2d::empty(facade, width, height).unwrap(),
+                occlusion: texture(UncompressedFloatFormat::F16),
+                blurred: texture(UncompressedFloatFormat::F16),
+            });
+        }
+    }
+
+    // Cleared framebuffer to draw the scene into with `prepass_program`, None while SSAO is off
+    pub fn prepass<F: Facade + ?Sized>(&self, facade: &F) -> Option<SimpleFrameBuffer<'_>> {
+        if self.quality == SsaoQuality::Off {
+            return None;
+        }
+        let targets = self.targets.as_ref()?;
+        let mut framebuffer = SimpleFrameBuffer::with_depth_buffer(facade, &targets.normals, &targets.depth).unwrap();
+        framebuffer.clear_color_and_depth((0.0, 0.0, 1.0, 1.0), 1.0);
+        Some(framebuffer)
+    }
+
+    // Computes and blurs the occlusion from what the prepass drew. `radius` is the size of the sampled
+    // hemisphere in view space units, `projection` has to be the one the prepass used.
+    pub fn occlusion(&self, projection: &Mat4, radius: f32) -> &Texture2d {
+        if self.quality == SsaoQuality::Off {
+            return &self.white;
+        }
+        let (Some(targets), Some((_, kernel))) = (&self.targets, &self.kernel) else {
+            return &self.white;
+        };
+
+        let (width, height) = targets.size;
+        let uniforms = uniform! {
+            u_depth: nearest(&targets.depth),
+            u_normals: nearest(&targets.normals),
+            // Tiled over the screen
+            u_noise: nearest(&self.noise).wrap_function(SamplerWrapFunction::Repeat),
+            u_kernel: nearest(kernel),
+            u_sample_count: self.quality.sample_count() as i32,
+            u_projection: *projection,
+            u_inverse_projection: math::mat4_inverse(projection).unwrap_or(math::IDENTITY),
+            u_radius: radius,
+            u_noise_scale: [width as f32 / NOISE_SIZE as f32, height as f32 / NOISE_SIZE as f32],
+        };
+        self.quad.draw(&mut targets.occlusion.as_surface(), &self.ssao_program, &uniforms);
+
+        let uniforms = uniform! { u_texture: nearest(&targets.occlusion), u_inverse_size: [1.0 / width as f32, 1.0 / height as f32] };
+        self.quad.draw(&mut targets.blurred.as_surface(), &self.blur_program, &uniforms);
+        &targets.blurred
+    }
+}
+
+// Sample offsets in the hemisphere above +z, one per texel. Later samples reach further out,
+// with more of them close to the center where occlusion matters most.
+fn kernel_texture<F: Facade + ?Sized>(facade: &F, sample_count: usize) -> Texture2d {
+    let mut seed = 0x9e37_79b9;
+    let samples: Vec<(f32, f32, f32)> = (0..sample_count).map(|i| {
+        let direction = math::normalize([random(&mut seed) * 2.0 - 1.0, random(&mut seed) * 2.0 - 1.0, random(&mut seed)]);
+        let fraction = i as f32 / sample_count as f32;
+        let distance = random(&mut seed) * (0.1 + 0.9 * fraction * fraction);
+        let [x, y, z] = math::scale(direction, distance);
+        (x, y, z)
+    }).collect();
+    Texture2d::with_format(facade, vec![samples], UncompressedFloatFormat::F32F32F32, MipmapsOption::NoMipmap).unwrap()
+}
+
+// The buffers line up texel for texel with the screen or hold data that must not be blended
+fn nearest<T>(texture: &T) -> Sampler<'_, T> {
+    Sampler::new(texture)
+        .minify_filter(MinifySamplerFilter::Nearest)
+        .magnify_filter(MagnifySamplerFilter::Nearest)
+        .wrap_function(SamplerWrapFunction::Clamp)
+}
+
+// Xorshift, good enough for sample patterns and keeps them the same every run
+fn random(seed: &mut u32) -> f32 {
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 17;
+    *seed ^= *seed << 5;
+    *seed as f32 / u32::MAX as f32
+}
@@ -0,0 +1,221 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+
+use glium::backend::Facade;
+use glium::debug::TimestampQuery;
+
+// Frames the rolling averages and percentiles are taken over, about four seconds at 60 frames per second
+const HISTORY: usize = 240;
+
+// Frames whose GPU timestamps are still waiting for their result. Older ones are given up on, a driver that never answers should not pile them up.
+const MAX_PENDING_QUERIES: usize = 8;
+
+// Draw calls and triangles since the last FrameStats::end_frame, counted by every place that draws
+static DRAW_CALLS: AtomicU64 = AtomicU64::new(0);
+static TRIANGLES: AtomicU64 = AtomicU64::new(0);
+
+// Call next to each draw, `triangles` is 0 for lines and points
+pub fn count_draw(triangles: usize) {
+    DRAW_CALLS.fetch_add(1, Ordering::Relaxed);
+    TRIANGLES.fetch_add(triangles as u64, Ordering::Relaxed);
+}
+
+// The last HISTORY values of one measurement
+#[derive(Debug, Default)]
+pub struct Series {
+    samples: VecDeque<f32>,
+}
+
+impl Series {
+    pub fn push(&mut self, value: f32) {
+        if self.samples.len() == HISTORY {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(value);
+    }
+
+    pub fn last(&self) -> Option<f32> {
+        self.samples.back().copied()
+    }
+
+    pub fn average(&self) -> Option<f32> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<f32>() / self.samples.len() as f32)
+    }
+
+    // Nearest rank percentile, `percent` from 0 to 100
+    pub fn percentile(&self, percent: f32) -> Option<f32> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<f32> = self.samples.iter().copied().collect();
+        sorted.sort_by(f32::total_cmp);
+        let rank = ((percent / 100.0 * sorted.len() as f32).ceil() as usize).clamp(1, sorted.len());
+        Some(sorted[rank - 1])
+    }
+
+    // Oldest first, for graphs
+    pub fn samples(&self) -> impl Iterator<Item = f32> + '_ {
+        self.samples.iter().copied()
+    }
+}
+
+// Timing and workload of the frames drawn so far. Times are in milliseconds. `frame_time` is the time between the starts of
+// two frames, `cpu_time` what the CPU spent between begin_frame() and end_frame(), and `gpu_time` the time between the GPU
+// reaching begin_frame() and end_frame(), every pass of the frame, from timestamps taken at both. GPU results arrive a few
+// frames late, they are read once ready rather than stalling the frame that issued them.
+#[derive(Default)]
+pub struct FrameStats {
+    pub frame_time: Series,
+    pub cpu_time: Series,
+    pub gpu_time: Series,
+    pub draw_calls: u64,
+    pub triangles: u64,
+    // Vertex and index buffers, and textures, of the scene
+    pub buffer_bytes: usize,
+    pub texture_bytes: usize,
+    frames: u64,
+    frame_start: Option<Instant>,
+    gpu_timing_unsupported: bool,
+    // Start timestamp of the frame being drawn, then both timestamps of the frames drawn before it
+    frame_start_query: Option<TimestampQuery>,
+    pending_queries: VecDeque<(TimestampQuery, TimestampQuery)>,
+    csv: Option<BufWriter<File>>,
+}
+
+impl FrameStats {
+    // Writes a row per frame to `path` from now on, see end_frame() for the columns
+    pub fn log_to_csv(&mut self, path: &Path) -> io::Result<()> {
+        let mut csv = BufWriter::new(File::create(path)?);
+        writeln!(csv, "frame,frame_ms,cpu_ms,gpu_ms,draw_calls,triangles,buffer_bytes,texture_bytes")?;
+        self.csv = Some(csv);
+        Ok(())
+    }
+
+    pub fn begin_frame<F: Facade + ?Sized>(&mut self, facade: &F) {
+        let now = Instant::now();
+        if let Some(frame_start) = self.frame_start {
+            self.frame_time.push((now - frame_start).as_secs_f32() * 1000.0);
+        }
+        self.frame_start = Some(now);
+
+        // The GPU writes timestamps in order, so frames are done oldest first
+        while self.pending_queries.front().is_some_and(|(_, end)| end.is_ready()) {
+            let (start, end) = self.pending_queries.pop_front().unwrap();
+            self.gpu_time.push(end.get().saturating_sub(start.get()) as f32 / 1e6);
+        }
+        self.frame_start_query = self.timestamp(facade);
+    }
+
+    // None where the driver has no timer queries
+    fn timestamp<F: Facade + ?Sized>(&mut self, facade: &F) -> Option<TimestampQuery> {
+        if self.gpu_timing_unsupported {
+            return None;
+        }
+        let query = TimestampQuery::new(facade);
+        self.gpu_timing_unsupported = query.is_none();
+        query
+    }
+
+    // Takes the draw counts since the last call, anything drawn after this is counted in the next frame
+    pub fn end_frame<F: Facade + ?Sized>(&mut self, facade: &F) {
+        if let Some(frame_start) = self.frame_start {
+            self.cpu_time.push(frame_start.elapsed().as_secs_f32() * 1000.0);
+        }
+        self.draw_calls = DRAW_CALLS.swap(0, Ordering::Relaxed);
+        self.triangles = TRIANGLES.swap(0, Ordering::Relaxed);
+        if let (Some(start), Some(end)) = (self.frame_start_query.take(), self.timestamp(facade)) {
+            if self.pending_queries.len() == MAX_PENDING_QUERIES {
+                self.pending_queries.pop_front();
+            }
+            self.pending_queries.push_back((start, end));
+        }
+
+        let row = self.csv_row();
+        if let Some(csv) = &mut self.csv {
+            if let Err(err) = writeln!(csv, "{}", row) {
+                eprintln!("Could not write frame statistics, stopping the log: {}", err);
+                self.csv = None;
+            }
+        }
+        self.frames += 1;
+    }
+
+    // The CSV line of the frame being ended, times without a value yet are left empty.
+    // The GPU column holds the latest result that has come back, which belongs to a slightly earlier frame.
+    fn csv_row(&self) -> String {
+        let milliseconds = |value: Option<f32>| value.map_or_else(String::new, |value| format!("{:.3}", value));
+        format!("{},{},{},{},{},{},{},{}", self.frames, milliseconds(self.frame_time.last()), milliseconds(self.cpu_time.last()),
+                milliseconds(self.gpu_time.last()), self.draw_calls, self.triangles, self.buffer_bytes, self.texture_bytes)
+    }
+
+    // Writes out what the CSV log still buffers, call before the program exits
+    pub fn flush(&mut self) {
+        if let Some(csv) = &mut self.csv {
+            if let Err(err) = csv.flush() {
+                eprintln!("Could not write frame statistics: {}", err);
+            }
+        }
+    }
+
+    // Lines for the HUD: rolling average and percentiles of each time, then the workload of the last frame
+    pub fn summary(&self) -> Vec<String> {
+        let timing = |name: &str, series: &Series| match (series.average(), series.percentile(50.0), series.percentile(95.0), series.percentile(99.0)) {
+            (Some(average), Some(p50), Some(p95), Some(p99)) => {
+                format!("{:<5} {:6.2} ms  p50 {:6.2}  p95 {:6.2}  p99 {:6.2}", name, average, p50, p95, p99)
+            }
+            _ => format!("{:<5}    n/a", name),
+        };
+        let fps = self.frame_time.average().map_or(0.0, |average| 1000.0 / average.max(1e-3));
+        vec![
+            format!("{:.0} fps", fps),
+            timing("frame", &self.frame_time),
+            timing("cpu", &self.cpu_time),
+            timing("gpu", &self.gpu_time),
+            format!("{} draw calls, {} triangles", self.draw_calls, self.triangles),
+            format!("buffers {:.1} MiB, textures {:.1} MiB", self.buffer_bytes as f32 / 1048576.0, self.texture_bytes as f32 / 1048576.0),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(values: &[f32]) -> Series {
+        let mut series = Series::default();
+        for &value in values {
+            series.push(value);
+        }
+        series
+    }
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let series = series(&[5.0, 1.0, 4.0, 2.0, 3.0]);
+        assert_eq!(series.percentile(0.0), Some(1.0));
+        assert_eq!(series.percentile(40.0), Some(2.0));
+        // 41% of 5 samples rounds up to the third
+        assert_eq!(series.percentile(41.0), Some(3.0));
+        assert_eq!(series.percentile(50.0), Some(3.0));
+        assert_eq!(series.percentile(100.0), Some(5.0));
+        assert_eq!(Series::default().percentile(50.0), None);
+        assert_eq!(Series::default().average(), None);
+    }
+
+    #[test]
+    fn csv_rows_leave_missing_times_empty() {
+        let mut stats = FrameStats { frames: 7, cpu_time: series(&[1.25]), draw_calls: 3, triangles: 992, buffer_bytes: 2048,
+                                     texture_bytes: 64, ..Default::default() };
+        assert_eq!(stats.csv_row(), "7,,1.250,,3,992,2048,64");
+        stats.frame_time.push(16.6667);
+        stats.gpu_time.push(0.5);
+        assert_eq!(stats.csv_row(), "7,16.667,1.250,0.500,3,992,2048,64");
+    }
+}
@@ -0,0 +1,151 @@
+use crate::math;
+use crate::mesh::{Mesh, MeshError, DEFAULT_COLOR};
+use crate::Vertex;
+
+const BINARY_HEADER_LEN: usize = 80;
+const BINARY_TRIANGLE_LEN: usize = 50;
+
+// Facet normal and the three corners of the facet
+type Facet = ([f32; 3], [[f32; 3]; 3]);
+
+// Binary STL files have no magic number, but the triangle count after the 80 byte header has to match the file size.
+// ASCII files start with "solid", which some exporters also write into binary headers, so the size check goes first.
+pub fn is_binary_stl(data: &[u8]) -> bool {
+    if data.len() < BINARY_HEADER_LEN + 4 {
+        return false;
+    }
+    let count = u32::from_le_bytes(data[BINARY_HEADER_LEN..BINARY_HEADER_LEN + 4].try_into().unwrap()) as usize;
+    data.len() == BINARY_HEADER_LEN + 4 + count * BINARY_TRIANGLE_LEN
+}
+
+pub fn is_ascii_stl(data: &[u8]) -> bool {
+    data.trim_ascii_start().starts_with(b"solid")
+}
+
+// Every facet gets its own three vertices carrying the facet normal, so hard edges stay hard
+pub fn load_stl(data: &[u8]) -> Result<Mesh, MeshError> {
+    let facets = if is_binary_stl(data) {
+        parse_binary(data)
+    } else if is_ascii_stl(data) {
+        parse_ascii(data)?
+    } else {
+        return Err(MeshError::InvalidData(String::from("not an STL file")));
+    };
+
+    let mut mesh = Mesh::default();
+    for (normal, corners) in facets {
+        // Lots of exporters write zero normals and leave it to the reader, fall back to the winding order
+        let normal = if normal == [0.0, 0.0, 0.0] {
+            math::normalize(math::cross(math::sub(corners[1], corners[0]), math::sub(corners[2], corners[0])))
+        } else {
+            math::normalize(normal)
+        };
+
+        for position in corners {
+            let index = u32::try_from(mesh.vertices.len()).map_err(|_| MeshError::TooManyVertices(mesh.vertices.len() + 1))?;
+            mesh.vertices.push(Vertex { position, color: DEFAULT_COLOR, normal, tex_coords: [0.0, 0.0], tangent: [0.0; 4] });
+            mesh.indices.push(index);
+        }
+    }
+    Ok(mesh)
+}
+
+fn parse_binary(data: &[u8]) -> Vec<Facet> {
+    // Each triangle is a normal, three corners and a 2 byte attribute we ignore
+    data[BINARY_HEADER_LEN + 4..].chunks_exact(BINARY_TRIANGLE_LEN).map(|triangle| {
+        let read_vec3 = |offset: usize| -> [f32; 3] {
+            [0, 1, 2].map(|i| f32::from_le_bytes(triangle[offset + i * 4..offset + i * 4 + 4].try_into().unwrap()))
+        };
+        (read_vec3(0), [read_vec3(12), read_vec3(24), read_vec3(36)])
+    }).collect()
+}
+
+fn parse_ascii(data: &[u8]) -> Result<Vec<Facet>, MeshError> {
+    let text = String::from_utf8_lossy(data);
+    let mut tokens = text.split_ascii_whitespace();
+    let mut facets = Vec::new();
+
+    let read_vec3 = |tokens: &mut std::str::SplitAsciiWhitespace| -> Result<[f32; 3], MeshError> {
+        let mut v = [0.0; 3];
+        for component in &mut v {
+            *component = tokens.next()
+                .and_then(|token| token.parse().ok())
+                .ok_or_else(|| MeshError::InvalidData(String::from("STL: expected a number")))?;
+        }
+        Ok(v)
+    };
+
+    while let Some(token) = tokens.next() {
+        // "facet normal nx ny nz outer loop vertex x y z vertex x y z vertex x y z endloop endfacet"
+        if token != "facet" {
+            continue;
+        }
+        if tokens.next() != Some("normal") {
+            return Err(MeshError::InvalidData(String::from("STL: expected 'normal' after 'facet'")));
+        }
+        let normal = read_vec3(&mut tokens)?;
+
+        let mut corners = Vec::with_capacity(3);
+        while let Some(token) = tokens.next() {
+            match token {
+                "vertex" => corners.push(read_vec3(&mut tokens)?),
+                "endfacet" => break,
+                _ => (),
+            }
+        }
+        // Facets are triangles in practice, fan anything bigger just in case
+        for i in 1..corners.len().saturating_sub(1) {
+            facets.push((normal, [corners[0], corners[i], corners[i + 1]]));
+        }
+    }
+    Ok(facets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn binary_stl(facets: &[Facet]) -> Vec<u8> {
+        // Starts with "solid" like some exporters write, the size check still has to pick binary
+        let mut data = b"solid but actually binary".to_vec();
+        data.resize(BINARY_HEADER_LEN, 0);
+        data.extend_from_slice(&(facets.len() as u32).to_le_bytes());
+        for (normal, corners) in facets {
+            for value in normal.iter().chain(corners.iter().flatten()) {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            data.extend_from_slice(&[0, 0]);
+        }
+        data
+    }
+
+    #[test]
+    fn ascii_facets_get_their_own_vertices() {
+        let data = b"solid test\nfacet normal 0 0 2\n outer loop\n  vertex 0 0 0\n  vertex 1 0 0\n  vertex 0 1 0\n endloop\nendfacet\n\
+                     facet normal 0 0 0\n outer loop\n  vertex 0 0 0\n  vertex 0 1 0\n  vertex 0 0 1\n endloop\nendfacet\nendsolid test\n";
+        assert!(is_ascii_stl(data) && !is_binary_stl(data));
+        let mesh = load_stl(data).unwrap();
+        assert_eq!(mesh.vertices.len(), 6);
+        assert_eq!(mesh.indices, vec![0, 1, 2, 3, 4, 5]);
+        // Normalized, and recomputed from the winding where the file left it zero
+        assert_eq!(mesh.vertices[0].normal, [0.0, 0.0, 1.0]);
+        assert_eq!(mesh.vertices[3].normal, [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn binary_facets_are_read_little_endian() {
+        let data = binary_stl(&[([0.0, 0.0, 1.0], [[0.0, 0.0, 0.0], [1.5, 0.0, 0.0], [0.0, -2.0, 0.25]])]);
+        assert!(is_binary_stl(&data));
+        let mesh = load_stl(&data).unwrap();
+        assert_eq!(mesh.vertices.len(), 3);
+        assert_eq!(mesh.vertices[2].position, [0.0, -2.0, 0.25]);
+        assert_eq!(mesh.vertices[1].normal, [0.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn size_mismatch_is_not_binary() {
+        let mut data = binary_stl(&[([0.0, 0.0, 1.0], [[0.0; 3]; 3])]);
+        data.push(0);
+        assert!(!is_binary_stl(&data));
+    }
+}
@@ -0,0 +1,53 @@
+use crate::math;
+use crate::mesh::Mesh;
+
+// True when every vertex has a tangent, loaders leave it zeroed when the file has none
+pub fn has_tangents(mesh: &Mesh) -> bool {
+    mesh.vertices.iter().all(|vertex| vertex.tangent != [0.0; 4])
+}
+
+// Fills in the tangents of a mesh from its positions, normals and texture coordinates (Lengyel's method).
+// The tangent points along +u, w is +1 or -1 so that cross(normal, tangent.xyz) * w points along +v like glTF expects.
+// Vertices whose triangles have no usable texture coordinates get an arbitrary tangent perpendicular to the normal.
+pub fn generate_tangents(mesh: &mut Mesh) {
+    let mut u_directions = vec![[0.0f32; 3]; mesh.vertices.len()];
+    let mut v_directions = vec![[0.0f32; 3]; mesh.vertices.len()];
+
+    for triangle in mesh.indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| &mesh.vertices[triangle[i] as usize]);
+        let edge1 = math::sub(b.position, a.position);
+        let edge2 = math::sub(c.position, a.position);
+        let (du1, dv1) = (b.tex_coords[0] - a.tex_coords[0], b.tex_coords[1] - a.tex_coords[1]);
+        let (du2, dv2) = (c.tex_coords[0] - a.tex_coords[0], c.tex_coords[1] - a.tex_coords[1]);
+
+        let determinant = du1 * dv2 - du2 * dv1;
+        if determinant.abs() < 1e-12 {
+            continue;
+        }
+        let r = 1.0 / determinant;
+        let u_direction = math::scale(math::sub(math::scale(edge1, dv2), math::scale(edge2, dv1)), r);
+        let v_direction = math::scale(math::sub(math::scale(edge2, du1), math::scale(edge1, du2)), r);
+        for &index in triangle {
+            u_directions[index as usize] = math::add(u_directions[index as usize], u_direction);
+            v_directions[index as usize] = math::add(v_directions[index as usize], v_direction);
+        }
+    }
+
+    for ((vertex, u_direction), v_direction) in mesh.vertices.iter_mut().zip(u_directions).zip(v_directions) {
+        let normal = vertex.normal;
+        // Gram-Schmidt, keep only the part of the u direction lying in the tangent plane
+        let mut tangent = math::normalize(math::sub(u_direction, math::scale(normal, math::dot(normal, u_direction))));
+        if tangent == [0.0, 0.0, 0.0] {
+            tangent = perpendicular(normal);
+        }
+        let handedness = if math::dot(math::cross(normal, tangent), v_direction) < 0.0 { -1.0 } else { 1.0 };
+        vertex.tangent = [tangent[0], tangent[1], tangent[2], handedness];
+    }
+}
+
+// Any unit vector perpendicular to the normal, crossed with whichever axis is furthest from it
+fn perpendicular(normal: [f32; 3]) -> [f32; 3] {
+    let axis = if normal[0].abs() < 0.9 { [1.0, 0.0, 0.0] } else { [0.0, 1.0, 0.0] };
+    let tangent = math::normalize(math::cross(axis, normal));
+    if tangent == [0.0, 0.0, 0.0] { [1.0, 0.0, 0.0] } else { tangent }
+}
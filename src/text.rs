@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use ab_glyph::{Font, FontVec, GlyphId, ScaleFont};
+use glium::backend::Facade;
+use glium::texture::{ClientFormat, MipmapsOption, RawImage2d, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+use glium::Surface;
+
+use crate::math::Mat4;
+
+#[derive(Copy, Clone, Debug)]
+struct TextVertex {
+    // Where the text was placed: pixels from the top left corner for screen text, a model space point for world text
+    position: [f32; 3],
+    // The glyph corner relative to `position`, in pixels for screen text and view space units for world text
+    offset: [f32; 2],
+    tex_coords: [f32; 2],
+    color: [f32; 4],
+    outline_color: [f32; 4],
+}
+implement_vertex!(TextVertex, position, offset, tex_coords, color, outline_color);
+
+// Fonts tried when none is given with --font
+pub const DEFAULT_FONTS: &[&str] = &[
+    "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+    "/usr/share/fonts/TTF/DejaVuSans.ttf",
+    "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+    "/Library/Fonts/Arial.ttf",
+    "/System/Library/Fonts/Supplemental/Arial.ttf",
+    "C:\\Windows\\Fonts\\arial.ttf",
+];
+
+// Glyphs are rendered into the atlas this many pixels high, from ascent to descent. The distance field keeps
+// edges sharp well beyond that, drawing much smaller than about a third of it starts to lose thin strokes.
+const BASE_SIZE: f32 = 48.0;
+// Pixels of distance the field covers on each side of an edge, also the padding around every glyph in the atlas
+const SPREAD: usize = 8;
+const ATLAS_WIDTH: usize = 1024;
+// How far out the outline reaches, in the 0 to 1 distance values of the atlas where 0.5 is the glyph's edge
+const OUTLINE_WIDTH: f32 = 0.25;
+// Drawn for characters the font or the atlas does not have
+const FALLBACK: char = '?';
+
+#[derive(Debug)]
+pub enum FontError {
+    Io(io::Error),
+    // Not a TrueType or OpenType font
+    InvalidFont,
+}
+
+impl fmt::Display for FontError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FontError::Io(err) => write!(f, "could not read font: {}", err),
+            FontError::InvalidFont => write!(f, "not a TrueType or OpenType font"),
+        }
+    }
+}
+
+impl Error for FontError {}
+
+impl From<io::Error> for FontError {
+    fn from(err: io::Error) -> Self {
+        FontError::Io(err)
+    }
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum HorizontalAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum VerticalAlign {
+    // The position is at the top of the first line
+    #[default]
+    Top,
+    Middle,
+    // Under the descenders of the last line
+    Bottom,
+}
+
+// How a string is drawn. `size` is the height of a line from ascent to descent, in pixels for screen text and
+// view space units for world text. The outline is left out while its alpha is 0.
+#[derive(Copy, Clone, Debug)]
+pub struct TextStyle {
+    pub size: f32,
+    pub color: [f32; 4],
+    pub outline_color: [f32; 4],
+    pub horizontal_align: HorizontalAlign,
+    pub vertical_align: VerticalAlign,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        TextStyle { size: 16.0, color: [1.0, 1.0, 1.0, 1.0], outline_color: [0.0; 4], horizontal_align: HorizontalAlign::Left,
+                    vertical_align: VerticalAlign::Top }
+    }
+}
+
+// A glyph's padded distance field quad, relative to the pen position on the baseline in units of the text size with y down
+#[derive(Copy, Clone, Debug)]
+struct GlyphQuad {
+    min: [f32; 2],
+    max: [f32; 2],
+    tex_min: [f32; 2],
+    tex_max: [f32; 2],
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Glyph {
+    id: GlyphId,
+    // In units of the text size
+    advance: f32,
+    // None for spaces and other glyphs without an outline
+    quad: Option<GlyphQuad>,
+}
+
+// The glyphs of a font rendered into a distance field atlas and the metrics to lay them out, the part of text
+// drawing that does not need the GPU
+struct FontAtlas {
+    font: FontVec,
+    glyphs: HashMap<char, Glyph>,
+    // Both in units of the text size, ascent above the baseline and the distance from one baseline to the next
+    ascent: f32,
+    line_advance: f32,
+    // ATLAS_WIDTH x atlas_height distance values, top row first
+    pixels: Vec<u8>,
+    atlas_height: usize,
+}
+
+// Text from a TrueType font through a signed distance field atlas, so one atlas draws crisp text at any size.
+// Like DebugDraw, strings are queued during the frame with screen() and world() and draw() renders them in one
+// batch per kind. Text is drawn over everything without depth testing, world labels stay readable behind geometry.
+pub struct TextRenderer {
+    font: FontAtlas,
+    atlas: Texture2d,
+    screen_program: glium::Program,
+    world_program: glium::Program,
+    screen_vertices: Vec<TextVertex>,
+    world_vertices: Vec<TextVertex>,
+}
+
+impl FontAtlas {
+    // Loads the font and renders printable ASCII and Latin-1 into the atlas
+    fn load(font_path: &Path) -> Result<Self, FontError> {
+        let font = FontVec::try_from_vec(fs::read(font_path)?).map_err(|_| FontError::InvalidFont)?;
+        let scaled = font.as_scaled(BASE_SIZE);
+
+        // Distance fields of every glyph first, then packed into rows left to right once their sizes are known
+        let mut fields = Vec::new();
+        let mut glyphs = HashMap::new();
+        for character in (' '..='~').chain('\u{a0}'..='\u{ff}') {
+            let id = font.glyph_id(character);
+            if id.0 == 0 && character != ' ' {
+                continue;
+            }
+            let advance = scaled.h_advance(id) / BASE_SIZE;
+            glyphs.insert(character, Glyph { id, advance, quad: None });
+            let Some(outlined) = font.outline_glyph(id.with_scale(BASE_SIZE)) else {
+                continue;
+            };
+            let bounds = outlined.px_bounds();
+            let (width, height) = (bounds.width() as usize + 2 * SPREAD, bounds.height() as usize + 2 * SPREAD);
+            let mut coverage = vec![0.0; width * height];
+            outlined.draw(|x, y, value| coverage[(y as usize + SPREAD) * width + x as usize + SPREAD] = value);
+            let origin = [bounds.min.x - SPREAD as f32, bounds.min.y - SPREAD as f32];
+            fields.push((character, origin, width, height, distance_field(&coverage, width, height)));
+        }
+
+        let mut placements = Vec::new();
+        let (mut x, mut y, mut row_height) = (0, 0, 0);
+        for (_, _, width, height, _) in &fields {
+            if x + width > ATLAS_WIDTH {
+                (x, y, row_height) = (0, y + row_height, 0);
+            }
+            placements.push((x, y));
+            x += width;
+            row_height = row_height.max(*height);
+        }
+        let atlas_height = (y + row_height).max(1);
+
+        let mut pixels = vec![0u8; ATLAS_WIDTH * atlas_height];
+        for ((character, origin, width, height, field), (x, y)) in fields.iter().zip(&placements) {
+            for row in 0..*height {
+                let start = (y + row) * ATLAS_WIDTH + x;
+                pixels[start..start + width].copy_from_slice(&field[row * width..(row + 1) * width]);
+            }
+            let tex = |px: usize, py: usize| [px as f32 / ATLAS_WIDTH as f32, py as f32 / atlas_height as f32];
+            let quad = GlyphQuad {
+                min: [origin[0] / BASE_SIZE, origin[1] / BASE_SIZE],
+                max: [(origin[0] + *width as f32) / BASE_SIZE, (origin[1] + *height as f32) / BASE_SIZE],
+                tex_min: tex(*x, *y),
+                tex_max: tex(x + width, y + height),
+            };
+            glyphs.get_mut(character).unwrap().quad = Some(quad);
+        }
+
+        Ok(FontAtlas {
+            ascent: scaled.ascent() / BASE_SIZE,
+            line_advance: (scaled.height() + scaled.line_gap()) / BASE_SIZE,
+            font,
+            glyphs,
+            pixels,
+            atlas_height,
+        })
+    }
+
+    // Quads of every visible glyph in style.size units, y down from the point the text is aligned to.
+    // Each line is aligned on its own, the vertical alignment applies to the block of lines.
+    fn layout(&self, text: &str, style: &TextStyle) -> Vec<GlyphQuad> {
+        let scaled = self.font.as_scaled(BASE_SIZE);
+        let lines: Vec<&str> = text.lines().collect();
+        let descent = scaled.descent() / BASE_SIZE;
+        let block_height = self.ascent - descent + lines.len().saturating_sub(1) as f32 * self.line_advance;
+        let first_baseline = match style.vertical_align {
+            VerticalAlign::Top => self.ascent,
+            VerticalAlign::Middle => self.ascent - block_height / 2.0,
+            VerticalAlign::Bottom => self.ascent - block_height,
+        };
+
+        let mut quads = Vec::new();
+        for (i, line) in lines.iter().enumerate() {
+            let baseline = first_baseline + i as f32 * self.line_advance;
+            let mut pen = 0.0;
+            let mut previous: Option<GlyphId> = None;
+            let start = quads.len();
+            for character in line.chars() {
+                let Some(glyph) = self.glyphs.get(&character).or_else(|| self.glyphs.get(&FALLBACK)) else {
+                    continue;
+                };
+                if let Some(previous) = previous {
+                    pen += scaled.kern(previous, glyph.id) / BASE_SIZE;
+                }
+                if let Some(quad) = glyph.quad {
+                    quads.push(GlyphQuad {
+                        min: [pen + quad.min[0], baseline + quad.min[1]],
+                        max: [pen + quad.max[0], baseline + quad.max[1]],
+                        ..quad
+                    });
+                }
+                pen += glyph.advance;
+                previous = Some(glyph.id);
+            }
+            let shift = match style.horizontal_align {
+                HorizontalAlign::Left => 0.0,
+                HorizontalAlign::Center => -pen / 2.0,
+                HorizontalAlign::Right => -pen,
+            };
+            for quad in &mut quads[start..] {
+                quad.min[0] += shift;
+                quad.max[0] += shift;
+            }
+        }
+        quads
+    }
+}
+
+impl TextRenderer {
+    // Loads the font, see FontAtlas::load, and uploads its atlas
+    pub fn new<F: Facade + ?Sized>(facade: &F, font_path: &Path) -> Result<Self, FontError> {
+        let mut font = FontAtlas::load(font_path)?;
+        let image = RawImage2d { data: std::mem::take(&mut font.pixels).into(), width: ATLAS_WIDTH as u32,
+                                 height: font.atlas_height as u32, format: ClientFormat::U8 };
+        let atlas = Texture2d::with_format(facade, image, UncompressedFloatFormat::U8, MipmapsOption::NoMipmap).unwrap();
+        // Text goes over the presented image, so its colors are taken as they are on screen
+        let program = |vertex_shader: &str| crate::srgb_program(facade, vertex_shader, "shaders/text/sdf.frag");
+        Ok(TextRenderer {
+            font,
+            atlas,
+            screen_program: program("shaders/text/screen.vert"),
+            world_program: program("shaders/text/world.vert"),
+            screen_vertices: Vec::new(),
+            world_vertices: Vec::new(),
+        })
+    }
+
+    // Queues `text` at `position` in pixels from the top left corner of the target, aligned there as the style says
+    pub fn screen(&mut self, position: [f32; 2], text: &str, style: &TextStyle) {
+        // Whole pixels keep small text from smearing across two rows
+        let position = [position[0].round(), position[1].round(), 0.0];
+        let quads = self.font.layout(text, style);
+        push_quads(&mut self.screen_vertices, position, &quads, style, 1.0);
+    }
+
+    // Queues `text` as a billboard at `position` in model space, facing the camera and upright on screen
+    pub fn world(&mut self, position: [f32; 3], text: &str, style: &TextStyle) {
+        let quads = self.font.layout(text, style);
+        // View space y goes up, the layout's goes down
+        push_quads(&mut self.world_vertices, position, &quads, style, -1.0);
+    }
+
+    // Draws and forgets everything queued since the last call, world text with the given matrices
+    pub fn draw<F: Facade + ?Sized, S: Surface>(&mut self, facade: &F, target: &mut S, perspective: &Mat4, view: &Mat4, model: &Mat4) {
+        let (width, height) = target.get_dimensions();
+        let atlas = self.atlas.sampled().minify_filter(MinifySamplerFilter::Linear).magnify_filter(MagnifySamplerFilter::Linear);
+        let params = glium::DrawParameters { blend: glium::Blend::alpha_blending(), ..Default::default() };
+        let triangles = glium::index::NoIndices(glium::index::PrimitiveType::TrianglesList);
+        let outline_edge = 0.5 - OUTLINE_WIDTH;
+
+        if !self.world_vertices.is_empty() {
+            let vertex_buffer = glium::VertexBuffer::new(facade, &self.world_vertices).unwrap();
+            let uniforms = uniform! { perspective: *perspective, view: *view, model: *model, u_atlas: atlas, u_outline_edge: outline_edge };
+            target.draw(&vertex_buffer, triangles, &self.world_program, &uniforms, &params).unwrap();
+            crate::stats::count_draw(self.world_vertices.len() / 3);
+            self.world_vertices.clear();
+        }
+        // Screen text goes on top of the labels in the scene
+        if !self.screen_vertices.is_empty() {
+            let vertex_buffer = glium::VertexBuffer::new(facade, &self.screen_vertices).unwrap();
+            let uniforms = uniform! { u_inverse_viewport: [1.0 / width as f32, 1.0 / height as f32], u_atlas: atlas, u_outline_edge: outline_edge };
+            target.draw(&vertex_buffer, triangles, &self.screen_program, &uniforms, &params).unwrap();
+            crate::stats::count_draw(self.screen_vertices.len() / 3);
+            self.screen_vertices.clear();
+        }
+    }
+}
+
+// Two triangles per glyph, the layout scaled to style.size and its y axis multiplied by `y_direction`
+fn push_quads(vertices: &mut Vec<TextVertex>, position: [f32; 3], quads: &[GlyphQuad], style: &TextStyle, y_direction: f32) {
+    for quad in quads {
+        let corner = |x: usize, y: usize| {
+            let offset = [if x == 0 { quad.min[0] } else { quad.max[0] }, if y == 0 { quad.min[1] } else { quad.max[1] }];
+            TextVertex {
+                position,
+                offset: [offset[0] * style.size, offset[1] * style.size * y_direction],
+                tex_coords: [if x == 0 { quad.tex_min[0] } else { quad.tex_max[0] }, if y == 0 { quad.tex_min[1] } else { quad.tex_max[1] }],
+                color: style.color,
+                outline_color: style.outline_color,
+            }
+        };
+        vertices.extend([corner(0, 0), corner(1, 0), corner(0, 1), corner(0, 1), corner(1, 0), corner(1, 1)]);
+    }
+}
+
+// Signed distance field of a coverage bitmap as bytes, 128 on the edge, higher inside and lower outside,
+// reaching 0 and 255 SPREAD pixels away from it
+fn distance_field(coverage: &[f32], width: usize, height: usize) -> Vec<u8> {
+    let inside: Vec<bool> = coverage.iter().map(|&value| value >= 0.5).collect();
+    let outside: Vec<bool> = inside.iter().map(|&inside| !inside).collect();
+    let to_inside = distance_transform(&inside, width, height);
+    let to_outside = distance_transform(&outside, width, height);
+    (0..width * height).map(|i| {
+        // The edge lies about halfway between a pixel and its nearest neighbour on the other side
+        let distance = if inside[i] { 0.5 - to_outside[i] } else { to_inside[i] - 0.5 };
+        ((0.5 - distance / (2.0 * SPREAD as f32)).clamp(0.0, 1.0) * 255.0).round() as u8
+    }).collect()
+}
+
+// Distance from every pixel to the nearest pixel where `seeds` is set, by passing the offset to the nearest seed on to
+// neighbours in a forward and a backward sweep (8SSEDT). Close to exact, a glyph's small errors do not show.
+fn distance_transform(seeds: &[bool], width: usize, height: usize) -> Vec<f32> {
+    const FAR: [i32; 2] = [1 << 14, 1 << 14];
+    let mut offsets: Vec<[i32; 2]> = seeds.iter().map(|&seed| if seed { [0, 0] } else { FAR }).collect();
+    let length_squared = |offset: [i32; 2]| offset[0] * offset[0] + offset[1] * offset[1];
+    let relax = |offsets: &mut Vec<[i32; 2]>, x: usize, y: usize, dx: i32, dy: i32| {
+        let (nx, ny) = (x as i32 + dx, y as i32 + dy);
+        if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {
+            return;
+        }
+        let neighbour = offsets[ny as usize * width + nx as usize];
+        let candidate = [neighbour[0] + dx, neighbour[1] + dy];
+        if length_squared(candidate) < length_squared(offsets[y * width + x]) {
+            offsets[y * width + x] = candidate;
+        }
+    };
+
+    for y in 0..height {
+        for x in 0..width {
+            for (dx, dy) in [(-1, 0), (0, -1), (-1, -1), (1, -1)] {
+                relax(&mut offsets, x, y, dx, dy);
+            }
+        }
+        for x in (0..width).rev() {
+            relax(&mut offsets, x, y, 1, 0);
+        }
+    }
+    for y in (0..height).rev() {
+        for x in (0..width).rev() {
+            for (dx, dy) in [(1, 0), (0, 1), (-1, 1), (1, 1)] {
+                relax(&mut offsets, x, y, dx, dy);
+            }
+        }
+        for x in 0..width {
+            relax(&mut offsets, x, y, -1, 0);
+        }
+    }
+    offsets.into_iter().map(|offset| (length_squared(offset) as f32).sqrt()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The layout needs a real font and the repository does not ship one, so the tests using this are ignored by default.
+    // Run them with `cargo test -- --ignored` where one of the default fonts is installed.
+    fn installed_font() -> FontAtlas {
+        DEFAULT_FONTS.iter().find_map(|path| FontAtlas::load(Path::new(path)).ok()).expect("none of text::DEFAULT_FONTS is installed")
+    }
+
+    fn style(horizontal_align: HorizontalAlign, vertical_align: VerticalAlign) -> TextStyle {
+        TextStyle { horizontal_align, vertical_align, ..Default::default() }
+    }
+
+    fn assert_close(a: f32, b: f32) {
+        assert!((a - b).abs() < 1e-5, "{} is not {}", a, b);
+    }
+
+    #[test]
+    fn distance_field_is_128_at_the_edge_and_falls_across_it() {
+        // Inside on the left half of a single row
+        let width = 4 * SPREAD;
+        let coverage: Vec<f32> = (0..width).map(|x| if x < width / 2 { 1.0 } else { 0.0 }).collect();
+        let field = distance_field(&coverage, width, 1);
+
+        // The edge runs between the two middle pixels
+        let edge = (field[width / 2 - 1] as f32 + field[width / 2] as f32) / 2.0;
+        assert!((edge - 128.0).abs() <= 1.0, "{} at the edge", edge);
+        assert!(field.windows(2).all(|pair| pair[0] >= pair[1]), "{:?}", field);
+        assert!(field[width / 2 - SPREAD..width / 2 + SPREAD].windows(2).all(|pair| pair[0] > pair[1]), "{:?}", field);
+        assert_eq!((field[0], field[width - 1]), (255, 0));
+    }
+
+    #[test]
+    #[ignore = "needs one of text::DEFAULT_FONTS installed"]
+    fn lines_are_aligned_on_their_own() {
+        let font = installed_font();
+        // Digits have the same advance and no kerning
+        let advance = font.glyphs[&'1'].advance;
+        let glyph_min = font.glyphs[&'1'].quad.unwrap().min[0];
+        for (align, shift) in [(HorizontalAlign::Left, 0.0), (HorizontalAlign::Center, -0.5), (HorizontalAlign::Right, -1.0)] {
+            let quads = font.layout("1\n11", &style(align, VerticalAlign::Top));
+            assert_eq!(quads.len(), 3);
+            assert_close(quads[0].min[0], glyph_min + shift * advance);
+            assert_close(quads[1].min[0], glyph_min + shift * 2.0 * advance);
+            assert_close(quads[2].min[0], quads[1].min[0] + advance);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs one of text::DEFAULT_FONTS installed"]
+    fn vertical_alignment_moves_the_whole_block() {
+        let font = installed_font();
+        let descent = font.font.as_scaled(BASE_SIZE).descent() / BASE_SIZE;
+        for (text, block_height) in [("1", font.ascent - descent), ("1\n1", font.ascent - descent + font.line_advance)] {
+            let top = font.layout(text, &style(HorizontalAlign::Left, VerticalAlign::Top))[0].min[1];
+            let middle = font.layout(text, &style(HorizontalAlign::Left, VerticalAlign::Middle))[0].min[1];
+            let bottom = font.layout(text, &style(HorizontalAlign::Left, VerticalAlign::Bottom))[0].min[1];
+            assert_close(middle - top, -block_height / 2.0);
+            assert_close(bottom - top, -block_height);
+        }
+    }
+}
@@ -0,0 +1,165 @@
+use std::fmt;
+
+use glium::backend::Facade;
+use glium::texture::{MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction};
+use glium::winit::keyboard::KeyCode;
+
+use crate::fullscreen::FullscreenQuad;
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum ToneMapping {
+    // Scales by the exposure and clips whatever is still above 1.0
+    Exposure,
+    Reinhard,
+    #[default]
+    Aces,
+}
+
+impl ToneMapping {
+    // Names accepted by --tone-map
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "exposure" => Some(ToneMapping::Exposure),
+            "reinhard" => Some(ToneMapping::Reinhard),
+            "aces" => Some(ToneMapping::Aces),
+            _ => None,
+        }
+    }
+
+    // Order the T key steps through
+    pub fn next(self) -> Self {
+        match self {
+            ToneMapping::Exposure => ToneMapping::Reinhard,
+            ToneMapping::Reinhard => ToneMapping::Aces,
+            ToneMapping::Aces => ToneMapping::Exposure,
+        }
+    }
+
+    // Value of u_operator in tone_map.frag
+    fn shader_operator(self) -> i32 {
+        match self {
+            ToneMapping::Exposure => 0,
+            ToneMapping::Reinhard => 1,
+            ToneMapping::Aces => 2,
+        }
+    }
+}
+
+impl fmt::Display for ToneMapping {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ToneMapping::Exposure => write!(f, "exposure only"),
+            ToneMapping::Reinhard => write!(f, "Reinhard"),
+            ToneMapping::Aces => write!(f, "ACES filmic"),
+        }
+    }
+}
+
+// How quickly auto exposure catches up with a change in brightness, higher is faster
+const ADAPTATION_SPEED: f32 = 1.5;
+
+pub const KEY_HELP: &str = "T to cycle tone mapping, E to toggle auto exposure, [ and ] to change exposure";
+
+// Turns the linear HDR scene into an 8 bit sRGB image ready to be presented.
+// Exposure is in stops, with auto exposure on it is added on top of the exposure that brings the
+// average scene luminance to middle grey. The average is a log luminance image averaged by its mipmaps,
+// adapted over time in a pair of 1x1 textures that take turns being read and written.
+pub struct ToneMapper {
+    pub operator: ToneMapping,
+    pub exposure: f32,
+    pub auto_exposure: bool,
+    quad: FullscreenQuad,
+    luminance_program: glium::Program,
+    adapt_program: glium::Program,
+    tone_map_program: glium::Program,
+    luminance: Option<Texture2d>,
+    adapted: [Texture2d; 2],
+    // Which of the adapted textures holds the latest value, None until the first frame was measured
+    current: Option<usize>,
+    output: Option<Texture2d>,
+}
+
+impl ToneMapper {
+    pub fn new<F: Facade + ?Sized>(facade: &F, operator: ToneMapping, exposure: f32, auto_exposure: bool) -> Self {
+        let adapted = || Texture2d::empty_with_format(facade, UncompressedFloatFormat::F32, MipmapsOption::NoMipmap, 1, 1).unwrap();
+        ToneMapper {
+            operator,
+            exposure,
+            auto_exposure,
+            quad: FullscreenQuad::new(facade),
+            luminance_program: FullscreenQuad::program(facade, "shaders/luminance.frag"),
+            adapt_program: FullscreenQuad::program(facade, "shaders/adapt_exposure.frag"),
+            tone_map_program: FullscreenQuad::program(facade, "shaders/tone_map.frag"),
+            luminance: None,
+            adapted: [adapted(), adapted()],
+            current: None,
+            output: None,
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyCode) {
+        match key {
+            KeyCode::KeyT => {
+                self.operator = self.operator.next();
+                println!("Tone mapping: {}", self.operator);
+            }
+            KeyCode::KeyE => {
+                self.auto_exposure = !self.auto_exposure;
+                println!("Auto exposure: {}", if self.auto_exposure { "on" } else { "off" });
+            }
+            KeyCode::BracketLeft | KeyCode::BracketRight => {
+                self.exposure += if key == KeyCode::BracketLeft { -0.5 } else { 0.5 };
+                println!("Exposure: {:+.1} EV", self.exposure);
+            }
+            _ => (),
+        }
+    }
+
+    // Tone maps `color` and returns the result, `elapsed` is the frame time in seconds used to adapt the exposure
+    pub fn run<F: Facade + ?Sized>(&mut self, facade: &F, color: &Texture2d, elapsed: f32) -> &Texture2d {
+        let (width, height) = color.dimensions();
+        if self.output.as_ref().is_none_or(|output| output.dimensions() != (width, height)) {
+            self.output = Some(Texture2d::empty_with_format(facade, UncompressedFloatFormat::U8U8U8U8, MipmapsOption::NoMipmap, width, height).unwrap());
+            self.luminance = Some(Texture2d::empty_with_format(facade, UncompressedFloatFormat::F32, MipmapsOption::EmptyMipmaps, width, height).unwrap());
+        }
+        let sampled = color.sampled()
+            .minify_filter(MinifySamplerFilter::Nearest)
+            .magnify_filter(MagnifySamplerFilter::Nearest)
+            .wrap_function(SamplerWrapFunction::Clamp);
+
+        if self.auto_exposure {
+            let luminance = self.luminance.as_ref().unwrap();
+            self.quad.draw(&mut luminance.as_surface(), &self.luminance_program, &uniform! { u_texture: sampled });
+            // Safe as the texture was created with room for every mip level
+            unsafe { luminance.generate_mipmaps() };
+
+            let (previous, next, blend) = match self.current {
+                Some(current) => (current, 1 - current, 1.0 - (-elapsed * ADAPTATION_SPEED).exp()),
+                // Nothing to adapt from yet, start at the measured value
+                None => (0, 1, 1.0),
+            };
+            let uniforms = uniform! {
+                u_luminance: luminance.sampled().minify_filter(MinifySamplerFilter::NearestMipmapNearest),
+                u_previous: self.adapted[previous].sampled(),
+                u_blend: blend,
+            };
+            self.quad.draw(&mut self.adapted[next].as_surface(), &self.adapt_program, &uniforms);
+            self.current = Some(next);
+        } else {
+            // Adapt from scratch when auto exposure comes back on
+            self.current = None;
+        }
+
+        let output = self.output.as_ref().unwrap();
+        let uniforms = uniform! {
+            u_texture: sampled,
+            u_adapted_luminance: self.adapted[self.current.unwrap_or(0)].sampled(),
+            u_exposure: 2f32.powf(self.exposure),
+            u_auto_exposure: self.auto_exposure,
+            u_operator: self.operator.shader_operator(),
+        };
+        self.quad.draw(&mut output.as_surface(), &self.tone_map_program, &uniforms);
+        output
+    }
+}
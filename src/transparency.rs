@@ -0,0 +1,152 @@
+use std::fmt;
+
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{DepthTexture2d, MipmapsOption, Texture2d, UncompressedFloatFormat};
+use glium::uniforms::{MagnifySamplerFilter, MinifySamplerFilter};
+use glium::{Blend, BlendingFunction, DrawParameters, LinearBlendingFactor, Surface};
+
+use crate::fullscreen::FullscreenQuad;
+use crate::scene::AlphaMode;
+
+// How alpha blended materials are drawn
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum Transparency {
+    // Whole primitives back to front, wrong where transparent surfaces intersect or overlap within a primitive
+    #[default]
+    Sorted,
+    // Weighted blended order independent transparency, no sorting needed but only an approximation of the layering
+    WeightedBlended,
+}
+
+impl Transparency {
+    // Names accepted by --transparency
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sorted" => Some(Transparency::Sorted),
+            "oit" => Some(Transparency::WeightedBlended),
+            _ => None,
+        }
+    }
+
+    // The I key switches between the two
+    pub fn next(self) -> Self {
+        match self {
+            Transparency::Sorted => Transparency::WeightedBlended,
+            Transparency::WeightedBlended => Transparency::Sorted,
+        }
+    }
+}
+
+impl fmt::Display for Transparency {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Transparency::Sorted => write!(f, "sorted back to front"),
+            Transparency::WeightedBlended => write!(f, "weighted blended OIT"),
+        }
+    }
+}
+
+// Value of u_alpha_cutoff in the lighting shaders
+pub fn alpha_cutoff(alpha_mode: AlphaMode) -> f32 {
+    match alpha_mode {
+        AlphaMode::Mask(cutoff) => cutoff,
+        _ => 0.0,
+    }
+}
+
+// Transparent surfaces test against the opaque depth but do not write it, so they never hide each other
+pub fn sorted_parameters<'a>(params: &DrawParameters<'a>) -> DrawParameters<'a> {
+    DrawParameters {
+        blend: Blend::alpha_blending(),
+        depth: glium::Depth { write: false, ..params.depth },
+        ..params.clone()
+    }
+}
+
+// The two passes of weighted blended OIT, see WeightedBlended
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum OitPass {
+    Accumulation,
+    Revealage,
+}
+
+impl OitPass {
+    // Value of u_transparency_pass in teapot_gouraud.frag
+    pub fn shader_pass(self) -> i32 {
+        match self {
+            OitPass::Accumulation => 2,
+            OitPass::Revealage => 3,
+        }
+    }
+
+    pub fn parameters<'a>(self, params: &DrawParameters<'a>) -> DrawParameters<'a> {
+        let color = match self {
+            // Sums the weighted colors and weights
+            OitPass::Accumulation => BlendingFunction::Addition { source: LinearBlendingFactor::One, destination: LinearBlendingFactor::One },
+            // Multiplies together how much of the background each layer lets through
+            OitPass::Revealage => BlendingFunction::Addition { source: LinearBlendingFactor::Zero, destination: LinearBlendingFactor::OneMinusSourceColor },
+        };
+        DrawParameters {
+            blend: Blend { color, alpha: color, constant_value: (0.0, 0.0, 0.0, 0.0) },
+            depth: glium::Depth { write: false, ..params.depth },
+            ..params.clone()
+        }
+    }
+}
+
+// Targets for weighted blended order independent transparency (McGuire and Bavoil 2013). Transparent geometry is drawn
+// once per OitPass into its own target, glium sets one blend function for every attachment so the two cannot share a
+// framebuffer. composite() then lays the result over the opaque scene.
+pub struct WeightedBlended {
+    quad: FullscreenQuad,
+    composite_program: glium::Program,
+    accumulation: Option<Texture2d>,
+    revealage: Option<Texture2d>,
+}
+
+impl WeightedBlended {
+    pub fn new<F: Facade + ?Sized>(facade: &F) -> Self {
+        WeightedBlended {
+            quad: FullscreenQuad::new(facade),
+            composite_program: FullscreenQuad::program(facade, "shaders/transparency/oit_composite.frag"),
+            accumulation: None,
+            revealage: None,
+        }
+    }
+
+    // Sizes the targets for a scene of `size` pixels, call before framebuffer()
+    pub fn prepare<F: Facade + ?Sized>(&mut self, facade: &F, size: (u32, u32)) {
+        let (width, height) = size;
+        if self.accumulation.as_ref().is_none_or(|accumulation| accumulation.dimensions() != size) {
+            let texture = |format| Texture2d::empty_with_format(facade, format, MipmapsOption::NoMipmap, width, height).unwrap();
+            self.accumulation = Some(texture(UncompressedFloatFormat::F16F16F16F16));
+            self.revealage = Some(texture(UncompressedFloatFormat::F16));
+        }
+    }
+
+    // Cleared target for one pass, depth tested against the opaque scene's `depth`
+    pub fn framebuffer<'a, F: Facade + ?Sized>(&'a self, facade: &F, pass: OitPass, depth: &'a DepthTexture2d) -> SimpleFrameBuffer<'a> {
+        let (texture, clear) = match pass {
+            OitPass::Accumulation => (self.accumulation.as_ref().unwrap(), 0.0),
+            // Everything shows through until a layer is drawn
+            OitPass::Revealage => (self.revealage.as_ref().unwrap(), 1.0),
+        };
+        let mut framebuffer = SimpleFrameBuffer::with_depth_buffer(facade, texture, depth).unwrap();
+        framebuffer.clear_color(clear, clear, clear, clear);
+        framebuffer
+    }
+
+    // Blends the transparent layers over what is already in `target`
+    pub fn composite<S: Surface>(&self, target: &mut S) {
+        let (Some(accumulation), Some(revealage)) = (&self.accumulation, &self.revealage) else {
+            return;
+        };
+        let uniforms = uniform! {
+            u_accumulation: accumulation.sampled().minify_filter(MinifySamplerFilter::Nearest).magnify_filter(MagnifySamplerFilter::Nearest),
+            u_revealage: revealage.sampled().minify_filter(MinifySamplerFilter::Nearest).magnify_filter(MagnifySamplerFilter::Nearest),
+        };
+        let params = DrawParameters { blend: Blend::alpha_blending(), ..Default::default() };
+        self.quad.draw_with_parameters(target, &self.composite_program, &uniforms, &params);
+    }
+}
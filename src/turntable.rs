@@ -0,0 +1,239 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{SrgbTexture2d, Texture2d};
+use glium::Surface;
+
+use crate::antialiasing::{self, AntiAliasing, Presenter, SceneTarget};
+use crate::capture::{self, CaptureTarget};
+use crate::math::{self, Mat4};
+use crate::scene::{AlphaMode, Scene};
+use crate::tone_mapping::{ToneMapper, ToneMapping};
+use crate::transparency;
+use crate::viewer::LIGHT;
+use crate::{render_mode, GpuPrimitive, LoadOptions};
+
+// Settings of the turntable command, see the usage comment above main
+#[derive(Clone, Debug)]
+pub struct TurntableOptions {
+    pub angles: u32,
+    pub size: (u32, u32),
+    pub output: PathBuf,
+    pub contact_sheet: bool,
+}
+
+impl Default for TurntableOptions {
+    fn default() -> Self {
+        TurntableOptions { angles: 8, size: (512, 512), output: PathBuf::from("turntable"), contact_sheet: false }
+    }
+}
+
+// Camera height above the model's center, as an angle looking down at it
+const ELEVATION: f32 = 20.0 * std::f32::consts::PI / 180.0;
+const FOV: f32 = std::f32::consts::PI / 3.0;
+// Neutral grey in linear HDR, the catalogue images should not pick up a tint from the background
+const BACKGROUND: (f32, f32, f32, f32) = (0.18, 0.18, 0.18, 1.0);
+
+// Renders `input`, an OBJ file or a directory of them, from options.angles evenly spaced angles around the vertical axis.
+// Images go to options.output as <model>_<angle>.png, plus <model>_sheet.png with all of them when a contact sheet is asked for.
+// Everything is drawn into offscreen textures behind a hidden window, nothing has to be looked at or clicked.
+pub fn render_turntable(input: &str, options: &TurntableOptions, load_options: &LoadOptions) {
+    let input = Path::new(input);
+    let models: Vec<PathBuf> = if input.is_dir() {
+        let mut models: Vec<PathBuf> = match fs::read_dir(input) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("obj")))
+                .collect(),
+            Err(err) => {
+                eprintln!("Could not read {}: {}", input.display(), err);
+                return;
+            }
+        };
+        models.sort();
+        models
+    } else {
+        vec![input.to_path_buf()]
+    };
+    if models.is_empty() {
+        eprintln!("No OBJ files in {}", input.display());
+        return;
+    }
+    if let Err(err) = fs::create_dir_all(&options.output) {
+        eprintln!("Could not create {}: {}", options.output.display(), err);
+        return;
+    }
+
+    let event_loop = glium::winit::event_loop::EventLoop::builder().build().unwrap();
+    let attributes = glium::winit::window::Window::default_attributes().with_title("turntable").with_visible(false);
+    let (_window, display) = glium::backend::glutin::SimpleWindowBuilder::new().set_window_builder(attributes).build(&event_loop);
+    let mut renderer = TurntableRenderer::new(&display, options.size);
+
+    for model in &models {
+        let name = model.file_stem().map_or_else(|| "model".to_string(), |stem| stem.to_string_lossy().to_string());
+        let scene = match crate::load_scene(&model.to_string_lossy(), load_options) {
+            Ok(scene) => scene,
+            Err(err) => {
+                eprintln!("Could not load {}: {}", model.display(), err);
+                continue;
+            }
+        };
+        if scene.bounds().is_none() {
+            eprintln!("{} has nothing to render", model.display());
+            continue;
+        }
+        let meshes = match crate::upload_meshes(&display, &scene) {
+            Ok(meshes) => meshes,
+            Err((mesh, err)) => {
+                eprintln!("Could not upload mesh {} of {}: {}", mesh, model.display(), err);
+                continue;
+            }
+        };
+        let textures = crate::upload_textures(&display, &scene);
+
+        let mut images = Vec::new();
+        for angle in 0..options.angles {
+            let turn = angle as f32 / options.angles as f32 * std::f32::consts::TAU;
+            let image = renderer.render(&display, &scene, &meshes, &textures, turn);
+            let path = options.output.join(format!("{}_{:03}.png", name, angle));
+            match image.save_with_format(&path, image::ImageFormat::Png) {
+                Ok(()) => println!("Wrote {}", path.display()),
+                Err(err) => eprintln!("Could not save {}: {}", path.display(), err),
+            }
+            images.push(image);
+        }
+
+        if options.contact_sheet {
+            let path = options.output.join(format!("{}_sheet.png", name));
+            match contact_sheet(&images).save_with_format(&path, image::ImageFormat::Png) {
+                Ok(()) => println!("Wrote {}", path.display()),
+                Err(err) => eprintln!("Could not save {}: {}", path.display(), err),
+            }
+        }
+    }
+}
+
+// The forward lit path of the viewer cut down to what a catalogue image needs: multisampled, tone mapped,
+// with the light fixed to the model so the turntable shows it from every side
+struct TurntableRenderer {
+    size: (u32, u32),
+    program: glium::Program,
+    scene_target: SceneTarget,
+    tone_mapper: ToneMapper,
+    presenter: Presenter,
+    capture_target: CaptureTarget,
+    white_texture: SrgbTexture2d,
+    // Stands in for the SSAO texture, the turntable has no ambient occlusion
+    no_occlusion: Texture2d,
+}
+
+impl TurntableRenderer {
+    fn new<F: Facade + ?Sized>(facade: &F, size: (u32, u32)) -> Self {
+        let samples = if antialiasing::msaa_supported(facade, 4) { 4 } else { 1 };
+        TurntableRenderer {
+            size,
+            program: glium::Program::from_source(facade, crate::read_shader("shaders/teapot_gouraud.vert").as_str(),
+                                                 crate::read_shader("shaders/teapot_gouraud.frag").as_str(), None).unwrap(),
+            scene_target: SceneTarget::new(facade, size, samples).unwrap(),
+            tone_mapper: ToneMapper::new(facade, ToneMapping::Aces, 0.0, false),
+            presenter: Presenter::new(facade),
+            capture_target: CaptureTarget::default(),
+            white_texture: SrgbTexture2d::new(facade, vec![vec![(1.0f32, 1.0, 1.0, 1.0)]]).unwrap(),
+            no_occlusion: Texture2d::new(facade, vec![vec![(1.0f32, 1.0, 1.0, 1.0)]]).unwrap(),
+        }
+    }
+
+    // One image with the camera `turn` radians around the scene's vertical axis, far enough out to fit the whole scene
+    fn render<F: Facade + ?Sized>(&mut self, facade: &F, scene: &Scene, meshes: &[Vec<GpuPrimitive>], textures: &[SrgbTexture2d],
+                                  turn: f32) -> image::RgbaImage {
+        let (width, height) = self.size;
+        let (min, max) = scene.bounds().unwrap_or(([0.0; 3], [0.0; 3]));
+        let center = math::scale(math::add(min, max), 0.5);
+        let radius = (0.5 * math::length(math::sub(max, min))).max(1e-3);
+
+        // The bounding sphere has to fit the narrower of the two fields of view
+        let half_fov = ((FOV / 2.0).tan() * (width as f32 / height as f32).min(1.0)).atan();
+        let distance = radius / half_fov.sin() * 1.05;
+        let direction = [-turn.sin() * ELEVATION.cos(), -ELEVATION.sin(), turn.cos() * ELEVATION.cos()];
+        let position = math::sub(center, math::scale(direction, distance));
+        let view = crate::view_matrix(&position, &direction, &[0.0, 1.0, 0.0]);
+        let perspective = crate::perspective_matrix(height as f32 / width as f32, FOV, (distance - radius).max(distance * 1e-3) * 0.5,
+                                                    distance + radius * 2.0);
+        // Fixed in the world so the lit side stays on the model, at the first angle it matches the viewer's light
+        let light = math::transform_vector(&view, LIGHT);
+
+        let params = glium::DrawParameters {
+            depth: glium::Depth {
+                test: glium::draw_parameters::DepthTest::IfLess,
+                write: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let draw = |target: &mut SimpleFrameBuffer, model: &Mat4, primitive: &GpuPrimitive, params: &glium::DrawParameters, pass: i32| {
+            let uniforms = uniform! {
+                model: *model,
+                tex: primitive.texture.map_or(&self.white_texture, |texture| &textures[texture]),
+                u_base_color: primitive.base_color,
+                u_emissive: primitive.emissive,
+                u_occlusion: &self.no_occlusion,
+                u_inverse_viewport: [1.0 / width as f32, 1.0 / height as f32],
+                u_light: light,
+                u_alpha_cutoff: transparency::alpha_cutoff(primitive.alpha_mode),
+                u_transparency_pass: pass,
+                perspective: perspective,
+                view: view
+            };
+            target.draw(&primitive.vertex_buffer, &primitive.indices, &self.program, &uniforms, params).unwrap();
+        };
+
+        let mut target = self.scene_target.framebuffer(facade);
+        target.clear_color_and_depth(BACKGROUND, 1.0);
+        // Opaque first, then blended primitives back to front like the viewer's sorted transparency
+        let mut transparent = Vec::new();
+        for (mesh, model) in scene.mesh_instances() {
+            let front_clockwise = math::mat3_determinant(&math::mat4_mul(&view, &model)) > 0.0;
+            for primitive in &meshes[mesh] {
+                let params = glium::DrawParameters {
+                    backface_culling: render_mode::backface_culling(primitive.culling, front_clockwise),
+                    ..params.clone()
+                };
+                if primitive.alpha_mode == AlphaMode::Blend {
+                    let depth = math::transform_point(&math::mat4_mul(&view, &model), primitive.center)[2];
+                    transparent.push((depth, model, transparency::sorted_parameters(&params), primitive));
+                } else {
+                    draw(&mut target, &model, primitive, &params, 0);
+                }
+            }
+        }
+        transparent.sort_by(|a, b| b.0.total_cmp(&a.0));
+        for (_, model, params, primitive) in &transparent {
+            draw(&mut target, model, primitive, params, 1);
+        }
+        drop(target);
+
+        self.scene_target.resolve(facade);
+        // No auto exposure, every angle has to come out equally bright
+        let color = self.tone_mapper.run(facade, &self.scene_target.color, 0.0);
+        let output = self.capture_target.texture(facade, self.size);
+        self.presenter.present(&mut output.as_surface(), color, AntiAliasing::Off);
+        capture::read_texture(output)
+    }
+}
+
+// All images in a grid as close to square as it gets, left to right and top to bottom
+fn contact_sheet(images: &[image::RgbaImage]) -> image::RgbaImage {
+    let Some(first) = images.first() else {
+        return image::RgbaImage::new(1, 1);
+    };
+    let (width, height) = first.dimensions();
+    let columns = (images.len() as f32).sqrt().ceil() as u32;
+    let rows = (images.len() as u32).div_ceil(columns);
+    let mut sheet = image::RgbaImage::new(columns * width, rows * height);
+    for (i, image) in images.iter().enumerate() {
+        let (column, row) = (i as u32 % columns, i as u32 / columns);
+        image::imageops::replace(&mut sheet, image, (column * width) as i64, (row * height) as i64);
+    }
+    sheet
+}
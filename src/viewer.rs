@@ -0,0 +1,726 @@
+use std::path::Path;
+use std::time::Instant;
+
+use glium::glutin::surface::WindowSurface;
+use glium::texture::SrgbTexture2d;
+use glium::winit::event::{ElementState, Event, WindowEvent};
+use glium::winit::keyboard::{KeyCode, PhysicalKey};
+use glium::{Display, Surface};
+
+use crate::antialiasing::{self, AntiAliasing, Presenter, SceneTarget};
+use crate::bloom::{self, Bloom};
+use crate::capture::{self, Capture};
+use crate::debug_draw::DebugDraw;
+use crate::deferred::{self, DeferredRenderer, GBufferView, PointLight, Renderer};
+use crate::ground_grid::GroundGrid;
+use crate::hud::Hud;
+use crate::math::{self, Mat4};
+use crate::mesh::MeshError;
+use crate::post_processing::{self, PostProcessing};
+use crate::render_mode::{self, RenderSettings, ShadingMode, VectorDisplay};
+use crate::scene::{AlphaMode, FaceCulling, Scene};
+use crate::ssao::{self, Ssao};
+use crate::stats::{self, FrameStats};
+use crate::text::{self, HorizontalAlign, TextRenderer, TextStyle, VerticalAlign};
+use crate::tone_mapping::{self, ToneMapper};
+use crate::transparency::{self, OitPass, Transparency, WeightedBlended};
+use crate::{GpuPrimitive, ViewOptions};
+
+pub const KEY_HELP: &str = "A to cycle anti-aliasing, I to switch between sorted and order independent transparency, \
+                            B to show bounds, light, axes and camera frusta, G to toggle the ground grid, \
+                            H to toggle the performance HUD, L to toggle mesh labels";
+
+// The view space direction the scene is lit from, the turntable renders use it too
+pub const LIGHT: [f32; 3] = [-1.0, 0.4, 0.9];
+
+// Flat colored edges of the solid + wireframe overlay
+const WIREFRAME_COLOR: [f32; 4] = [0.05, 0.05, 0.05, 1.0];
+
+// Opens a window showing `scene` until it is closed. Scenes without their own camera are scaled and moved to fit the
+// fixed view, whatever units they are modelled in.
+pub fn view_scene(scene: Scene, view_options: &ViewOptions) {
+    let event_loop = glium::winit::event_loop::EventLoop::builder().build().unwrap();
+    let (window, display) = glium::backend::glutin::SimpleWindowBuilder::new().build(&event_loop);
+
+    let mut viewer = match Viewer::new(&display, scene, view_options) {
+        Ok(viewer) => viewer,
+        Err((name, err)) => {
+            eprintln!("Could not upload mesh {}: {}", name, err);
+            return;
+        }
+    };
+
+    #[allow(deprecated)]
+    let _ = event_loop.run(move |event, window_target| {
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::CloseRequested => {
+                    viewer.close();
+                    window_target.exit();
+                }
+                WindowEvent::Resized(window_size) => display.resize(window_size.into()),
+                WindowEvent::KeyboardInput { event, .. } if event.state == ElementState::Pressed && !event.repeat => {
+                    if let PhysicalKey::Code(key) = event.physical_key {
+                        viewer.handle_key(key);
+                    }
+                }
+                WindowEvent::RedrawRequested => viewer.draw(&display),
+                _ => (),
+            },
+            Event::AboutToWait => window.request_redraw(),
+            _ => (),
+        };
+    });
+}
+
+// Everything the passes of one frame share: the camera, where the scene ended up in front of it, and the sizes drawn at
+struct FrameView {
+    view: Mat4,
+    perspective: Mat4,
+    // Scene to world, fits scenes without a camera into the fixed view
+    base_model: Mat4,
+    // What the frame is drawn for, the window or the capture size, and what the scene target could be made at
+    size: (u32, u32),
+    target_size: (u32, u32),
+    // Ranges the debug shading modes spread their colors over, and a vector line length to suit the scene's size
+    bounds_min: [f32; 3],
+    bounds_size: [f32; 3],
+    depth_range: [f32; 2],
+    vector_length: f32,
+}
+
+// Alpha blended primitives of the lit view with their view space depth, held back and drawn after everything opaque
+type Transparent<'a> = (f32, Mat4, glium::DrawParameters<'a>, &'a GpuPrimitive);
+
+// Add depth testing here
+fn depth_parameters() -> glium::DrawParameters<'static> {
+    glium::DrawParameters {
+        depth: glium::Depth {
+            test: glium::draw_parameters::DepthTest::IfLess,
+            write: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    }
+}
+
+// The scene on the GPU and the programs that draw it into the scene target
+struct ScenePasses {
+    scene: Scene,
+    meshes: Vec<Vec<GpuPrimitive>>,
+    textures: Vec<SrgbTexture2d>,
+    // Untextured materials sample a single white texel so the shader does not need a separate path for them
+    white_texture: SrgbTexture2d,
+    bounds: ([f32; 3], [f32; 3]),
+    // Scales and moves the scene into a bounding sphere of radius 1 around the point the fixed view looks at
+    fixed_view_model: Mat4,
+    program: glium::Program,
+    wireframe_program: glium::Program,
+    // Debug shading modes and the normal/tangent lines
+    debug_shading_program: glium::Program,
+    vectors_program: glium::Program,
+    // Front/back face diagnostic, uses the Gouraud vertex shader
+    facing_program: glium::Program,
+}
+
+impl ScenePasses {
+    fn new<F: glium::backend::Facade + ?Sized>(facade: &F, scene: Scene) -> Result<Self, (String, MeshError)> {
+        let meshes = crate::upload_meshes(facade, &scene)?;
+        let textures = crate::upload_textures(facade, &scene);
+        let white_texture = SrgbTexture2d::new(facade, vec![vec![(1.0f32, 1.0, 1.0, 1.0)]]).unwrap();
+
+        let bounds = scene.bounds().unwrap_or(([0.0; 3], [0.0; 3]));
+        let model_scale = 1.0 / (0.5 * math::length(math::sub(bounds.1, bounds.0))).max(1e-6);
+        let model_center = math::scale(math::add(bounds.0, bounds.1), 0.5);
+        // Remember that in CG most matrices are in column-major order
+        // So matrix is actually (s being model_scale and c the scene's center)
+        // s   0.0 0.0 -s*cx
+        // 0.0 s   0.0 -s*cy
+        // 0.0 0.0 s   2.0 - s*cz
+        // 0.0 0.0 0.0 1.0
+        // in row major order
+
+        // In column major order, order of transformations is inverse that of multiplication
+        // So for transform: scale, rotate then translate, the order of multiplication is translate * rotate * scale * vector
+        // In row major order, the order of multiplication is scale * rotate * translate * vector
+        let fixed_view_model = [
+            [model_scale, 0.0, 0.0, 0.0],
+            [0.0, model_scale, 0.0, 0.0],
+            [0.0, 0.0, model_scale, 0.0],
+            [-model_scale * model_center[0], -model_scale * model_center[1], 2.0 - model_scale * model_center[2], 1.0f32]
+        ];
+
+        // Default shaders
+        // let vertex_shader_src = read_shader("shaders/teapot.vert");
+        // let fragment_shader_src = read_shader("shaders/teapot.frag");
+
+        // Gouraud shading shaders
+        let vertex_shader_src = crate::read_shader("shaders/teapot_gouraud.vert");
+        let fragment_shader_src = crate::read_shader("shaders/teapot_gouraud.frag");
+        let program = |vertex_shader: &str, fragment_shader: &str, geometry_shader: Option<&str>| {
+            glium::Program::from_source(facade, vertex_shader, fragment_shader, geometry_shader).unwrap()
+        };
+
+        Ok(ScenePasses {
+            program: program(&vertex_shader_src, &fragment_shader_src, None),
+            wireframe_program: program(&crate::read_shader("shaders/wireframe.vert"), &crate::read_shader("shaders/wireframe.frag"), None),
+            debug_shading_program: program(&crate::read_shader("shaders/debug_shading.vert"), &crate::read_shader("shaders/debug_shading.frag"), None),
+            vectors_program: program(&crate::read_shader("shaders/vectors.vert"), &crate::read_shader("shaders/vectors.frag"),
+                                     Some(&crate::read_shader("shaders/vectors.geom"))),
+            facing_program: program(&vertex_shader_src, &crate::read_shader("shaders/facing.frag"), None),
+            scene,
+            meshes,
+            textures,
+            white_texture,
+            bounds,
+            fixed_view_model,
+        })
+    }
+
+    // Models that bring their own camera are shown through it in their own units. `orbit_angle` turns the camera around
+    // the scene's center.
+    fn frame_view(&self, size: (u32, u32), target_size: (u32, u32), orbit_angle: Option<f32>) -> FrameView {
+        let (view, perspective, base_model) = match self.scene.first_camera() {
+            Some((camera, camera_world)) => {
+                let (view, perspective) = crate::scene_camera_matrices(camera, &camera_world, size);
+                (view, perspective, math::IDENTITY)
+            }
+            None => {
+                let view = crate::view_matrix(&[2.0, -1.0, 1.0], &[-2.0, 1.0, 1.0], &[0.0, 1.0, 0.0]);
+                let perspective = crate::perspective_matrix(size.1 as f32 / size.0 as f32, std::f32::consts::PI / 3.0, 0.1, 1024.0);
+                (view, perspective, self.fixed_view_model)
+            }
+        };
+        let view = match orbit_angle {
+            Some(angle) => {
+                let center = math::transform_point(&base_model, math::scale(math::add(self.bounds.0, self.bounds.1), 0.5));
+                crate::orbit_view(&view, center, angle)
+            }
+            None => view,
+        };
+
+        let (bounds_min, bounds_max) = math::transform_bounds(&base_model, self.bounds.0, self.bounds.1);
+        let (view_min, view_max) = math::transform_bounds(&math::mat4_mul(&view, &base_model), self.bounds.0, self.bounds.1);
+        let bounds_size = math::sub(bounds_max, bounds_min);
+        FrameView {
+            view,
+            perspective,
+            base_model,
+            size,
+            target_size,
+            bounds_min,
+            bounds_size,
+            depth_range: [view_min[2].max(0.0), view_max[2]],
+            vector_length: 0.03 * math::length(bounds_size),
+        }
+    }
+
+    // Every mesh instance with its model matrix and whether its front faces end up clockwise on screen
+    fn instances<'a>(&'a self, frame: &FrameView) -> impl Iterator<Item = (Mat4, bool, &'a [GpuPrimitive])> + 'a {
+        let (base_model, view) = (frame.base_model, frame.view);
+        self.scene.mesh_instances().into_iter().map(move |(mesh, world)| {
+            let model = math::mat4_mul(&base_model, &world);
+            // perspective_matrix looks down +z, so an unmirrored view * model shows counter-clockwise faces clockwise
+            let front_clockwise = math::mat3_determinant(&math::mat4_mul(&view, &model)) > 0.0;
+            (model, front_clockwise, self.meshes[mesh].as_slice())
+        })
+    }
+
+    fn texture(&self, primitive: &GpuPrimitive) -> &SrgbTexture2d {
+        primitive.texture.map_or(&self.white_texture, |texture| &self.textures[texture])
+    }
+
+    // Depth and normals for ambient occlusion, drawn with the same culling as the scene itself
+    fn draw_occlusion_prepass<S: Surface>(&self, target: &mut S, program: &glium::Program, frame: &FrameView, settings: &RenderSettings) {
+        for (model, front_clockwise, primitives) in self.instances(frame) {
+            // Blended surfaces neither occlude nor land in the G-buffer, they are drawn after the opaque scene
+            for primitive in primitives.iter().filter(|primitive| primitive.alpha_mode != AlphaMode::Blend) {
+                let params = glium::DrawParameters {
+                    backface_culling: render_mode::backface_culling(settings.culling_override.unwrap_or(primitive.culling), front_clockwise),
+                    ..depth_parameters()
+                };
+                let uniforms = uniform! { model: model, perspective: frame.perspective, view: frame.view, u_front_clockwise: front_clockwise };
+                target.draw(&primitive.vertex_buffer, &primitive.indices, program, &uniforms, &params).unwrap();
+                stats::count_draw(primitive.triangles);
+            }
+        }
+    }
+
+    // The opaque surfaces' materials for the deferred renderer to light
+    fn draw_gbuffer<S: Surface>(&self, target: &mut S, program: &glium::Program, frame: &FrameView, settings: &RenderSettings) {
+        for (model, front_clockwise, primitives) in self.instances(frame) {
+            for primitive in primitives.iter().filter(|primitive| primitive.alpha_mode != AlphaMode::Blend) {
+                let params = glium::DrawParameters {
+                    backface_culling: render_mode::backface_culling(settings.culling_override.unwrap_or(primitive.culling), front_clockwise),
+                    ..depth_parameters()
+                };
+                let uniforms = uniform! {
+                    model: model,
+                    tex: self.texture(primitive),
+                    u_base_color: primitive.base_color,
+                    u_emissive: primitive.emissive,
+                    u_metallic: primitive.metallic,
+                    u_roughness: primitive.roughness,
+                    u_alpha_cutoff: transparency::alpha_cutoff(primitive.alpha_mode),
+                    perspective: frame.perspective,
+                    view: frame.view
+                };
+                target.draw(&primitive.vertex_buffer, &primitive.indices, program, &uniforms, &settings.mode.shaded_parameters(&params)).unwrap();
+                stats::count_draw(primitive.triangles);
+            }
+        }
+    }
+
+    // The lit or diagnostic scene and its overlays. After a deferred frame only the overlays are left to draw.
+    // Returns the blended primitives, which are left for draw_transparent().
+    fn draw_forward<S: Surface>(&self, target: &mut S, frame: &FrameView, occlusion: &glium::texture::Texture2d, settings: &RenderSettings,
+                                deferred_frame: bool) -> Vec<Transparent<'_>> {
+        let mut transparent = Vec::new();
+        for (model, front_clockwise, primitives) in self.instances(frame) {
+            for primitive in primitives {
+                // The face diagnostic has to see back faces to color them
+                let culling = if settings.show_facing { FaceCulling::None } else { settings.culling_override.unwrap_or(primitive.culling) };
+                let params = glium::DrawParameters {
+                    backface_culling: render_mode::backface_culling(culling, front_clockwise),
+                    ..depth_parameters()
+                };
+                let shaded_params = settings.mode.shaded_parameters(&params);
+
+                if settings.show_facing {
+                    let uniforms = uniform! { model: model, perspective: frame.perspective, view: frame.view, u_light: LIGHT,
+                                              u_front_clockwise: front_clockwise };
+                    target.draw(&primitive.vertex_buffer, &primitive.indices, &self.facing_program, &uniforms, &shaded_params).unwrap();
+                    stats::count_draw(primitive.triangles);
+                } else if settings.shading != ShadingMode::Lit {
+                    let uniforms = uniform! { model: model, perspective: frame.perspective, view: frame.view, u_mode: settings.shading.shader_mode(),
+                                              u_depth_range: frame.depth_range, u_bounds_min: frame.bounds_min, u_bounds_size: frame.bounds_size };
+                    target.draw(&primitive.vertex_buffer, &primitive.indices, &self.debug_shading_program, &uniforms, &shaded_params).unwrap();
+                    stats::count_draw(primitive.triangles);
+                } else if primitive.alpha_mode == AlphaMode::Blend {
+                    let depth = math::transform_point(&math::mat4_mul(&frame.view, &model), primitive.center)[2];
+                    transparent.push((depth, model, shaded_params.clone(), primitive));
+                } else if !deferred_frame {
+                    let uniforms = uniform! {
+                        model: model,
+                        tex: self.texture(primitive),
+                        u_base_color: primitive.base_color,
+                        u_emissive: primitive.emissive,
+                        u_occlusion: occlusion.sampled().wrap_function(glium::uniforms::SamplerWrapFunction::Clamp),
+                        u_inverse_viewport: [1.0 / frame.target_size.0 as f32, 1.0 / frame.target_size.1 as f32],
+                        u_light: LIGHT,
+                        u_alpha_cutoff: transparency::alpha_cutoff(primitive.alpha_mode),
+                        u_transparency_pass: 0,
+                        perspective : frame.perspective,
+                        view: frame.view
+                    };
+                    target.draw(&primitive.vertex_buffer, &primitive.indices, &self.program, &uniforms, &shaded_params).unwrap();
+                    stats::count_draw(primitive.triangles);
+                }
+
+                if settings.vectors != VectorDisplay::Off {
+                    let uniforms = uniform! { model: model, perspective: frame.perspective, view: frame.view, u_length: frame.vector_length,
+                                              u_show_tangents: settings.vectors == VectorDisplay::TangentFrames };
+                    target.draw(&primitive.vertex_buffer, glium::index::NoIndices(glium::index::PrimitiveType::Points),
+                                &self.vectors_program, &uniforms, &params).unwrap();
+                    stats::count_draw(0);
+                }
+
+                if let Some(overlay_params) = &settings.mode.overlay_parameters(&params) {
+                    let uniforms = uniform! { model: model, perspective: frame.perspective, view: frame.view, u_color: WIREFRAME_COLOR };
+                    target.draw(&primitive.vertex_buffer, &primitive.indices, &self.wireframe_program, &uniforms, overlay_params).unwrap();
+                    stats::count_draw(primitive.triangles);
+                }
+            }
+        }
+        // Back to front, the farthest first
+        transparent.sort_by(|a, b| b.0.total_cmp(&a.0));
+        transparent
+    }
+
+    // Blends `transparent` over the scene, sorted when `pass` is None or into one of the order independent transparency targets
+    fn draw_transparent<S: Surface>(&self, surface: &mut S, transparent: &[Transparent], pass: Option<OitPass>, frame: &FrameView,
+                                    occlusion: &glium::texture::Texture2d) {
+        for (_, model, params, primitive) in transparent {
+            let (params, shader_pass) = match pass {
+                Some(pass) => (pass.parameters(params), pass.shader_pass()),
+                None => (transparency::sorted_parameters(params), 1),
+            };
+            let uniforms = uniform! {
+                model: *model,
+                tex: self.texture(primitive),
+                u_base_color: primitive.base_color,
+                u_emissive: primitive.emissive,
+                u_occlusion: occlusion.sampled().wrap_function(glium::uniforms::SamplerWrapFunction::Clamp),
+                u_inverse_viewport: [1.0 / frame.target_size.0 as f32, 1.0 / frame.target_size.1 as f32],
+                u_light: LIGHT,
+                u_alpha_cutoff: 0.0f32,
+                u_transparency_pass: shader_pass,
+                perspective: frame.perspective,
+                view: frame.view
+            };
+            surface.draw(&primitive.vertex_buffer, &primitive.indices, &self.program, &uniforms, &params).unwrap();
+            stats::count_draw(primitive.triangles);
+        }
+    }
+}
+
+// The ground grid, B's bounds, light, axes and camera frusta, and the axes gizmo in the corner
+struct Overlays {
+    debug_draw: DebugDraw,
+    ground_grid: GroundGrid,
+    show_grid: bool,
+    show_debug_shapes: bool,
+}
+
+impl Overlays {
+    fn draw<F: glium::backend::Facade + ?Sized, S: Surface>(&mut self, facade: &F, target: &mut S, scene: &Scene, bounds: ([f32; 3], [f32; 3]),
+                                                            frame: &FrameView) {
+        if self.show_grid {
+            self.ground_grid.draw(target, &frame.perspective, &frame.view, &frame.base_model, bounds);
+        }
+
+        let debug_draw = &mut self.debug_draw;
+        if self.show_debug_shapes {
+            let (min, max) = bounds;
+            let center = math::scale(math::add(min, max), 0.5);
+            let radius = 0.5 * math::length(math::sub(max, min));
+            debug_draw.depth_test = true;
+            debug_draw.aabb(min, max, [1.0, 0.9, 0.2, 1.0]);
+            debug_draw.sphere(center, radius, [0.6, 0.6, 0.6, 1.0]);
+            debug_draw.grid([center[0], min[1], center[2]], 3.0 * radius, 12, [0.4, 0.4, 0.4, 1.0]);
+            // u_light is a view space direction, bring it back into the scene to show where the light comes from
+            if let Some(inverse) = math::mat4_inverse(&math::mat4_mul(&frame.view, &frame.base_model)) {
+                let light_direction = math::normalize(math::transform_vector(&inverse, LIGHT));
+                debug_draw.arrow(math::add(center, math::scale(light_direction, 1.5 * radius)), center, [1.0, 1.0, 0.6, 1.0]);
+            }
+            let world = scene.world_transforms();
+            for (node, world) in scene.nodes.iter().zip(&world) {
+                if let (Some(camera), Some(world)) = (node.camera, world) {
+                    let (camera_view, camera_projection) = crate::scene_camera_matrices(&scene.cameras[camera], world, frame.size);
+                    if let Some(inverse) = math::mat4_inverse(&math::mat4_mul(&camera_projection, &camera_view)) {
+                        debug_draw.frustum(&inverse, [0.9, 0.5, 1.0, 1.0]);
+                    }
+                }
+            }
+            debug_draw.depth_test = false;
+            debug_draw.axis_triad(&math::IDENTITY, 0.5 * radius);
+            debug_draw.draw(facade, target, &frame.perspective, &frame.view, &frame.base_model, None);
+        }
+
+        // Corner gizmo: the scene axes turned like the current view, in a small orthographic viewport.
+        // Only the rotation of view * model is kept, moved in front of the gizmo's camera.
+        let mut gizmo_view = math::mat4_mul(&frame.view, &frame.base_model);
+        for column in gizmo_view.iter_mut().take(3) {
+            let axis = math::normalize([column[0], column[1], column[2]]);
+            column[..3].copy_from_slice(&axis);
+        }
+        gizmo_view[3] = [0.0, 0.0, 3.0, 1.0];
+        debug_draw.depth_test = false;
+        debug_draw.axis_triad(&math::IDENTITY, 1.0);
+        let gizmo_viewport = glium::Rect { left: 10, bottom: 10, width: 90, height: 90 };
+        debug_draw.draw(facade, target, &crate::orthographic_matrix(1.2, 1.2, 0.1, 10.0), &gizmo_view, &math::IDENTITY, Some(gizmo_viewport));
+    }
+}
+
+// L labels every mesh in the scene and shows the scene's size. Without a usable font the labels are left out.
+struct Labels {
+    text_renderer: Option<TextRenderer>,
+    visible: bool,
+}
+
+impl Labels {
+    fn new<F: glium::backend::Facade + ?Sized>(facade: &F, font: Option<&str>) -> Self {
+        let font_paths: Vec<&str> = font.map_or_else(|| text::DEFAULT_FONTS.to_vec(), |path| vec![path]);
+        let text_renderer = font_paths.iter().find_map(|path| match TextRenderer::new(facade, Path::new(path)) {
+            Ok(text_renderer) => Some(text_renderer),
+            Err(err) => {
+                if font.is_some() {
+                    eprintln!("Could not load {}: {}", path, err);
+                }
+                None
+            }
+        });
+        if text_renderer.is_none() {
+            println!("No font found, labels are off. Pass --font <file.ttf> to use one.");
+        }
+        Labels { text_renderer, visible: false }
+    }
+
+    // Mesh names at their middle, sized to the scene so they keep their place in it when zooming, and the size of the scene's
+    // bounds in the bottom right corner of a target `size` pixels large. `pixel_scale` grows the screen text with a capture
+    // that is larger than the window.
+    fn draw<F: glium::backend::Facade + ?Sized, S: Surface>(&mut self, facade: &F, target: &mut S, passes: &ScenePasses, frame: &FrameView,
+                                                            size: (u32, u32), pixel_scale: f32) {
+        let Some(text_renderer) = self.text_renderer.as_mut().filter(|_| self.visible) else {
+            return;
+        };
+        let (scene, (min, max)) = (&passes.scene, passes.bounds);
+        let label_size = math::length(math::transform_vector(&frame.base_model, math::sub(max, min))) * 0.04;
+        let label_style = TextStyle { size: label_size, outline_color: [0.0, 0.0, 0.0, 0.8], horizontal_align: HorizontalAlign::Center,
+                                      vertical_align: VerticalAlign::Middle, ..Default::default() };
+        for (mesh, world) in scene.mesh_instances() {
+            if let Some(primitive) = passes.meshes[mesh].first().filter(|_| !scene.meshes[mesh].name.is_empty()) {
+                text_renderer.world(math::transform_point(&world, primitive.center), &scene.meshes[mesh].name, &label_style);
+            }
+        }
+        let extent = math::sub(max, min);
+        let margin = 10.0 * pixel_scale;
+        let readout_style = TextStyle { size: 18.0 * pixel_scale, color: [1.0, 0.9, 0.4, 1.0], outline_color: [0.0, 0.0, 0.0, 0.8],
+                                        horizontal_align: HorizontalAlign::Right, vertical_align: VerticalAlign::Bottom };
+        text_renderer.screen([size.0 as f32 - margin, size.1 as f32 - margin],
+                             &format!("{:.3} × {:.3} × {:.3}", extent[0], extent[1], extent[2]), &readout_style);
+        text_renderer.draw(facade, target, &frame.perspective, &frame.view, &frame.base_model);
+    }
+}
+
+// The interactive viewer. The scene is drawn offscreen in linear HDR, multisampled for MSAA, run through bloom and the
+// post processing passes, tone mapped to sRGB and then presented through a copy or FXAA pass.
+struct Viewer {
+    passes: ScenePasses,
+    settings: RenderSettings,
+    overlays: Overlays,
+    labels: Labels,
+    // H shows frame times and workload
+    hud: Hud,
+    frame_stats: FrameStats,
+    renderer: Renderer,
+    ssao: Ssao,
+    deferred: DeferredRenderer,
+    point_light_count: usize,
+    transparency: Transparency,
+    weighted_blended: WeightedBlended,
+    bloom: Bloom,
+    post_processing: PostProcessing,
+    tone_mapper: ToneMapper,
+    anti_aliasing: AntiAliasing,
+    // Remade when the window size or the sample count changes
+    scene_target: Option<SceneTarget>,
+    presenter: Presenter,
+    capture: Capture,
+    // Seconds per turn of the camera around the scene
+    orbit: Option<f32>,
+    last_frame: Instant,
+    // Seconds the scene has been running, stepped by a recording's frame rate rather than the real time while recording
+    scene_time: f32,
+}
+
+impl Viewer {
+    fn new(display: &Display<WindowSurface>, scene: Scene, view_options: &ViewOptions) -> Result<Self, (String, MeshError)> {
+        let passes = ScenePasses::new(display, scene)?;
+
+        let mut frame_stats = FrameStats::default();
+        frame_stats.buffer_bytes = passes.meshes.iter().flatten().map(|primitive| primitive.vertex_buffer.get_size() + primitive.indices.get_size()).sum();
+        frame_stats.texture_bytes = passes.scene.textures.iter().map(|texture| texture.rgba.len()).sum();
+        if let Some(path) = &view_options.stats_csv {
+            match frame_stats.log_to_csv(Path::new(path)) {
+                Ok(()) => println!("Logging frame statistics to {}", path),
+                Err(err) => eprintln!("Could not create {}: {}", path, err),
+            }
+        }
+
+        let mut post_processing = PostProcessing::new(display);
+        for path in &view_options.post_passes {
+            post_processing.add_pass(display, path);
+        }
+
+        let viewer = Viewer {
+            passes,
+            settings: RenderSettings::default(),
+            overlays: Overlays { debug_draw: DebugDraw::new(display), ground_grid: GroundGrid::new(display), show_grid: true, show_debug_shapes: false },
+            labels: Labels::new(display, view_options.font.as_deref()),
+            hud: Hud::new(display, view_options.hud),
+            frame_stats,
+            renderer: view_options.renderer,
+            ssao: Ssao::new(display, view_options.ssao),
+            deferred: DeferredRenderer::new(display),
+            point_light_count: view_options.point_lights,
+            transparency: view_options.transparency,
+            weighted_blended: WeightedBlended::new(display),
+            bloom: Bloom::new(display, view_options.bloom),
+            post_processing,
+            tone_mapper: ToneMapper::new(display, view_options.tone_mapping, view_options.exposure, view_options.auto_exposure),
+            anti_aliasing: view_options.anti_aliasing,
+            scene_target: None,
+            presenter: Presenter::new(display),
+            capture: Capture::new(view_options.capture_scale, view_options.frame_rate, view_options.record.as_deref()),
+            orbit: view_options.orbit,
+            last_frame: Instant::now(),
+            scene_time: 0.0,
+        };
+        viewer.print_settings();
+        Ok(viewer)
+    }
+
+    fn print_settings(&self) {
+        println!("Renderer: {:?}", self.renderer);
+        if self.renderer == Renderer::Forward && self.point_light_count > 0 {
+            println!("Point lights are only drawn by the deferred renderer, pass --renderer deferred");
+        }
+        if !self.post_processing.passes.is_empty() {
+            let names: Vec<&str> = self.post_processing.passes.iter().map(|pass| pass.name.as_str()).collect();
+            println!("Post processing: {}", names.join(", "));
+        }
+        println!("Anti-aliasing: {}", self.anti_aliasing);
+        println!("Tone mapping: {}, exposure {:+.1} EV{}", self.tone_mapper.operator, self.tone_mapper.exposure,
+                 if self.tone_mapper.auto_exposure { " over auto exposure" } else { "" });
+        println!("Ambient occlusion: {}", self.ssao.quality);
+        println!("Transparency: {}", self.transparency);
+        println!("Keys:");
+        for key_help in [render_mode::KEY_HELP, KEY_HELP, ssao::KEY_HELP, deferred::KEY_HELP, bloom::KEY_HELP, post_processing::KEY_HELP,
+                         tone_mapping::KEY_HELP, capture::KEY_HELP] {
+            println!("  {}", key_help);
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyCode) {
+        self.settings.handle_key(key);
+        self.ssao.handle_key(key);
+        if self.renderer == Renderer::Deferred {
+            self.deferred.handle_key(key);
+        }
+        self.bloom.handle_key(key);
+        self.post_processing.handle_key(key);
+        self.tone_mapper.handle_key(key);
+        self.capture.handle_key(key);
+        match key {
+            KeyCode::KeyA => {
+                self.anti_aliasing = self.anti_aliasing.next();
+                println!("Anti-aliasing: {}", self.anti_aliasing);
+            }
+            KeyCode::KeyI => {
+                self.transparency = self.transparency.next();
+                println!("Transparency: {}", self.transparency);
+            }
+            KeyCode::KeyB => {
+                self.overlays.show_debug_shapes = !self.overlays.show_debug_shapes;
+                println!("Debug shapes: {}", if self.overlays.show_debug_shapes { "on" } else { "off" });
+            }
+            KeyCode::KeyG => {
+                self.overlays.show_grid = !self.overlays.show_grid;
+                println!("Ground grid: {}", if self.overlays.show_grid { "on" } else { "off" });
+            }
+            KeyCode::KeyH => self.hud.visible = !self.hud.visible,
+            KeyCode::KeyL => {
+                self.labels.visible = !self.labels.visible;
+                println!("Labels: {}", if self.labels.visible { "on" } else { "off" });
+            }
+            _ => (),
+        }
+    }
+
+    fn close(&mut self) {
+        self.frame_stats.flush();
+        println!("Frame statistics over the last {} frames:", self.frame_stats.frame_time.samples().count());
+        for line in self.frame_stats.summary() {
+            println!("  {}", line);
+        }
+    }
+
+    // Falls back to FXAA where the sample count cannot be used, then remakes the scene target if it does not fit `size` any more
+    fn prepare_scene_target<F: glium::backend::Facade + ?Sized>(&mut self, facade: &F, size: (u32, u32)) {
+        if self.anti_aliasing.samples() > 1 && !antialiasing::msaa_supported(facade, self.anti_aliasing.samples()) {
+            println!("{} is not supported, falling back to FXAA", self.anti_aliasing);
+            self.anti_aliasing = AntiAliasing::Fxaa;
+        }
+        // The G-buffer shares the scene depth, which has to be single sampled for that
+        if self.anti_aliasing.samples() > 1 && self.renderer == Renderer::Deferred {
+            println!("{} does not work with deferred shading, using FXAA", self.anti_aliasing);
+            self.anti_aliasing = AntiAliasing::Fxaa;
+        }
+        if self.scene_target.as_ref().is_none_or(|scene_target| scene_target.requested_size != size || scene_target.samples != self.anti_aliasing.samples()) {
+            self.scene_target = match SceneTarget::new(facade, size, self.anti_aliasing.samples()) {
+                Ok(scene_target) => Some(scene_target),
+                Err(err) => {
+                    println!("Could not create {} render target ({:?}), falling back to FXAA", self.anti_aliasing, err);
+                    self.anti_aliasing = AntiAliasing::Fxaa;
+                    Some(SceneTarget::new(facade, size, 1).unwrap())
+                }
+            };
+        }
+    }
+
+    fn draw(&mut self, display: &Display<WindowSurface>) {
+        self.frame_stats.begin_frame(display);
+        let mut frame = display.draw();
+        let window_size = frame.get_dimensions();
+        // A recording steps time by exactly one of its frames, so the sequence plays back at the right speed
+        let now = Instant::now();
+        let elapsed = self.capture.time_step((now - self.last_frame).as_secs_f32());
+        self.last_frame = now;
+        self.scene_time += elapsed;
+        let capturing = self.capture.active();
+        let capture_scale = self.capture.scale;
+        let size = if capturing { (window_size.0 * capture_scale, window_size.1 * capture_scale) } else { window_size };
+
+        self.prepare_scene_target(display, size);
+        let scene_target = self.scene_target.as_ref().unwrap();
+        // May be smaller than asked for, projections keep the window's aspect since the target is stretched over it
+        let target_size = scene_target.size();
+        let orbit_angle = self.orbit.map(|seconds| self.scene_time / seconds * std::f32::consts::TAU);
+        let frame_view = self.passes.frame_view(size, target_size, orbit_angle);
+        let mut target = scene_target.framebuffer(display);
+        target.clear_color_and_depth((0.0, 0.0, 1.0, 1.0), 1.0);
+
+        self.ssao.prepare(display, target_size);
+        if let Some(mut prepass) = self.ssao.prepass(display) {
+            self.passes.draw_occlusion_prepass(&mut prepass, &self.ssao.prepass_program, &frame_view, &self.settings);
+        }
+        let occlusion = self.ssao.occlusion(&frame_view.perspective, 0.04 * math::length(frame_view.bounds_size));
+
+        // The deferred path fills the G-buffer and lights it into the scene color here, the forward pass then
+        // skips the lit draw and only adds overlays. Diagnostic shading stays forward.
+        let deferred_frame = self.renderer == Renderer::Deferred && !self.settings.show_facing && self.settings.shading == ShadingMode::Lit;
+        if deferred_frame {
+            self.deferred.prepare(display, target_size);
+            let mut gbuffer = self.deferred.geometry_framebuffer(display, &scene_target.depth);
+            self.passes.draw_gbuffer(&mut gbuffer, &self.deferred.geometry_program, &frame_view, &self.settings);
+            drop(gbuffer);
+
+            let mut color = glium::framebuffer::SimpleFrameBuffer::new(display, &scene_target.color).unwrap();
+            if self.deferred.view == GBufferView::Off {
+                let bounds_max = math::add(frame_view.bounds_min, frame_view.bounds_size);
+                let point_lights: Vec<PointLight> = deferred::light_ring(self.point_light_count, frame_view.bounds_min, bounds_max).into_iter()
+                    .map(|point_light| PointLight { position: math::transform_point(&frame_view.view, point_light.position), ..point_light })
+                    .collect();
+                self.deferred.light(&mut color, &scene_target.depth, occlusion, &frame_view.perspective, LIGHT, &point_lights);
+            } else {
+                self.deferred.show(&mut color, &scene_target.depth, &frame_view.perspective, frame_view.depth_range);
+            }
+        }
+
+        let transparent = self.passes.draw_forward(&mut target, &frame_view, occlusion, &self.settings, deferred_frame);
+        if self.transparency == Transparency::Sorted {
+            self.passes.draw_transparent(&mut target, &transparent, None, &frame_view, occlusion);
+        }
+        self.overlays.draw(display, &mut target, &self.passes.scene, self.passes.bounds, &frame_view);
+        drop(target);
+
+        scene_target.resolve(display);
+        // Order independent transparency works on the resolved scene, so it is not multisampled
+        if self.transparency == Transparency::WeightedBlended && !transparent.is_empty() {
+            self.weighted_blended.prepare(display, target_size);
+            for pass in [OitPass::Accumulation, OitPass::Revealage] {
+                let mut surface = self.weighted_blended.framebuffer(display, pass, &scene_target.depth);
+                self.passes.draw_transparent(&mut surface, &transparent, Some(pass), &frame_view, occlusion);
+            }
+            self.weighted_blended.composite(&mut glium::framebuffer::SimpleFrameBuffer::new(display, &scene_target.color).unwrap());
+        }
+        let color = self.bloom.run(display, &scene_target.color);
+        let color = self.post_processing.run(display, color, &scene_target.depth);
+        let color = self.tone_mapper.run(display, color, elapsed);
+
+        if capturing {
+            let output = self.capture.texture(display, target_size);
+            self.presenter.present(&mut output.as_surface(), color, self.anti_aliasing);
+            // Labels are part of the picture, unlike the HUD
+            self.labels.draw(display, &mut output.as_surface(), &self.passes, &frame_view, target_size, capture_scale as f32);
+            self.capture.save();
+        }
+        self.presenter.present(&mut frame, color, self.anti_aliasing);
+        self.labels.draw(display, &mut frame, &self.passes, &frame_view, window_size, 1.0);
+        // The HUD shows the statistics up to this frame, it and swapping buffers are left out of the times
+        self.frame_stats.end_frame(display);
+        self.hud.draw(display, &mut frame, &self.frame_stats);
+        frame.finish().unwrap();
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use crate::math;
+use crate::mesh::Mesh;
+
+#[derive(Copy, Clone, Debug, Default)]
+pub struct WindingReport {
+    // Groups of triangles connected through shared edges
+    pub components: usize,
+    pub flipped_triangles: usize,
+    // Edges shared by more than two triangles, orientation is not carried across them
+    pub non_manifold_edges: usize,
+    // Edges both triangles still run the same way, only left on surfaces that cannot be oriented like a Moebius strip
+    pub inconsistent_edges: usize,
+}
+
+impl fmt::Display for WindingReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Winding: {} components, flipped {} triangles, {} non-manifold edges, {} edges left inconsistent",
+               self.components, self.flipped_triangles, self.non_manifold_edges, self.inconsistent_edges)
+    }
+}
+
+// Makes neighbouring triangles agree on their winding, then turns each connected piece to face outwards:
+// closed pieces by the sign of their volume, open ones by agreeing with the vertex normals, or when there are
+// none by whichever way most of their triangles already faced.
+// Triangles are connected through positions rather than indices, so seams split by normals or texture coordinates still count.
+pub fn fix_winding(mesh: &Mesh) -> (Mesh, WindingReport) {
+    let mut position_ids: HashMap<[u32; 3], u32> = HashMap::new();
+    let ids: Vec<u32> = mesh.vertices.iter().map(|vertex| {
+        let next = position_ids.len() as u32;
+        *position_ids.entry(vertex.position.map(f32::to_bits)).or_insert(next)
+    }).collect();
+
+    // For each edge (smaller id first) the triangles using it and whether they run from the smaller id to the larger
+    let triangles: Vec<[u32; 3]> = mesh.indices.chunks_exact(3).map(|triangle| [triangle[0], triangle[1], triangle[2]]).collect();
+    let mut edges: HashMap<(u32, u32), Vec<(usize, bool)>> = HashMap::new();
+    let triangle_edges = |triangle: &[u32; 3]| [0, 1, 2].map(|i| (ids[triangle[i] as usize], ids[triangle[(i + 1) % 3] as usize]));
+    for (t, triangle) in triangles.iter().enumerate() {
+        for (a, b) in triangle_edges(triangle) {
+            if a != b {
+                edges.entry((a.min(b), a.max(b))).or_default().push((t, a < b));
+            }
+        }
+    }
+
+    let mut report = WindingReport::default();
+    let mut flips: Vec<Option<bool>> = vec![None; triangles.len()];
+    for start in 0..triangles.len() {
+        if flips[start].is_some() {
+            continue;
+        }
+        report.components += 1;
+
+        // Breadth first walk over manifold edges, a neighbour has to run the shared edge the other way
+        let mut component = vec![start];
+        let mut closed = true;
+        flips[start] = Some(false);
+        let mut queue = VecDeque::from([start]);
+        while let Some(t) = queue.pop_front() {
+            for (a, b) in triangle_edges(&triangles[t]) {
+                let Some(users) = edges.get(&(a.min(b), a.max(b))) else {
+                    continue;
+                };
+                if users.len() != 2 {
+                    closed = false;
+                    continue;
+                }
+                let forward = (a < b) != flips[t].unwrap();
+                for &(other, other_direction) in users {
+                    if other != t && flips[other].is_none() {
+                        flips[other] = Some(other_direction == forward);
+                        component.push(other);
+                        queue.push_back(other);
+                    }
+                }
+            }
+        }
+
+        // Positive when the component as currently flipped faces the right way
+        let corners = |t: usize| triangles[t].map(|index| mesh.vertices[index as usize]);
+        let sign = |t: usize| if flips[t].unwrap() { -1.0 } else { 1.0 };
+        let score: f32 = if closed {
+            component.iter().map(|&t| {
+                let [a, b, c] = corners(t);
+                sign(t) * math::dot(a.position, math::cross(b.position, c.position))
+            }).sum()
+        } else {
+            component.iter().map(|&t| {
+                let [a, b, c] = corners(t);
+                let face_normal = math::cross(math::sub(b.position, a.position), math::sub(c.position, a.position));
+                sign(t) * math::dot(face_normal, math::add(math::add(a.normal, b.normal), c.normal))
+            }).sum()
+        };
+        let flipped = component.iter().filter(|&&t| flips[t] == Some(true)).count();
+        if score < 0.0 || (score == 0.0 && flipped * 2 > component.len()) {
+            for &t in &component {
+                flips[t] = flips[t].map(|flip| !flip);
+            }
+        }
+    }
+
+    for users in edges.values() {
+        match users.as_slice() {
+            [(first, first_direction), (second, second_direction)]
+                if (*first_direction != flips[*first].unwrap()) == (*second_direction != flips[*second].unwrap()) => {
+                report.inconsistent_edges += 1;
+            }
+            users if users.len() > 2 => report.non_manifold_edges += 1,
+            _ => (),
+        }
+    }
+
+    let mut result = mesh.clone();
+    for (triangle, flip) in result.indices.chunks_exact_mut(3).zip(flips) {
+        if flip == Some(true) {
+            triangle.swap(1, 2);
+            report.flipped_triangles += 1;
+        }
+    }
+    (result, report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::primitives;
+
+    fn signed_volume(mesh: &Mesh) -> f32 {
+        mesh.indices.chunks_exact(3).map(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize].position);
+            math::dot(a, math::cross(b, c)) / 6.0
+        }).sum()
+    }
+
+    #[test]
+    fn a_face_against_its_neighbours_is_flipped_back() {
+        let cube = primitives::cube(1.0, 2);
+        let mut broken = cube.clone();
+        broken.indices.swap(4, 5);
+
+        let (fixed, report) = fix_winding(&broken);
+        assert_eq!(report.components, 1);
+        assert_eq!(report.flipped_triangles, 1);
+        assert_eq!(report.inconsistent_edges, 0);
+        // Only the order within the triangle changes, its corners start from the same vertex
+        assert_eq!(fixed.indices, cube.indices);
+    }
+
+    #[test]
+    fn an_inside_out_closed_mesh_is_turned_around() {
+        let cube = primitives::cube(1.0, 1);
+        let mut inside_out = cube.clone();
+        for triangle in inside_out.indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+        assert!(signed_volume(&inside_out) < 0.0);
+
+        let (fixed, report) = fix_winding(&inside_out);
+        assert_eq!(report.flipped_triangles, cube.triangle_count());
+        assert!(signed_volume(&fixed) > 0.0);
+        assert_eq!(fixed.indices, cube.indices);
+    }
+
+    #[test]
+    fn a_consistent_mesh_is_left_alone() {
+        let cube = primitives::cube(1.0, 1);
+        let (fixed, report) = fix_winding(&cube);
+        assert_eq!(report.flipped_triangles, 0);
+        assert_eq!(fixed.indices, cube.indices);
+    }
+}